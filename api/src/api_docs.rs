@@ -1,7 +1,8 @@
 use crate::handlers::{
-    admin_score_upload, cluster_stats, commissions, config, docs, events, global_unstake_hints,
-    glossary, jito, jito_mev, list_validators, reports_commission_changes, reports_scoring,
-    reports_scoring_html, reports_staking, rewards, unstake_hints, uptimes,
+    admin_score_upload, cluster_stats, commissions, config, decentralization_history, docs, events,
+    global_unstake_hints, glossary, health_freshness, health_live, health_ready, jito, jito_mev,
+    list_validators, operators, reports_commission_changes, reports_scoring, reports_scoring_html,
+    reports_staking, rewards, snapshots, unstake_hints, uptimes, validator_operator,
     validator_score_breakdown, validator_score_breakdowns, validator_scores,
     validators_block_rewards, validators_flat, versions, workflow_metrics_upload,
 };
@@ -19,15 +20,21 @@ use utoipa::OpenApi;
     ),
     components(
         schemas(admin_score_upload::ResponseAdminScoreUpload),
+        schemas(crate::cache::CacheSection),
         schemas(cluster_stats::ResponseClusterStats),
+        schemas(decentralization_history::ResponseDecentralizationHistory),
         schemas(commissions::ResponseCommissions),
         schemas(config::ConfigStakes),
         schemas(config::ResponseConfig),
         schemas(config::StakeDelegationAuthorityRecord),
         schemas(global_unstake_hints::ResponseGlobalUnstakeHints),
+        schemas(health_freshness::ResponseFreshness),
+        schemas(health_freshness::SectionFreshness),
+        schemas(health_ready::ResponseReady),
         schemas(list_validators::OrderDirection),
         schemas(list_validators::OrderField),
         schemas(list_validators::ResponseValidators),
+        schemas(operators::ResponseOperators),
         schemas(reports_commission_changes::CommissionChange),
         schemas(reports_commission_changes::ResponseCommissionChanges),
         schemas(reports_scoring::ResponseReportScoring),
@@ -35,10 +42,15 @@ use utoipa::OpenApi;
         schemas(reports_staking::Stake),
         schemas(rewards::ResponseRewards),
         schemas(store::dto::BlockProductionStats),
+        schemas(store::dto::ClientDiversityStats),
         schemas(store::dto::ClusterStats),
+        schemas(store::dto::ConcentrationMetrics),
         schemas(store::dto::CommissionRecord),
         schemas(store::dto::DCConcentrationStats),
+        schemas(store::dto::DecentralizationStats),
         schemas(store::dto::GlobalUnstakeHintRecord),
+        schemas(store::dto::OperatorLinkRecord),
+        schemas(store::dto::OperatorRecord),
         schemas(store::dto::UnstakeHintRecord),
         schemas(store::dto::UnstakeHint),
         schemas(store::dto::UptimeRecord),
@@ -60,6 +72,7 @@ use utoipa::OpenApi;
         schemas(unstake_hints::ResponseUnstakeHints),
         schemas(uptimes::ResponseUptimes),
         schemas(events::ResponseEvents),
+        schemas(validator_operator::ResponseValidatorOperator),
         schemas(validator_score_breakdown::ResponseScoreBreakdown),
         schemas(validator_score_breakdown::ScoreBreakdown),
         schemas(validator_score_breakdowns::ResponseScoreBreakdowns),
@@ -75,18 +88,25 @@ use utoipa::OpenApi;
         cluster_stats::handler,
         commissions::handler,
         config::handler,
+        decentralization_history::handler,
         docs::handler,
         glossary::handler,
+        health_freshness::handler,
+        health_live::handler,
+        health_ready::handler,
         list_validators::handler,
+        operators::handler,
         reports_commission_changes::handler,
         reports_scoring_html::handler,
         reports_scoring::handler,
         reports_staking::handler,
         rewards::handler,
+        snapshots::handler,
         unstake_hints::handler,
         global_unstake_hints::handler,
         uptimes::handler,
         events::handler,
+        validator_operator::handler,
         validator_score_breakdown::handler,
         validator_score_breakdowns::handler,
         validator_scores::handler,
@@ -1,32 +1,45 @@
+use crate::cache_file;
 use crate::context::WrappedContext;
-use log::{error, info};
+use crate::db;
+use crate::metrics;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
 use rust_decimal::Decimal;
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use store::dto::{
     ClusterStats, CommissionRecord, ScoringRunRecord, UptimeRecord, ValidatorRecord,
     ValidatorScoreRecord, ValidatorsAggregated, VersionRecord,
 };
+use store::notifications::{StoreNotification, STORE_CHANNEL};
+use store::watermarks::Watermarks;
+use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration, Instant};
+use tokio_postgres::Client;
 
 pub(crate) use store::utils::DEFAULT_CACHE_EPOCHS;
 pub(crate) const DEFAULT_COMPUTING_EPOCHS: u64 = 20;
+const CACHE_POLL_TIME_S: u64 = 10;
 const CACHE_WARMUP_TIME_S: u64 = 10 * 60;
+const BIGQUERY_CHECK_TIME_S: u64 = 10 * 60;
+const LISTENER_RECONNECT_TIME_S: u64 = 30;
 
-type CachedValidators = HashMap<String, ValidatorRecord>;
-type CachedCommissions = HashMap<String, Vec<CommissionRecord>>;
-type CachedVersions = HashMap<String, Vec<VersionRecord>>;
-type CachedUptimes = HashMap<String, Vec<UptimeRecord>>;
-type CachedClusterStats = Option<ClusterStats>;
-type CachedValidatorsAggregated = Vec<ValidatorsAggregated>;
+static REFRESH_LOCK: Mutex<()> = Mutex::const_new(());
 
-#[derive(Default, Clone)]
+pub(crate) type CachedValidators = HashMap<String, ValidatorRecord>;
+pub(crate) type CachedCommissions = HashMap<String, Vec<CommissionRecord>>;
+pub(crate) type CachedVersions = HashMap<String, Vec<VersionRecord>>;
+pub(crate) type CachedUptimes = HashMap<String, Vec<UptimeRecord>>;
+pub(crate) type CachedClusterStats = Option<ClusterStats>;
+pub(crate) type CachedValidatorsAggregated = Vec<ValidatorsAggregated>;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct CachedSingleRunScores {
     pub scoring_run: Option<ScoringRunRecord>,
     pub scores: HashMap<String, ValidatorScoreRecord>,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct CachedMultiRunScores {
     pub scoring_runs: Option<Vec<ScoringRunRecord>>,
     pub scores: HashMap<Decimal, Vec<ValidatorScoreRecord>>,
@@ -43,10 +56,130 @@ pub struct Cache {
     pub validators_single_run_scores: CachedSingleRunScores,
     pub validators_multi_run_scores: CachedMultiRunScores,
     pub per_epoch: Option<PerEpochCache>,
+    pub per_epoch_checked_at: Option<Instant>,
+    /// Watermarks of the tables each section was loaded from
+    pub watermarks: HashMap<CacheSection, Watermarks>,
+    /// Whether changes are announced to the cache, otherwise it checks for them often
+    pub listening: bool,
+    /// Sections restored from the cache file, stale until they are reloaded from the database
+    pub restored: HashSet<CacheSection>,
+    /// When the restored cache file was saved
+    pub restored_at: Option<DateTime<Utc>>,
+    /// How the last checks and reloads of each section went
+    pub warm_stats: HashMap<CacheSection, SectionWarmStats>,
+}
+
+/// How the last checks and reloads of a cache section went.
+#[derive(Debug, Clone, Default)]
+pub struct SectionWarmStats {
+    /// When the section was last reloaded from the database
+    pub warmed_at: Option<DateTime<Utc>>,
+    pub warm_duration: Option<Duration>,
+    /// When its tables were last checked for changes, whether they changed or not
+    pub checked_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+/// Parts of the cache that are loaded separately, each from its own tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheSection {
+    Scores,
+    Versions,
+    Commissions,
+    Uptimes,
+    ClusterStats,
+    Validators,
+}
+
+impl CacheSection {
+    pub const ALL: [CacheSection; 6] = [
+        CacheSection::Scores,
+        CacheSection::Versions,
+        CacheSection::Commissions,
+        CacheSection::Uptimes,
+        CacheSection::ClusterStats,
+        CacheSection::Validators,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CacheSection::Scores => "scores",
+            CacheSection::Versions => "versions",
+            CacheSection::Commissions => "commissions",
+            CacheSection::Uptimes => "uptimes",
+            CacheSection::ClusterStats => "cluster_stats",
+            CacheSection::Validators => "validators",
+        }
+    }
+
+    /// The sections loaded from the table a notification announced changes of.
+    pub fn of_dataset(dataset: &str) -> Vec<CacheSection> {
+        match dataset {
+            "scoring_runs" | "scores" => vec![CacheSection::Scores, CacheSection::Validators],
+            "versions" => vec![CacheSection::Versions],
+            "commissions" => vec![CacheSection::Commissions],
+            "uptimes" => vec![CacheSection::Uptimes, CacheSection::Validators],
+            "validator_operators" | "operator_links" => vec![CacheSection::ClusterStats],
+            "mev" | "jito_priority_fee" => vec![CacheSection::Validators],
+            "validators" | "cluster_info" | "epochs" => vec![
+                CacheSection::Versions,
+                CacheSection::Commissions,
+                CacheSection::Uptimes,
+                CacheSection::ClusterStats,
+                CacheSection::Validators,
+            ],
+            _ => vec![],
+        }
+    }
+
+    /// The watermarks of the tables the section is loaded from.
+    fn watermarks(&self, watermarks: &Watermarks) -> Watermarks {
+        match self {
+            CacheSection::Scores => Watermarks {
+                last_scoring_run_id: watermarks.last_scoring_run_id,
+                scoring_runs: watermarks.scoring_runs,
+                ..Default::default()
+            },
+            CacheSection::Versions => Watermarks {
+                last_epoch: watermarks.last_epoch,
+                last_version_id: watermarks.last_version_id,
+                ..Default::default()
+            },
+            CacheSection::Commissions => Watermarks {
+                last_epoch: watermarks.last_epoch,
+                last_closed_epoch: watermarks.last_closed_epoch,
+                validators_updated_at: watermarks.validators_updated_at,
+                last_commission_id: watermarks.last_commission_id,
+                ..Default::default()
+            },
+            CacheSection::Uptimes => Watermarks {
+                last_epoch: watermarks.last_epoch,
+                last_closed_epoch: watermarks.last_closed_epoch,
+                last_uptime_id: watermarks.last_uptime_id,
+                uptimes_end_at: watermarks.uptimes_end_at,
+                ..Default::default()
+            },
+            CacheSection::ClusterStats => Watermarks {
+                last_epoch: watermarks.last_epoch,
+                last_closed_epoch: watermarks.last_closed_epoch,
+                validators_updated_at: watermarks.validators_updated_at,
+                operators_updated_at: watermarks.operators_updated_at,
+                ..Default::default()
+            },
+            CacheSection::Validators => Watermarks {
+                operators_updated_at: None,
+                last_commission_id: None,
+                last_version_id: None,
+                ..watermarks.clone()
+            },
+        }
+    }
 }
 
 /// BigQuery-sourced validator data, cached and refreshed only when a new epoch lands in BigQuery.
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct PerEpochCache {
     pub epoch: u64,
     pub unique_delegators: HashMap<String, u64>,
@@ -101,6 +234,53 @@ impl Cache {
         }
     }
 
+    /// Whether the section holds data, loaded from the database or restored from the cache file.
+    pub fn is_loaded(&self, section: CacheSection) -> bool {
+        self.watermarks.contains_key(&section) || self.restored.contains(&section)
+    }
+
+    /// Whether the section still holds the data restored from the cache file.
+    pub fn is_stale(&self, section: CacheSection) -> bool {
+        !self.watermarks.contains_key(&section) && self.restored.contains(&section)
+    }
+
+    /// Whether every section holds data, possibly stale, so that the API can serve requests.
+    pub fn is_ready(&self) -> bool {
+        CacheSection::ALL
+            .into_iter()
+            .all(|section| self.is_loaded(section))
+    }
+
+    /// Records a successful check of the section, `reloaded_in` is set when its data was reloaded.
+    fn record_check(&mut self, section: CacheSection, reloaded_in: Option<Duration>) {
+        let now = Utc::now();
+        let stats = self.warm_stats.entry(section).or_default();
+        stats.checked_at = Some(now);
+        if reloaded_in.is_some() {
+            stats.warmed_at = Some(now);
+            stats.warm_duration = reloaded_in;
+            metrics::CACHE_LAST_WARM
+                .with_label_values(&[section.name()])
+                .set(now.timestamp());
+        }
+        metrics::CACHE_LAST_CHECK
+            .with_label_values(&[section.name()])
+            .set(now.timestamp());
+        metrics::CACHE_WARM_FAILED
+            .with_label_values(&[section.name()])
+            .set(0);
+    }
+
+    /// Records a failed check or reload of the section, the last error is kept until the next failure.
+    fn record_error(&mut self, section: CacheSection, err: &anyhow::Error) {
+        let stats = self.warm_stats.entry(section).or_default();
+        stats.last_error = Some(err.to_string());
+        stats.failed_at = Some(Utc::now());
+        metrics::CACHE_WARM_FAILED
+            .with_label_values(&[section.name()])
+            .set(1);
+    }
+
     pub fn get_validators(&self) -> CachedValidators {
         self.validators.clone()
     }
@@ -149,17 +329,94 @@ impl Cache {
                     .take(epochs)
                     .cloned()
                     .collect(),
+                client_diversity_stats: cluster_stats
+                    .client_diversity_stats
+                    .iter()
+                    .take(epochs)
+                    .cloned()
+                    .collect(),
+                decentralization_stats: cluster_stats
+                    .decentralization_stats
+                    .iter()
+                    .take(epochs)
+                    .cloned()
+                    .collect(),
             })
     }
 }
 
-pub async fn warm_validators_cache(context: &WrappedContext) -> anyhow::Result<()> {
-    info!("Loading validators from DB");
-    let warmup_timer = Instant::now();
+/// The watermarks of the section to store once it is reloaded and those it was loaded with,
+/// `None` when its tables did not change since.
+async fn section_watermarks(
+    context: &WrappedContext,
+    section: CacheSection,
+    watermarks: &Watermarks,
+) -> Option<(Watermarks, Option<Watermarks>)> {
+    let watermarks = section.watermarks(watermarks);
+    let cached = context.read().await.cache.watermarks.get(&section).cloned();
+
+    (cached.as_ref() != Some(&watermarks)).then_some((watermarks, cached))
+}
+
+/// The id after which rows were only appended to the table since `cached`, all other watermarks being the same.
+fn appended_after(
+    cached: Option<&Watermarks>,
+    watermarks: &Watermarks,
+    last_id: fn(&Watermarks) -> Option<i64>,
+    without_last_id: fn(&Watermarks) -> Watermarks,
+) -> Option<i64> {
+    let cached = cached?;
+    let after_id = last_id(cached)?;
+
+    (last_id(watermarks) > Some(after_id) && without_last_id(cached) == without_last_id(watermarks))
+        .then_some(after_id)
+}
+
+fn merge_records<T>(
+    cached: &mut HashMap<String, Vec<T>>,
+    records: HashMap<String, Vec<T>>,
+) -> usize {
+    let mut merged = 0;
+    for (vote_account, records) in records {
+        merged += records.len();
+        cached.entry(vote_account).or_default().extend(records);
+    }
+    merged
+}
+
+pub async fn warm_validators_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    watermarks: &Watermarks,
+) -> anyhow::Result<bool> {
+    let section_watermarks =
+        section_watermarks(context, CacheSection::Validators, watermarks).await;
+    let per_epoch_due = context
+        .read()
+        .await
+        .cache
+        .per_epoch_checked_at
+        .is_none_or(|checked_at| {
+            checked_at.elapsed() >= Duration::from_secs(BIGQUERY_CHECK_TIME_S)
+        });
+    if section_watermarks.is_none() && !per_epoch_due {
+        return Ok(false);
+    }
 
     let cached = context.read().await.cache.per_epoch.clone();
+    let refreshed = if per_epoch_due {
+        let refreshed = PerEpochCache::load(&cached).await;
+        context.write().await.cache.per_epoch_checked_at = Some(Instant::now());
+        refreshed
+    } else {
+        None
+    };
+    if section_watermarks.is_none() && refreshed.is_none() {
+        return Ok(false);
+    }
 
-    let refreshed = PerEpochCache::load(&cached).await;
+    info!("Loading validators from DB");
+    let warmup_timer = Instant::now();
     let (unique_delegators, take_rates) = refreshed
         .as_ref()
         .or(cached.as_ref())
@@ -167,7 +424,7 @@ pub async fn warm_validators_cache(context: &WrappedContext) -> anyhow::Result<(
         .unwrap_or_default();
 
     let validators = store::utils::load_validators(
-        &context.read().await.psql_client,
+        psql_client,
         context.read().await.scoring_url.clone(),
         DEFAULT_CACHE_EPOCHS,
         DEFAULT_COMPUTING_EPOCHS,
@@ -183,6 +440,10 @@ pub async fn warm_validators_cache(context: &WrappedContext) -> anyhow::Result<(
         }
         ctx.cache.validators.clone_from(&validators);
         ctx.cache.validators_aggregated = store::utils::aggregate_validators(&validators);
+        ctx.cache.watermarks.insert(
+            CacheSection::Validators,
+            CacheSection::Validators.watermarks(watermarks),
+        );
     }
 
     info!(
@@ -191,169 +452,443 @@ pub async fn warm_validators_cache(context: &WrappedContext) -> anyhow::Result<(
         warmup_timer.elapsed().as_millis()
     );
 
-    Ok(())
+    Ok(true)
 }
-pub async fn warm_commissions_cache(context: &WrappedContext) -> anyhow::Result<()> {
-    info!("Loading commissions from DB");
+pub async fn warm_commissions_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    watermarks: &Watermarks,
+) -> anyhow::Result<bool> {
+    let Some((watermarks, cached)) =
+        section_watermarks(context, CacheSection::Commissions, watermarks).await
+    else {
+        return Ok(false);
+    };
     let warmup_timer = Instant::now();
-    let commissions =
-        store::utils::load_commissions(&context.read().await.psql_client, DEFAULT_CACHE_EPOCHS)
+
+    match appended_after(
+        cached.as_ref(),
+        &watermarks,
+        |w| w.last_commission_id,
+        |w| Watermarks {
+            last_commission_id: None,
+            ..w.clone()
+        },
+    ) {
+        Some(after_id) => {
+            info!("Loading new commissions from DB");
+            let commissions = store::utils::load_commissions(
+                psql_client,
+                DEFAULT_CACHE_EPOCHS,
+                Some(after_id),
+                watermarks.last_commission_id,
+            )
             .await?;
 
-    context
-        .write()
-        .await
-        .cache
-        .commissions
-        .clone_from(&commissions);
-    info!(
-        "Loaded {} commissions to cache in {} ms",
-        commissions.len(),
-        warmup_timer.elapsed().as_millis()
-    );
+            let mut ctx = context.write().await;
+            let merged = merge_records(&mut ctx.cache.commissions, commissions);
+            ctx.cache
+                .watermarks
+                .insert(CacheSection::Commissions, watermarks);
+            info!(
+                "Merged {} new commissions to cache in {} ms",
+                merged,
+                warmup_timer.elapsed().as_millis()
+            );
+        }
+        None => {
+            info!("Loading commissions from DB");
+            let commissions = store::utils::load_commissions(
+                psql_client,
+                DEFAULT_CACHE_EPOCHS,
+                None,
+                watermarks.last_commission_id,
+            )
+            .await?;
 
-    Ok(())
+            let mut ctx = context.write().await;
+            ctx.cache.commissions.clone_from(&commissions);
+            ctx.cache
+                .watermarks
+                .insert(CacheSection::Commissions, watermarks);
+            info!(
+                "Loaded {} commissions to cache in {} ms",
+                commissions.len(),
+                warmup_timer.elapsed().as_millis()
+            );
+        }
+    }
+
+    Ok(true)
 }
-pub async fn warm_versions_cache(context: &WrappedContext) -> anyhow::Result<()> {
-    info!("Loading versions from DB");
+pub async fn warm_versions_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    watermarks: &Watermarks,
+) -> anyhow::Result<bool> {
+    let Some((watermarks, cached)) =
+        section_watermarks(context, CacheSection::Versions, watermarks).await
+    else {
+        return Ok(false);
+    };
     let warmup_timer = Instant::now();
-    let versions =
-        store::utils::load_versions(&context.read().await.psql_client, DEFAULT_CACHE_EPOCHS)
+
+    match appended_after(
+        cached.as_ref(),
+        &watermarks,
+        |w| w.last_version_id,
+        |w| Watermarks {
+            last_version_id: None,
+            ..w.clone()
+        },
+    ) {
+        Some(after_id) => {
+            info!("Loading new versions from DB");
+            let versions = store::utils::load_versions(
+                psql_client,
+                DEFAULT_CACHE_EPOCHS,
+                Some(after_id),
+                watermarks.last_version_id,
+            )
             .await?;
 
-    context.write().await.cache.versions.clone_from(&versions);
-    info!(
-        "Loaded {} versions to cache in {} ms",
-        versions.len(),
-        warmup_timer.elapsed().as_millis()
-    );
+            let mut ctx = context.write().await;
+            let merged = merge_records(&mut ctx.cache.versions, versions);
+            ctx.cache
+                .watermarks
+                .insert(CacheSection::Versions, watermarks);
+            info!(
+                "Merged {} new versions to cache in {} ms",
+                merged,
+                warmup_timer.elapsed().as_millis()
+            );
+        }
+        None => {
+            info!("Loading versions from DB");
+            let versions = store::utils::load_versions(
+                psql_client,
+                DEFAULT_CACHE_EPOCHS,
+                None,
+                watermarks.last_version_id,
+            )
+            .await?;
 
-    Ok(())
+            let mut ctx = context.write().await;
+            ctx.cache.versions.clone_from(&versions);
+            ctx.cache
+                .watermarks
+                .insert(CacheSection::Versions, watermarks);
+            info!(
+                "Loaded {} versions to cache in {} ms",
+                versions.len(),
+                warmup_timer.elapsed().as_millis()
+            );
+        }
+    }
+
+    Ok(true)
 }
-pub async fn warm_uptimes_cache(context: &WrappedContext) -> anyhow::Result<()> {
+pub async fn warm_uptimes_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    watermarks: &Watermarks,
+) -> anyhow::Result<bool> {
+    // uptimes are extended in place, so they are reloaded whole
+    let Some((watermarks, _)) =
+        section_watermarks(context, CacheSection::Uptimes, watermarks).await
+    else {
+        return Ok(false);
+    };
     info!("Loading uptimes from DB");
     let warmup_timer = Instant::now();
-    let uptimes =
-        store::utils::load_uptimes(&context.read().await.psql_client, DEFAULT_CACHE_EPOCHS).await?;
+    let uptimes = store::utils::load_uptimes(psql_client, DEFAULT_CACHE_EPOCHS).await?;
 
-    context.write().await.cache.uptimes.clone_from(&uptimes);
+    let mut ctx = context.write().await;
+    ctx.cache.uptimes.clone_from(&uptimes);
+    ctx.cache
+        .watermarks
+        .insert(CacheSection::Uptimes, watermarks);
     info!(
         "Loaded {} uptimes to cache in {} ms",
         uptimes.len(),
         warmup_timer.elapsed().as_millis()
     );
 
-    Ok(())
+    Ok(true)
 }
-pub async fn warm_cluster_stats_cache(context: &WrappedContext) -> anyhow::Result<()> {
+pub async fn warm_cluster_stats_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    watermarks: &Watermarks,
+) -> anyhow::Result<bool> {
+    let Some((watermarks, _)) =
+        section_watermarks(context, CacheSection::ClusterStats, watermarks).await
+    else {
+        return Ok(false);
+    };
     info!("Loading cluster_stats from DB");
     let warmup_timer = Instant::now();
-    let cluster_stats =
-        store::utils::load_cluster_stats(&context.read().await.psql_client, DEFAULT_CACHE_EPOCHS)
-            .await?;
+    let cluster_stats = store::utils::load_cluster_stats(psql_client, DEFAULT_CACHE_EPOCHS).await?;
 
-    context.write().await.cache.cluster_stats = Some(cluster_stats);
+    let mut ctx = context.write().await;
+    ctx.cache.cluster_stats = Some(cluster_stats);
+    ctx.cache
+        .watermarks
+        .insert(CacheSection::ClusterStats, watermarks);
     info!(
         "Loaded cluster_stats to cache in {} ms",
         warmup_timer.elapsed().as_millis()
     );
 
-    Ok(())
+    Ok(true)
 }
-pub async fn warm_scores_cache(context: &WrappedContext) -> anyhow::Result<()> {
+pub async fn warm_scores_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    watermarks: &Watermarks,
+) -> anyhow::Result<bool> {
+    let Some((watermarks, cached)) =
+        section_watermarks(context, CacheSection::Scores, watermarks).await
+    else {
+        return Ok(false);
+    };
     info!("Loading scores from DB");
     let warmup_timer = Instant::now();
 
-    let last_scoring_run =
-        store::utils::load_last_scoring_run(&context.read().await.psql_client).await?;
+    let scoring_runs = store::scoring::load_scoring_runs(psql_client).await?;
+    let CachedMultiRunScores {
+        scoring_runs: cached_scoring_runs,
+        scores: cached_scores,
+    } = context.read().await.cache.get_validators_multi_run_scores();
+
+    // Scores of new runs are added to the cached ones unless some cached runs were removed
+    let after_scoring_run_id = cached
+        .as_ref()
+        .and_then(|cached| cached.last_scoring_run_id)
+        .filter(|after_scoring_run_id| {
+            let cached_runs = cached_scoring_runs.as_ref().map_or(0, |runs| runs.len());
+            let new_runs = scoring_runs
+                .iter()
+                .filter(|run| run.scoring_run_id > Decimal::from(*after_scoring_run_id))
+                .count();
+            cached_runs > 0 && cached_runs + new_runs == scoring_runs.len()
+        });
+    let multi_run_scores = match after_scoring_run_id {
+        Some(after_scoring_run_id) => {
+            let mut scores = cached_scores;
+            // a run stored since the watermarks were loaded is replaced, not duplicated, on the next refresh
+            scores.extend(
+                store::scoring::load_all_scores(psql_client, Some(after_scoring_run_id)).await?,
+            );
+            scores
+        }
+        None => store::scoring::load_all_scores(psql_client, None).await?,
+    };
+
+    // the runs are sorted from the last one
+    let last_scoring_run = scoring_runs.first().cloned();
+    let cached_single_run_scores = context
+        .read()
+        .await
+        .cache
+        .get_validators_single_run_scores();
     let scores = match &last_scoring_run {
+        Some(scoring_run)
+            if cached_single_run_scores
+                .scoring_run
+                .as_ref()
+                .map(|cached| cached.scoring_run_id)
+                == Some(scoring_run.scoring_run_id) =>
+        {
+            cached_single_run_scores.scores
+        }
         Some(scoring_run) => {
-            store::utils::load_scores(
-                &context.read().await.psql_client,
-                scoring_run.scoring_run_id,
-            )
-            .await?
+            store::utils::load_scores(psql_client, scoring_run.scoring_run_id).await?
         }
         None => Default::default(),
     };
-    let multi_run_scores =
-        store::scoring::load_all_scores(&context.read().await.psql_client).await?;
-
-    let last_scoring_run =
-        store::utils::load_last_scoring_run(&context.read().await.psql_client).await?;
-
-    let multi_run_scoring_runs =
-        store::scoring::load_scoring_runs(&context.read().await.psql_client).await?;
 
     let scores_len = scores.len();
     let multi_run_scores_len: usize = multi_run_scores.values().map(|v| v.len()).sum();
 
-    context
-        .write()
-        .await
-        .cache
+    let mut ctx = context.write().await;
+    ctx.cache
         .validators_single_run_scores
         .clone_from(&CachedSingleRunScores {
             scoring_run: last_scoring_run,
             scores,
         });
-    info!(
-        "Loaded {} single run scores to cache in {} ms",
-        scores_len,
-        warmup_timer.elapsed().as_millis()
-    );
-
-    context
-        .write()
-        .await
-        .cache
+    ctx.cache
         .validators_multi_run_scores
         .clone_from(&CachedMultiRunScores {
-            scoring_runs: Some(multi_run_scoring_runs),
+            scoring_runs: Some(scoring_runs),
             scores: multi_run_scores,
         });
+    ctx.cache
+        .watermarks
+        .insert(CacheSection::Scores, watermarks);
     info!(
-        "Loaded {} multiple run scores to cache in {} ms",
+        "Loaded {} single run scores and {} multiple run scores{} to cache in {} ms",
+        scores_len,
         multi_run_scores_len,
+        if after_scoring_run_id.is_some() {
+            " incrementally"
+        } else {
+            ""
+        },
         warmup_timer.elapsed().as_millis()
     );
 
-    Ok(())
+    Ok(true)
 }
 
-pub fn spawn_cache_warmer(context: WrappedContext) {
-    tokio::spawn(async move {
-        loop {
-            info!("Warming up the cache");
-
-            if let Err(err) = warm_scores_cache(&context).await {
-                error!("Failed to update the scores: {err}");
+/// Reloads the given sections of the cache if their tables changed since they were loaded
+/// and saves the cache to the cache file when any of them was reloaded.
+pub async fn refresh_cache(
+    context: &WrappedContext,
+    psql_client: &Client,
+    sections: &HashSet<CacheSection>,
+) -> anyhow::Result<()> {
+    // the listener and the timer must not merge the same new rows twice
+    let _refreshing = REFRESH_LOCK.lock().await;
+    let watermarks = store::watermarks::load_watermarks(psql_client).await?;
+
+    let mut reloaded = false;
+    for section in CacheSection::ALL {
+        if !sections.contains(&section) {
+            continue;
+        }
+        let warm_timer = Instant::now();
+        let result = match section {
+            CacheSection::Scores => warm_scores_cache(context, psql_client, &watermarks).await,
+            CacheSection::Versions => warm_versions_cache(context, psql_client, &watermarks).await,
+            CacheSection::Commissions => {
+                warm_commissions_cache(context, psql_client, &watermarks).await
             }
-
-            if let Err(err) = warm_versions_cache(&context).await {
-                error!("Failed to update the versions: {err}");
+            CacheSection::Uptimes => warm_uptimes_cache(context, psql_client, &watermarks).await,
+            CacheSection::ClusterStats => {
+                warm_cluster_stats_cache(context, psql_client, &watermarks).await
             }
-
-            if let Err(err) = warm_commissions_cache(&context).await {
-                error!("Failed to update the commissions: {err}");
+            CacheSection::Validators => {
+                warm_validators_cache(context, psql_client, &watermarks).await
             }
-
-            if let Err(err) = warm_uptimes_cache(&context).await {
-                error!("Failed to update the uptimes: {err}");
+        };
+        let mut ctx = context.write().await;
+        match result {
+            Ok(section_reloaded) => {
+                ctx.cache
+                    .record_check(section, section_reloaded.then(|| warm_timer.elapsed()));
+                reloaded |= section_reloaded;
+            }
+            Err(err) => {
+                error!("Failed to update the {section:?} cache: {err}");
+                ctx.cache.record_error(section, &err);
             }
+        }
+    }
 
-            if let Err(err) = warm_cluster_stats_cache(&context).await {
-                error!("Failed to update the cluster stats: {err}");
+    if reloaded {
+        let cache_file = context.read().await.cache_file.clone();
+        if let Some(cache_file) = cache_file {
+            if let Err(err) = cache_file::save(context, &cache_file).await {
+                error!("Failed to save the cache to {cache_file}: {err}");
             }
+        }
+    }
 
-            if let Err(err) = warm_validators_cache(&context).await {
-                error!("Failed to update the validators: {err}");
+    Ok(())
+}
+
+/// Records the failure of a refresh that could not check the sections at all.
+async fn record_refresh_error(
+    context: &WrappedContext,
+    sections: &HashSet<CacheSection>,
+    err: &anyhow::Error,
+) {
+    let mut ctx = context.write().await;
+    for section in sections {
+        ctx.cache.record_error(*section, err);
+    }
+}
+
+/// Checks all sections of the cache for changes, often while no listener is connected, otherwise rarely.
+pub fn spawn_cache_warmer(context: WrappedContext) {
+    tokio::spawn(async move {
+        let sections = CacheSection::ALL.into_iter().collect();
+        loop {
+            let refreshed = match db::replica(&context).await {
+                Ok(psql_client) => refresh_cache(&context, &psql_client, &sections).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = refreshed {
+                error!("Failed to check the cache for changes: {err}");
+                record_refresh_error(&context, &sections, &err).await;
             }
 
-            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-            let run_every = Duration::from_secs(CACHE_WARMUP_TIME_S);
-            let sleep_seconds = now.as_secs() % run_every.as_secs();
-            sleep(Duration::from_secs(run_every.as_secs() - sleep_seconds)).await;
+            let poll_time_s = if context.read().await.cache.listening {
+                CACHE_WARMUP_TIME_S
+            } else {
+                CACHE_POLL_TIME_S
+            };
+            sleep(Duration::from_secs(poll_time_s)).await;
+        }
+    });
+}
+
+/// Refreshes the sections of the cache whose tables the store announced as changed.
+/// The changes are read from the primary database, a replica may not have them yet.
+pub fn spawn_cache_listener(context: WrappedContext) {
+    tokio::spawn(async move {
+        loop {
+            let db = context.read().await.db.clone();
+            match db.listen(STORE_CHANNEL).await {
+                Ok((_listening_client, mut notifications)) => {
+                    info!("Listening for changes announced on {STORE_CHANNEL}");
+                    context.write().await.cache.listening = true;
+
+                    while let Some(notification) = notifications.recv().await {
+                        // changes of one command arrive together, they are refreshed at once
+                        let mut sections = HashSet::new();
+                        let mut notification = Some(notification);
+                        while let Some(received) = notification
+                            .take()
+                            .or_else(|| notifications.try_recv().ok())
+                        {
+                            match serde_json::from_str::<StoreNotification>(received.payload()) {
+                                Ok(changed) => {
+                                    info!(
+                                        "Dataset {} changed (epoch {:?})",
+                                        changed.dataset, changed.epoch
+                                    );
+                                    sections.extend(CacheSection::of_dataset(&changed.dataset));
+                                }
+                                Err(err) => {
+                                    error!("Unexpected notification {}: {err}", received.payload())
+                                }
+                            }
+                        }
+                        if sections.is_empty() {
+                            continue;
+                        }
+
+                        let refreshed = match db.primary().await {
+                            Ok(psql_client) => {
+                                refresh_cache(&context, &psql_client, &sections).await
+                            }
+                            Err(err) => Err(err),
+                        };
+                        if let Err(err) = refreshed {
+                            error!("Failed to refresh the cache on a notification: {err}");
+                            record_refresh_error(&context, &sections, &err).await;
+                        }
+                    }
+
+                    context.write().await.cache.listening = false;
+                    warn!("Stopped listening for changes, the cache is checked on the timer");
+                }
+                Err(err) => error!("Failed to listen for changes on {STORE_CHANNEL}: {err}"),
+            }
+            sleep(Duration::from_secs(LISTENER_RECONNECT_TIME_S)).await;
         }
     });
 }
@@ -0,0 +1,141 @@
+use crate::cache::{
+    Cache, CacheSection, CachedClusterStats, CachedCommissions, CachedMultiRunScores,
+    CachedSingleRunScores, CachedUptimes, CachedValidators, CachedValidatorsAggregated,
+    CachedVersions, PerEpochCache,
+};
+use crate::context::WrappedContext;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::mem::take;
+use std::path::Path;
+use tokio::time::Instant;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// The cached data saved to the cache file, so that a restarted API serves it
+/// before the sections are reloaded from the database.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    saved_at: DateTime<Utc>,
+    /// Sections that held data when the file was saved, the others are not restored
+    sections: Vec<CacheSection>,
+    validators: CachedValidators,
+    commissions: CachedCommissions,
+    versions: CachedVersions,
+    uptimes: CachedUptimes,
+    cluster_stats: CachedClusterStats,
+    validators_aggregated: CachedValidatorsAggregated,
+    validators_single_run_scores: CachedSingleRunScores,
+    validators_multi_run_scores: CachedMultiRunScores,
+    per_epoch: Option<PerEpochCache>,
+}
+
+impl CacheFile {
+    fn from_cache(cache: &Cache) -> Self {
+        Self {
+            saved_at: Utc::now(),
+            sections: CacheSection::ALL
+                .into_iter()
+                .filter(|section| cache.is_loaded(*section))
+                .collect(),
+            validators: cache.validators.clone(),
+            commissions: cache.commissions.clone(),
+            versions: cache.versions.clone(),
+            uptimes: cache.uptimes.clone(),
+            cluster_stats: cache.cluster_stats.clone(),
+            validators_aggregated: cache.validators_aggregated.clone(),
+            validators_single_run_scores: cache.validators_single_run_scores.clone(),
+            validators_multi_run_scores: cache.validators_multi_run_scores.clone(),
+            per_epoch: cache.per_epoch.clone(),
+        }
+    }
+
+    /// Moves the saved sections to the cache, marked as restored until they are reloaded.
+    fn restore(mut self, cache: &mut Cache) {
+        for section in &self.sections {
+            match section {
+                CacheSection::Scores => {
+                    cache.validators_single_run_scores =
+                        take(&mut self.validators_single_run_scores);
+                    cache.validators_multi_run_scores = take(&mut self.validators_multi_run_scores);
+                }
+                CacheSection::Versions => cache.versions = take(&mut self.versions),
+                CacheSection::Commissions => cache.commissions = take(&mut self.commissions),
+                CacheSection::Uptimes => cache.uptimes = take(&mut self.uptimes),
+                CacheSection::ClusterStats => cache.cluster_stats = take(&mut self.cluster_stats),
+                CacheSection::Validators => {
+                    cache.validators = take(&mut self.validators);
+                    cache.validators_aggregated = take(&mut self.validators_aggregated);
+                    cache.per_epoch = take(&mut self.per_epoch);
+                }
+            }
+        }
+        cache.restored = self.sections.into_iter().collect();
+        cache.restored_at = Some(self.saved_at);
+    }
+
+    fn write(&self, path: &str) -> anyhow::Result<()> {
+        // written aside and renamed, a crash while saving must not leave a truncated file behind
+        let tmp_path = format!("{path}.tmp");
+        let mut encoder = zstd::Encoder::new(File::create(&tmp_path)?, ZSTD_LEVEL)?;
+        serde_json::to_writer(&mut encoder, self)?;
+        let mut file = encoder.finish()?;
+        file.flush()?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+
+    fn read(path: &str) -> anyhow::Result<Self> {
+        let decoder = zstd::Decoder::new(File::open(path)?)?;
+
+        Ok(serde_json::from_reader(BufReader::new(decoder))?)
+    }
+}
+
+/// Saves the cache to the file, the data is serialized without holding the context.
+pub async fn save(context: &WrappedContext, path: &str) -> anyhow::Result<()> {
+    let timer = Instant::now();
+    let cache_file = CacheFile::from_cache(&context.read().await.cache);
+    let sections = cache_file.sections.len();
+
+    let path_owned = path.to_string();
+    tokio::task::spawn_blocking(move || cache_file.write(&path_owned)).await??;
+    info!(
+        "Saved {} cache sections to {} in {} ms",
+        sections,
+        path,
+        timer.elapsed().as_millis()
+    );
+
+    Ok(())
+}
+
+/// Restores the cache saved by a previous run, if there is one.
+/// It must be called before the cache is warmed, which then replaces the restored sections.
+pub async fn restore(context: &WrappedContext, path: &str) -> anyhow::Result<()> {
+    if !Path::new(path).exists() {
+        info!("No cache file at {path}, the cache starts empty");
+        return Ok(());
+    }
+
+    let timer = Instant::now();
+    let path_owned = path.to_string();
+    let cache_file = tokio::task::spawn_blocking(move || CacheFile::read(&path_owned)).await??;
+    let saved_at = cache_file.saved_at;
+    let sections = cache_file.sections.len();
+    cache_file.restore(&mut context.write().await.cache);
+    info!(
+        "Restored {} cache sections saved at {} from {} in {} ms",
+        sections,
+        saved_at,
+        path,
+        timer.elapsed().as_millis()
+    );
+
+    Ok(())
+}
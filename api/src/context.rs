@@ -1,28 +1,31 @@
 use crate::cache::Cache;
+use crate::db::Db;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio_postgres::Client;
 
 pub struct Context {
-    pub psql_client: Client,
+    pub db: Db,
     pub glossary_path: String,
     pub blacklist_path: String,
     pub scoring_url: String,
+    pub cache_file: Option<String>,
     pub cache: Cache,
 }
 
 impl Context {
     pub fn new(
-        psql_client: Client,
+        db: Db,
         glossary_path: String,
         blacklist_path: String,
         scoring_url: String,
+        cache_file: Option<String>,
     ) -> anyhow::Result<Self> {
         Ok(Self {
-            psql_client,
+            db,
             glossary_path,
             blacklist_path,
             scoring_url,
+            cache_file,
             cache: Cache::new(),
         })
     }
@@ -0,0 +1,211 @@
+use crate::context::WrappedContext;
+use crate::metrics;
+use deadpool_postgres::{
+    Manager, ManagerConfig, Object, Pool, PoolConfig, RecyclingMethod, Runtime, Timeouts,
+};
+use log::{error, info, warn};
+use openssl::ssl::{SslConnector, SslMethod};
+use postgres_openssl::MakeTlsConnector;
+use structopt::StructOpt;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+use tokio_postgres::{AsyncMessage, Client, Notification};
+
+const HEALTH_CHECK_INTERVAL_S: u64 = 30;
+
+#[derive(Debug, StructOpt)]
+pub struct DbParams {
+    #[structopt(long = "postgres-url")]
+    postgres_url: String,
+
+    #[structopt(
+        long = "postgres-replica-url",
+        help = "Read replica the read-only queries are sent to, they fall back to the primary when it is unreachable."
+    )]
+    postgres_replica_url: Option<String>,
+
+    #[structopt(long = "postgres-ssl-root-cert", env = "PG_SSLROOTCERT")]
+    postgres_ssl_root_cert: String,
+
+    #[structopt(
+        long = "postgres-pool-size",
+        default_value = "16",
+        help = "Maximum number of connections per database."
+    )]
+    postgres_pool_size: usize,
+
+    #[structopt(
+        long = "postgres-pool-timeout-s",
+        default_value = "10",
+        help = "How long to wait for a free connection, to connect and to check a connection before reusing it."
+    )]
+    postgres_pool_timeout_s: u64,
+}
+
+/// Connection pools of the primary database and the optional read replica.
+/// Connections are checked with a query before they are reused and broken ones are replaced by new ones.
+#[derive(Clone)]
+pub struct Db {
+    primary: Pool,
+    replica: Option<Pool>,
+    primary_url: String,
+    connector: MakeTlsConnector,
+}
+
+fn create_pool(url: &str, connector: MakeTlsConnector, params: &DbParams) -> anyhow::Result<Pool> {
+    let manager = Manager::from_config(
+        url.parse()?,
+        connector,
+        ManagerConfig {
+            recycling_method: RecyclingMethod::Verified,
+        },
+    );
+    let timeout = Some(Duration::from_secs(params.postgres_pool_timeout_s));
+
+    Ok(Pool::builder(manager)
+        .config(PoolConfig {
+            max_size: params.postgres_pool_size,
+            timeouts: Timeouts {
+                wait: timeout,
+                create: timeout,
+                recycle: timeout,
+            },
+            ..Default::default()
+        })
+        .runtime(Runtime::Tokio1)
+        .build()?)
+}
+
+impl Db {
+    pub async fn connect(params: &DbParams) -> anyhow::Result<Self> {
+        let mut builder = SslConnector::builder(SslMethod::tls())?;
+        builder.set_ca_file(&params.postgres_ssl_root_cert)?;
+        let connector = MakeTlsConnector::new(builder.build());
+
+        let db = Self {
+            primary: create_pool(&params.postgres_url, connector.clone(), params)?,
+            replica: params
+                .postgres_replica_url
+                .as_ref()
+                .map(|url| create_pool(url, connector.clone(), params))
+                .transpose()?,
+            primary_url: params.postgres_url.clone(),
+            connector,
+        };
+
+        // Fail fast on a wrong URL or certificate instead of on the first request
+        let _ = db.primary().await?;
+        if let Some(replica) = &db.replica {
+            let _ = replica.get().await?;
+        }
+        info!(
+            "Connected to the database with up to {} connections{}",
+            params.postgres_pool_size,
+            if db.replica.is_some() {
+                " and to the read replica"
+            } else {
+                ""
+            }
+        );
+
+        Ok(db)
+    }
+
+    /// A connection to the primary database, for writes and for reads that must see them.
+    pub async fn primary(&self) -> anyhow::Result<Object> {
+        Ok(self.primary.get().await?)
+    }
+
+    /// A connection for read-only queries, to the replica unless it is not configured or unreachable.
+    pub async fn replica(&self) -> anyhow::Result<Object> {
+        if let Some(replica) = &self.replica {
+            match replica.get().await {
+                Ok(client) => return Ok(client),
+                Err(err) => warn!("Read replica is unavailable, using the primary: {err}"),
+            }
+        }
+        self.primary().await
+    }
+
+    /// Listens on the channel of the primary database (replicas do not get notifications) with a connection of its own.
+    /// The notifications are received until the connection drops, the returned client keeps it open.
+    pub async fn listen(
+        &self,
+        channel: &str,
+    ) -> anyhow::Result<(Client, mpsc::UnboundedReceiver<Notification>)> {
+        let (client, mut connection) =
+            tokio_postgres::connect(&self.primary_url, self.connector.clone()).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(message) = std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+                match message {
+                    Ok(AsyncMessage::Notification(notification)) => {
+                        if sender.send(notification).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        error!("Listening connection error: {err}");
+                        break;
+                    }
+                }
+            }
+        });
+        client.batch_execute(&format!("LISTEN {channel}")).await?;
+
+        Ok((client, receiver))
+    }
+
+    fn pools(&self) -> impl Iterator<Item = (&'static str, &Pool)> {
+        std::iter::once(("primary", &self.primary))
+            .chain(self.replica.iter().map(|replica| ("replica", replica)))
+    }
+}
+
+pub async fn primary(context: &WrappedContext) -> anyhow::Result<Object> {
+    let db = context.read().await.db.clone();
+    db.primary().await
+}
+
+pub async fn replica(context: &WrappedContext) -> anyhow::Result<Object> {
+    let db = context.read().await.db.clone();
+    db.replica().await
+}
+
+/// Periodically checks that every database is reachable and exports the state of the pools as metrics.
+pub fn spawn_health_check(db: Db) {
+    tokio::spawn(async move {
+        loop {
+            for (name, pool) in db.pools() {
+                let healthy = match pool.get().await {
+                    Ok(client) => match client.simple_query("SELECT 1").await {
+                        Ok(_) => true,
+                        Err(err) => {
+                            error!("Health check of the {name} database failed: {err}");
+                            false
+                        }
+                    },
+                    Err(err) => {
+                        error!("Failed to connect to the {name} database: {err}");
+                        false
+                    }
+                };
+                let status = pool.status();
+                metrics::DB_HEALTHY
+                    .with_label_values(&[name])
+                    .set(healthy as i64);
+                metrics::DB_POOL_CONNECTIONS
+                    .with_label_values(&[name, "open"])
+                    .set(status.size as i64);
+                metrics::DB_POOL_CONNECTIONS
+                    .with_label_values(&[name, "available"])
+                    .set(status.available as i64);
+                metrics::DB_POOL_CONNECTIONS
+                    .with_label_values(&[name, "waiting"])
+                    .set(status.waiting as i64);
+            }
+            sleep(Duration::from_secs(HEALTH_CHECK_INTERVAL_S)).await;
+        }
+    });
+}
@@ -1,3 +1,4 @@
+use crate::db;
 use crate::metrics;
 use crate::utils::response_error;
 use crate::{context::WrappedContext, utils::response_error_500};
@@ -106,8 +107,18 @@ pub async fn handler(
         }
     }
 
+    let mut psql_client = match db::primary(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(err) => {
+            log::error!("Failed to get a database connection: {err}");
+            return Ok(response_error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Database is unavailable!".into(),
+            ));
+        }
+    };
     let result = store::utils::store_scoring(
-        &mut context.write().await.psql_client,
+        &mut psql_client,
         query_params.epoch,
         query_params.ui_id,
         components,
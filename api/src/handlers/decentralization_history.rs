@@ -0,0 +1,63 @@
+use crate::cache::DEFAULT_CACHE_EPOCHS;
+use crate::context::WrappedContext;
+use crate::metrics;
+use crate::utils::response_error;
+use log::error;
+use serde::{Deserialize, Serialize};
+use store::dto::DecentralizationStats;
+use warp::{http::StatusCode, reply::json, Reply};
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct ResponseDecentralizationHistory {
+    decentralization_history: Vec<DecentralizationStats>,
+}
+
+#[derive(Deserialize, Serialize, Debug, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryParams {
+    /// How many of the last epochs to chart, all cached epochs by default.
+    epochs: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    tag = "General",
+    operation_id = "Show history of decentralization metrics",
+    path = "/cluster-stats/decentralization",
+    params(QueryParams),
+    responses(
+        (status = 200, body = ResponseDecentralizationHistory)
+    )
+)]
+pub async fn handler(
+    query_params: QueryParams,
+    context: WrappedContext,
+) -> Result<impl Reply, warp::Rejection> {
+    metrics::REQUEST_DECENTRALIZATION_HISTORY.inc();
+
+    log::info!("Query decentralization history {query_params:?}");
+
+    let cluster_stats = context
+        .read()
+        .await
+        .cache
+        .get_cluster_stats(query_params.epochs.unwrap_or(DEFAULT_CACHE_EPOCHS as usize));
+
+    Ok(match cluster_stats {
+        Some(cluster_stats) => {
+            // charted from the oldest epoch on
+            let mut decentralization_history = cluster_stats.decentralization_stats;
+            decentralization_history.reverse();
+            warp::reply::with_status(
+                json(&ResponseDecentralizationHistory {
+                    decentralization_history,
+                }),
+                StatusCode::OK,
+            )
+        }
+        _ => {
+            error!("No cluster stats found");
+            response_error(StatusCode::NOT_FOUND, "Failed to fetch records!".into())
+        }
+    })
+}
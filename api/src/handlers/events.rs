@@ -1,5 +1,5 @@
 use crate::context::WrappedContext;
-use crate::utils::response_error;
+use crate::utils::{replica_connection, response_error};
 use chrono::{DateTime, Utc};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
@@ -91,14 +91,17 @@ pub async fn handler(
         }
     };
 
-    let ctx = context.read().await;
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
 
-    let from_epoch = match query_params.resolve_from_epoch(&ctx.psql_client).await {
+    let from_epoch = match query_params.resolve_from_epoch(&psql_client).await {
         Ok(from_epoch) => from_epoch,
         Err((status, message)) => return Ok(response_error(status, message)),
     };
 
-    let events = match get_events_with_context(&ctx.psql_client, &vote_key, from_epoch).await {
+    let events = match get_events_with_context(&psql_client, &vote_key, from_epoch).await {
         Ok(events) => events,
         Err(err) => {
             error!("Failed to fetch events for {vote_account}: {err}");
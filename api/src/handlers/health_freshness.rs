@@ -0,0 +1,150 @@
+use crate::cache::{Cache, CacheSection};
+use crate::context::WrappedContext;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use warp::{http::StatusCode, reply::json, Reply};
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct SectionFreshness {
+    section: CacheSection,
+    /// Whether the section holds data, loaded from the database or restored from the cache file
+    loaded: bool,
+    /// Whether it still serves the data restored from the cache file
+    stale: bool,
+    /// When the section was last reloaded from the database
+    last_warm_at: Option<DateTime<Utc>>,
+    last_warm_duration_ms: Option<u64>,
+    /// When its tables were last checked for changes, whether they changed or not
+    last_checked_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+    /// The latest epoch of the cached data
+    latest_epoch: Option<u64>,
+    /// The slot of the latest commission observation
+    latest_slot: Option<u64>,
+    /// The scoring run the single run scores come from
+    scoring_run_id: Option<i64>,
+    /// The epoch the unique delegators and take rates were loaded from BigQuery for
+    bigquery_epoch: Option<u64>,
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct ResponseFreshness {
+    /// Whether every cache section holds data
+    ready: bool,
+    /// Whether changes are announced to the cache by the store, otherwise they are polled for
+    listening: bool,
+    /// When the restored cache file was saved
+    restored_at: Option<DateTime<Utc>>,
+    sections: Vec<SectionFreshness>,
+}
+
+#[derive(Default)]
+struct LatestData {
+    epoch: Option<u64>,
+    slot: Option<u64>,
+    scoring_run_id: Option<i64>,
+    bigquery_epoch: Option<u64>,
+}
+
+fn latest_data(cache: &Cache, section: CacheSection) -> LatestData {
+    match section {
+        CacheSection::Scores => {
+            let scoring_run = cache.validators_single_run_scores.scoring_run.as_ref();
+            LatestData {
+                epoch: scoring_run.and_then(|run| run.epoch.try_into().ok()),
+                scoring_run_id: scoring_run.and_then(|run| run.scoring_run_id.to_i64()),
+                ..Default::default()
+            }
+        }
+        CacheSection::Versions => LatestData {
+            epoch: cache.versions.values().flatten().map(|v| v.epoch).max(),
+            ..Default::default()
+        },
+        CacheSection::Commissions => {
+            let latest = cache
+                .commissions
+                .values()
+                .flatten()
+                .map(|c| (c.epoch, c.epoch_slot))
+                .max();
+            LatestData {
+                epoch: latest.map(|(epoch, _)| epoch),
+                slot: latest.map(|(_, epoch_slot)| epoch_slot),
+                ..Default::default()
+            }
+        }
+        CacheSection::Uptimes => LatestData {
+            epoch: cache.uptimes.values().flatten().map(|u| u.epoch).max(),
+            ..Default::default()
+        },
+        CacheSection::ClusterStats => LatestData {
+            epoch: cache.cluster_stats.as_ref().and_then(|cluster_stats| {
+                cluster_stats
+                    .block_production_stats
+                    .iter()
+                    .map(|s| s.epoch)
+                    .max()
+            }),
+            ..Default::default()
+        },
+        CacheSection::Validators => LatestData {
+            epoch: cache
+                .validators
+                .values()
+                .flat_map(|v| v.epoch_stats.iter().map(|s| s.epoch))
+                .max(),
+            bigquery_epoch: cache.per_epoch.as_ref().map(|c| c.epoch),
+            ..Default::default()
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    tag = "General",
+    operation_id = "Data freshness",
+    path = "/health/freshness",
+    responses(
+        (status = 200, body = ResponseFreshness)
+    )
+)]
+pub async fn handler(context: WrappedContext) -> Result<impl Reply, warp::Rejection> {
+    let context = context.read().await;
+    let cache = &context.cache;
+
+    let sections = CacheSection::ALL
+        .into_iter()
+        .map(|section| {
+            let stats = cache.warm_stats.get(&section).cloned().unwrap_or_default();
+            let latest = latest_data(cache, section);
+            SectionFreshness {
+                section,
+                loaded: cache.is_loaded(section),
+                stale: cache.is_stale(section),
+                last_warm_at: stats.warmed_at,
+                last_warm_duration_ms: stats
+                    .warm_duration
+                    .map(|duration| duration.as_millis() as u64),
+                last_checked_at: stats.checked_at,
+                last_error: stats.last_error,
+                last_error_at: stats.failed_at,
+                latest_epoch: latest.epoch,
+                latest_slot: latest.slot,
+                scoring_run_id: latest.scoring_run_id,
+                bigquery_epoch: latest.bigquery_epoch,
+            }
+        })
+        .collect();
+
+    Ok(warp::reply::with_status(
+        json(&ResponseFreshness {
+            ready: cache.is_ready(),
+            listening: cache.listening,
+            restored_at: cache.restored_at,
+            sections,
+        }),
+        StatusCode::OK,
+    ))
+}
@@ -0,0 +1,14 @@
+use warp::{http::StatusCode, Reply};
+
+#[utoipa::path(
+    get,
+    tag = "General",
+    operation_id = "Liveness",
+    path = "/health/live",
+    responses(
+        (status = 200, description = "The API is running, it may still be loading its cache")
+    )
+)]
+pub async fn handler() -> Result<impl Reply, warp::Rejection> {
+    Ok(warp::reply::with_status("OK", StatusCode::OK))
+}
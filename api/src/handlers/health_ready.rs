@@ -0,0 +1,52 @@
+use crate::cache::CacheSection;
+use crate::context::WrappedContext;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use warp::{http::StatusCode, reply::json, Reply};
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct ResponseReady {
+    /// Whether every cache section holds data
+    ready: bool,
+    /// Sections holding no data yet
+    missing_sections: Vec<CacheSection>,
+    /// Sections serving the data restored from the cache file until they are reloaded from the database
+    stale_sections: Vec<CacheSection>,
+    /// When the restored cache file was saved
+    restored_at: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    tag = "General",
+    operation_id = "Readiness",
+    path = "/health/ready",
+    responses(
+        (status = 200, body = ResponseReady, description = "Every cache section holds data"),
+        (status = 503, body = ResponseReady, description = "Some cache sections are still loading")
+    )
+)]
+pub async fn handler(context: WrappedContext) -> Result<impl Reply, warp::Rejection> {
+    let context = context.read().await;
+    let cache = &context.cache;
+
+    let response = ResponseReady {
+        ready: cache.is_ready(),
+        missing_sections: CacheSection::ALL
+            .into_iter()
+            .filter(|section| !cache.is_loaded(*section))
+            .collect(),
+        stale_sections: CacheSection::ALL
+            .into_iter()
+            .filter(|section| cache.is_stale(*section))
+            .collect(),
+        restored_at: cache.restored_at,
+    };
+    let status = if response.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok(warp::reply::with_status(json(&response), status))
+}
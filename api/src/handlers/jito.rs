@@ -1,5 +1,5 @@
 use crate::context::WrappedContext;
-use crate::utils::response_error;
+use crate::utils::{replica_connection, response_error};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use store::dto::JitoRecord;
@@ -30,17 +30,21 @@ pub async fn handler(
 ) -> Result<impl Reply, warp::Rejection> {
     info!("Fetching Jito Priority Fee Info");
 
-    let validators =
-        match get_last_jito_info(&context.read().await.psql_client, DEFAULT_EPOCHS).await {
-            Ok(r) => r,
-            Err(err) => {
-                error!("Failed to fetch Jito info: {err}");
-                return Ok(response_error(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to fetch Jito records!".into(),
-                ));
-            }
-        };
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
+
+    let validators = match get_last_jito_info(&psql_client, DEFAULT_EPOCHS).await {
+        Ok(r) => r,
+        Err(err) => {
+            error!("Failed to fetch Jito info: {err}");
+            return Ok(response_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch Jito records!".into(),
+            ));
+        }
+    };
 
     Ok(warp::reply::with_status(
         json(&ResponseJito { validators }),
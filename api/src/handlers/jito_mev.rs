@@ -1,5 +1,5 @@
 use crate::context::WrappedContext;
-use crate::utils::response_error;
+use crate::utils::{replica_connection, response_error};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use store::{dto::JitoMevRecord, validators_jito::get_last_mev_info};
@@ -29,8 +29,12 @@ pub async fn handler(
 ) -> Result<impl Reply, warp::Rejection> {
     info!("Fetching Jito MEV Info");
 
-    let response = match get_last_mev_info(&context.read().await.psql_client, DEFAULT_EPOCHS).await
-    {
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(warp::reply::with_header(reply, "Deprecation", "true")),
+    };
+
+    let response = match get_last_mev_info(&psql_client, DEFAULT_EPOCHS).await {
         Ok(validators) => {
             warp::reply::with_status(json(&ResponseJitoMev { validators }), StatusCode::OK)
         }
@@ -44,6 +44,8 @@ pub struct QueryParams {
     query_with_names: Option<bool>,
     query_sfdp: Option<bool>,
     query_incident_free: Option<bool>,
+    /// Comma-separated client software (agave, jito-solana, firedancer, other or unknown).
+    query_client: Option<String>,
     /// When true, `query` also matches datacenter location fields (country, city) in addition to
     /// validator name, vote account and identity.
     search_properties: Option<bool>,
@@ -82,6 +84,7 @@ pub struct GetValidatorsConfig {
     pub query_with_names: Option<bool>,
     pub query_sfdp: Option<bool>,
     pub query_incident_free: Option<bool>,
+    pub query_client: Option<Vec<String>>,
     pub search_properties: Option<bool>,
     pub query_from_date: Option<DateTime<Utc>>,
     pub epochs: usize,
@@ -240,6 +243,13 @@ pub fn filter_validators(
         validators.retain(|_, v| v.incidents.is_empty() == query_incident_free);
     }
 
+    if let Some(clients) = &config.query_client {
+        validators.retain(|_, v| {
+            let client = v.client.as_deref().unwrap_or("unknown");
+            clients.iter().any(|c| c == client)
+        });
+    }
+
     validators.into_values().collect()
 }
 
@@ -280,6 +290,11 @@ pub async fn handler(
         query_with_names: query_params.query_with_names,
         query_sfdp: query_params.query_sfdp,
         query_incident_free: query_params.query_incident_free,
+        query_client: query_params.query_client.map(|c| {
+            c.split(",")
+                .map(|client| client.trim().to_lowercase())
+                .collect()
+        }),
         search_properties: query_params.search_properties,
         query_from_date: query_params.query_from_date,
         epochs: query_params.epochs.unwrap_or(DEFAULT_EPOCHS),
@@ -2,20 +2,27 @@ pub mod admin_score_upload;
 pub mod cluster_stats;
 pub mod commissions;
 pub mod config;
+pub mod decentralization_history;
 pub mod docs;
 pub mod events;
 pub mod global_unstake_hints;
 pub mod glossary;
+pub mod health_freshness;
+pub mod health_live;
+pub mod health_ready;
 pub mod jito;
 pub mod jito_mev;
 pub mod list_validators;
+pub mod operators;
 pub mod reports_commission_changes;
 pub mod reports_scoring;
 pub mod reports_scoring_html;
 pub mod reports_staking;
 pub mod rewards;
+pub mod snapshots;
 pub mod unstake_hints;
 pub mod uptimes;
+pub mod validator_operator;
 pub mod validator_score_breakdown;
 pub mod validator_score_breakdowns;
 pub mod validator_scores;
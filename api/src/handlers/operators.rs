@@ -0,0 +1,67 @@
+use crate::context::WrappedContext;
+use crate::metrics;
+use crate::utils::{replica_connection, response_error};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use store::dto::OperatorRecord;
+use store::utils::load_operators;
+use warp::{http::StatusCode, reply::json, Reply};
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct ResponseOperators {
+    operators: Vec<OperatorRecord>,
+}
+
+#[derive(Deserialize, Serialize, Debug, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryParams {
+    /// Epoch of the clustering, the last clustered epoch by default.
+    epoch: Option<u64>,
+    /// Only operators running at least this many vote accounts.
+    min_validators: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    tag = "Validators",
+    operation_id = "List operators",
+    path = "/operators",
+    params(QueryParams),
+    responses(
+        (status = 200, body = ResponseOperators)
+    )
+)]
+pub async fn handler(
+    query_params: QueryParams,
+    context: WrappedContext,
+) -> Result<impl Reply, warp::Rejection> {
+    info!("Fetching operators {query_params:?}");
+    metrics::REQUEST_COUNT_OPERATORS.inc();
+
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
+
+    let operators = match load_operators(&psql_client, query_params.epoch, None).await {
+        Ok(operators) => operators,
+        Err(err) => {
+            error!("Failed to fetch operators: {err}");
+            return Ok(response_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch records!".into(),
+            ));
+        }
+    };
+
+    let min_validators = query_params.min_validators.unwrap_or(1);
+    let operators = operators
+        .into_iter()
+        .filter(|operator| operator.vote_accounts.len() >= min_validators)
+        .collect();
+
+    Ok(warp::reply::with_status(
+        json(&ResponseOperators { operators }),
+        StatusCode::OK,
+    ))
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use crate::{context::WrappedContext, utils::response_error_500};
+use crate::{
+    context::WrappedContext,
+    utils::{replica_connection, response_error_500},
+};
 use chrono::{DateTime, Utc};
 use log::{error, info};
 use serde::Serialize;
@@ -154,14 +157,18 @@ fn scoring_run_to_report(scoring_run: ScoringRunRecord) -> Report {
 pub async fn handler(context: WrappedContext) -> Result<impl Reply, warp::Rejection> {
     info!("Serving the scoring reports");
 
-    let scoring_runs =
-        match store::scoring::load_scoring_runs(&context.read().await.psql_client).await {
-            Ok(scoring_runs) => scoring_runs,
-            Err(err) => {
-                error!("Failed to fetch scoring run records: {err}");
-                return Ok(response_error_500("Failed to fetch records!".into()));
-            }
-        };
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
+
+    let scoring_runs = match store::scoring::load_scoring_runs(&psql_client).await {
+        Ok(scoring_runs) => scoring_runs,
+        Err(err) => {
+            error!("Failed to fetch scoring run records: {err}");
+            return Ok(response_error_500("Failed to fetch records!".into()));
+        }
+    };
 
     Ok(warp::reply::with_status(
         reply::json(&ResponseReportScoring {
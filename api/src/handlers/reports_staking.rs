@@ -1,5 +1,5 @@
 use crate::{
-    cache::CachedSingleRunScores, context::WrappedContext, metrics, utils::response_error,
+    cache::CachedSingleRunScores, context::WrappedContext, db, metrics, utils::response_error,
 };
 use log::{error, info, warn};
 use serde::Serialize;
@@ -42,7 +42,8 @@ fn filter_and_sort_stakes(records: &mut Vec<StakingChange>) {
 
 async fn get_planned_stakes(context: WrappedContext) -> anyhow::Result<Vec<StakingChange>> {
     let mut records = Vec::new();
-    let last_epoch = match get_last_epoch(&context.read().await.psql_client).await? {
+    let psql_client = db::replica(&context).await?;
+    let last_epoch = match get_last_epoch(&psql_client).await? {
         Some(last_epoch) => last_epoch,
         _ => return Ok(Default::default()),
     };
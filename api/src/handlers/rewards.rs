@@ -1,5 +1,5 @@
 use crate::context::WrappedContext;
-use crate::utils::response_error;
+use crate::utils::{replica_connection, response_error};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use store::rewards::{
@@ -41,13 +41,15 @@ pub async fn handler(
     let epochs = query_params.epochs.unwrap_or(DEFAULT_EPOCHS);
     info!("Fetching rewards for past {epochs:?}");
 
-    let context_guard = context.read().await;
-    let psql_client = &context_guard.psql_client;
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
     let (inflation_result, mev_result, jito_result, block_result) = tokio::join!(
-        get_estimated_inflation_rewards(psql_client, epochs),
-        get_mev_rewards(psql_client, epochs),
-        get_jito_priority_rewards(psql_client, epochs),
-        get_block_rewards(psql_client, epochs),
+        get_estimated_inflation_rewards(&psql_client, epochs),
+        get_mev_rewards(&psql_client, epochs),
+        get_jito_priority_rewards(&psql_client, epochs),
+        get_block_rewards(&psql_client, epochs),
     );
 
     let rewards_inflation_est = match inflation_result {
@@ -0,0 +1,70 @@
+use crate::context::WrappedContext;
+use crate::db;
+use log::{error, info};
+use store::snapshots::load_archived_snapshot;
+use warp::{http, http::StatusCode, hyper, Reply};
+
+fn text_response(status: StatusCode, message: &'static str) -> http::Response<hyper::Body> {
+    http::response::Builder::new()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "text/plain")
+        .body(hyper::Body::from(message))
+        .unwrap()
+}
+
+#[utoipa::path(
+    get,
+    tag = "Snapshots",
+    operation_id = "Download an ingested snapshot",
+    path = "/snapshots/{snapshot_id}",
+    params(
+        ("snapshot_id" = i64, Path, description = "ID of the snapshot the stored records are tagged with")
+    ),
+    responses(
+        (status = 200, description = "The snapshot file as it was ingested"),
+        (status = 404, description = "No such snapshot")
+    )
+)]
+pub async fn handler(
+    snapshot_id: i64,
+    context: WrappedContext,
+) -> Result<impl Reply, warp::Rejection> {
+    info!("Serving snapshot {snapshot_id}");
+
+    let psql_client = match db::replica(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(err) => {
+            error!("Failed to get a database connection: {err}");
+            return Ok(text_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Database is unavailable",
+            ));
+        }
+    };
+
+    let snapshot = match load_archived_snapshot(&psql_client, snapshot_id).await {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return Ok(text_response(StatusCode::NOT_FOUND, "Snapshot not found")),
+        Err(err) => {
+            error!("Failed to load snapshot {snapshot_id}: {err}");
+            return Ok(text_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load the snapshot",
+            ));
+        }
+    };
+
+    Ok(http::response::Builder::new()
+        .status(StatusCode::OK)
+        .header(hyper::header::CONTENT_TYPE, snapshot.content_type())
+        .header(
+            hyper::header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}-{}-{}.{}\"",
+                snapshot.snapshot_type, snapshot.epoch, snapshot.id, snapshot.format
+            ),
+        )
+        .header("x-content-sha256", snapshot.content_hash)
+        .body(hyper::Body::from(snapshot.data))
+        .unwrap())
+}
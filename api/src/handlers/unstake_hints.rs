@@ -1,4 +1,8 @@
-use crate::{context::WrappedContext, metrics, utils::response_error_500};
+use crate::{
+    context::WrappedContext,
+    metrics,
+    utils::{replica_connection, response_error_500},
+};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use store::dto::UnstakeHintRecord;
@@ -31,8 +35,13 @@ pub async fn handler(
     info!("Fetching unstake hints {:?}", query_params.epoch);
     metrics::REQUEST_UNSTAKE_HINTS.inc();
 
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
+
     let unstake_hints = store::scoring::load_marinade_unstake_hint_records(
-        &context.read().await.psql_client,
+        &psql_client,
         &context.read().await.blacklist_path,
         query_params.epoch,
     )
@@ -0,0 +1,72 @@
+use crate::context::WrappedContext;
+use crate::metrics;
+use crate::utils::{replica_connection, response_error};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use store::dto::OperatorRecord;
+use store::utils::load_validator_operator;
+use warp::{http::StatusCode, reply::json, Reply};
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct ResponseValidatorOperator {
+    operator: OperatorRecord,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct QueryParams {}
+
+#[utoipa::path(
+    get,
+    tag = "Validators",
+    operation_id = "Show operator of a validator",
+    path = "/validators/{vote_account}/operator",
+    params(
+        ("vote_account" = String, Path, description = "Vote account or identity of the validator")
+    ),
+    responses(
+        (status = 200, body = ResponseValidatorOperator)
+    )
+)]
+pub async fn handler(
+    vote_account: String,
+    _query_params: QueryParams,
+    context: WrappedContext,
+) -> Result<impl Reply, warp::Rejection> {
+    info!("Fetching operator {:?}", &vote_account);
+    metrics::REQUEST_COUNT_VALIDATOR_OPERATOR.inc();
+
+    let vote_account = context
+        .read()
+        .await
+        .cache
+        .get_validators()
+        .values()
+        .find(|record| record.identity == vote_account || record.vote_account == vote_account)
+        .map(|record| record.vote_account.clone())
+        .unwrap_or(vote_account);
+
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
+
+    Ok(
+        match load_validator_operator(&psql_client, &vote_account).await {
+            Ok(Some(operator)) => warp::reply::with_status(
+                json(&ResponseValidatorOperator { operator }),
+                StatusCode::OK,
+            ),
+            Ok(None) => {
+                error!("No operator found for {}", &vote_account);
+                response_error(StatusCode::NOT_FOUND, "Failed to fetch records!".into())
+            }
+            Err(err) => {
+                error!("Failed to fetch operator of {}: {err}", &vote_account);
+                response_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to fetch records!".into(),
+                )
+            }
+        },
+    )
+}
@@ -1,5 +1,5 @@
 use crate::context::WrappedContext;
-use crate::utils::response_error;
+use crate::utils::{replica_connection, response_error};
 use log::{error, info};
 use serde::{Deserialize, Serialize};
 use store::dto::ValidatorBlockRewardsRecord;
@@ -31,24 +31,25 @@ pub async fn handler(
 ) -> Result<impl Reply, warp::Rejection> {
     info!("Fetching last validators block rewards");
 
-    let validators = match get_last_block_rewards(
-        &context.read().await.psql_client,
-        DEFAULT_EPOCHS,
-        VALIDATORS_BLOCK_REWARDS_TABLE,
-    )
-    .await
-    {
-        Ok(r) => r,
-        Err(err) => {
-            error!("Failed to fetch validators block rewards: {err}");
-            return Ok(response_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!(
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply),
+    };
+    let validators =
+        match get_last_block_rewards(&psql_client, DEFAULT_EPOCHS, VALIDATORS_BLOCK_REWARDS_TABLE)
+            .await
+        {
+            Ok(r) => r,
+            Err(err) => {
+                error!("Failed to fetch validators block rewards: {err}");
+                return Ok(response_error(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!(
                     "Failed to fetch validators block rewards for last {DEFAULT_EPOCHS} epochs!"
                 ),
-            ));
-        }
-    };
+                ));
+            }
+        };
 
     Ok(warp::reply::with_status(
         json(&ResponseValidatorsBlockRewards { validators }),
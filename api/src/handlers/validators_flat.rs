@@ -1,6 +1,6 @@
 use crate::context::WrappedContext;
 use crate::metrics;
-use crate::utils::response_error_500;
+use crate::utils::{replica_connection, response_error_500};
 use log::error;
 use serde::{Deserialize, Serialize};
 use warp::Reply;
@@ -32,8 +32,12 @@ pub async fn handler(
     log::info!("Query flat validators {query_params:?}");
 
     let epochs = query_params.epochs.unwrap_or(DEFAULT_EPOCHS);
+    let psql_client = match replica_connection(&context).await {
+        Ok(psql_client) => psql_client,
+        Err(reply) => return Ok(reply.into_response()),
+    };
     let validators = store::utils::load_validators_aggregated_flat(
-        &context.read().await.psql_client,
+        &psql_client,
         query_params.last_epoch,
         epochs,
     )
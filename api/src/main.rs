@@ -1,15 +1,15 @@
 use crate::context::{Context, WrappedContext};
+use crate::db::{Db, DbParams};
 use crate::handlers::{
-    admin_score_upload, cluster_stats, commissions, config, docs, events, global_unstake_hints,
-    glossary, jito, jito_mev, list_validators, reports_commission_changes, reports_scoring,
-    reports_scoring_html, reports_staking, rewards, unstake_hints, uptimes,
+    admin_score_upload, cluster_stats, commissions, config, decentralization_history, docs, events,
+    global_unstake_hints, glossary, health_freshness, health_live, health_ready, jito, jito_mev,
+    list_validators, operators, reports_commission_changes, reports_scoring, reports_scoring_html,
+    reports_staking, rewards, snapshots, unstake_hints, uptimes, validator_operator,
     validator_score_breakdown, validator_score_breakdowns, validator_scores,
     validators_block_rewards, validators_flat, versions, workflow_metrics_upload,
 };
 use env_logger::Env;
-use log::{error, info};
-use openssl::ssl::{SslConnector, SslMethod};
-use postgres_openssl::MakeTlsConnector;
+use log::{info, warn};
 use std::convert::Infallible;
 use std::sync::Arc;
 use structopt::StructOpt;
@@ -18,18 +18,17 @@ use warp::{Filter, Rejection};
 
 pub mod api_docs;
 pub mod cache;
+pub mod cache_file;
 pub mod context;
+pub mod db;
 pub mod handlers;
 pub mod metrics;
 pub mod utils;
 
 #[derive(Debug, StructOpt)]
 pub struct Params {
-    #[structopt(long = "postgres-url")]
-    postgres_url: String,
-
-    #[structopt(long = "postgres-ssl-root-cert", env = "PG_SSLROOTCERT")]
-    pub postgres_ssl_root_cert: String,
+    #[structopt(flatten)]
+    db: DbParams,
 
     #[structopt(long = "scoring-url")]
     scoring_url: String,
@@ -45,6 +44,12 @@ pub struct Params {
 
     #[structopt(long = "port", default_value = "8000")]
     port: u16,
+
+    #[structopt(
+        long = "cache-file",
+        help = "File the cache is saved to whenever it is refreshed and restored from at startup."
+    )]
+    cache_file: Option<String>,
 }
 
 #[tokio::main]
@@ -54,25 +59,26 @@ async fn main() -> anyhow::Result<()> {
 
     let params = Params::from_args();
 
-    let mut builder = SslConnector::builder(SslMethod::tls())?;
-    builder.set_ca_file(&params.postgres_ssl_root_cert)?;
-    let connector = MakeTlsConnector::new(builder.build());
-
-    let (psql_client, psql_conn) = tokio_postgres::connect(&params.postgres_url, connector).await?;
-    tokio::spawn(async move {
-        if let Err(err) = psql_conn.await {
-            error!("PSQL Connection error: {err}");
-            std::process::exit(1);
-        }
-    });
+    let db = Db::connect(&params.db).await?;
+    let psql_client = db.primary().await?;
+    store::migrations::check_schema_version(&psql_client).await?;
+    drop(psql_client);
+    db::spawn_health_check(db.clone());
 
     let context = Arc::new(RwLock::new(Context::new(
-        psql_client,
+        db,
         params.glossary_path,
         params.blacklist_path,
         params.scoring_url,
+        params.cache_file.clone(),
     )?));
+    if let Some(cache_file) = &params.cache_file {
+        if let Err(err) = cache_file::restore(&context, cache_file).await {
+            warn!("Failed to restore the cache from {cache_file}, it starts empty: {err}");
+        }
+    }
     cache::spawn_cache_warmer(context.clone());
+    cache::spawn_cache_listener(context.clone());
     let cors = warp::cors()
         .allow_any_origin()
         .allow_headers(vec![
@@ -96,6 +102,23 @@ async fn main() -> anyhow::Result<()> {
 
     let route_api_docs_html = warp::path("docs").and(warp::get()).and_then(docs::handler);
 
+    let route_health_live = warp::path!("health" / "live")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and_then(health_live::handler);
+
+    let route_health_ready = warp::path!("health" / "ready")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_context(context.clone()))
+        .and_then(health_ready::handler);
+
+    let route_health_freshness = warp::path!("health" / "freshness")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_context(context.clone()))
+        .and_then(health_freshness::handler);
+
     let route_validators = warp::path!("validators")
         .and(warp::path::end())
         .and(warp::get())
@@ -144,6 +167,13 @@ async fn main() -> anyhow::Result<()> {
         .and(with_context(context.clone()))
         .and_then(cluster_stats::handler);
 
+    let route_decentralization_history = warp::path!("cluster-stats" / "decentralization")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<decentralization_history::QueryParams>())
+        .and(with_context(context.clone()))
+        .and_then(decentralization_history::handler);
+
     let route_uptimes = warp::path!("validators" / String / "uptimes")
         .and(warp::path::end())
         .and(warp::get())
@@ -165,6 +195,20 @@ async fn main() -> anyhow::Result<()> {
         .and(with_context(context.clone()))
         .and_then(versions::handler);
 
+    let route_validator_operator = warp::path!("validators" / String / "operator")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<validator_operator::QueryParams>())
+        .and(with_context(context.clone()))
+        .and_then(validator_operator::handler);
+
+    let route_operators = warp::path!("operators")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<operators::QueryParams>())
+        .and(with_context(context.clone()))
+        .and_then(operators::handler);
+
     let route_commissions = warp::path!("validators" / String / "commissions")
         .and(warp::path::end())
         .and(warp::get())
@@ -215,6 +259,12 @@ async fn main() -> anyhow::Result<()> {
         .and(with_context(context.clone()))
         .and_then(rewards::handler);
 
+    let route_snapshots = warp::path!("snapshots" / i64)
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_context(context.clone()))
+        .and_then(snapshots::handler);
+
     let route_jito_mev = warp::path!("mev")
         .and(warp::path::end())
         .and(warp::get())
@@ -262,7 +312,11 @@ async fn main() -> anyhow::Result<()> {
     let routes = top_level
         .or(route_api_docs_oas)
         .or(route_api_docs_html)
+        .or(route_health_live)
+        .or(route_health_ready)
+        .or(route_health_freshness)
         .or(route_cluster_stats)
+        .or(route_decentralization_history)
         .or(route_validators)
         .or(route_validator_score_breakdown)
         .or(route_validator_score_breakdowns)
@@ -272,6 +326,8 @@ async fn main() -> anyhow::Result<()> {
         .or(route_uptimes)
         .or(route_events)
         .or(route_versions)
+        .or(route_validator_operator)
+        .or(route_operators)
         .or(route_commissions)
         .or(route_glossary)
         .or(route_jito_mev)
@@ -281,6 +337,7 @@ async fn main() -> anyhow::Result<()> {
         .or(route_reports_scoring_html)
         .or(route_reports_staking)
         .or(route_rewards)
+        .or(route_snapshots)
         .or(route_unstake_hints)
         .or(route_global_unstake_hints)
         .or(route_reports_commission_changes)
@@ -11,6 +11,11 @@ lazy_static! {
         "How many times /cluster-stats endpoint was requested"
     )
     .unwrap();
+    pub static ref REQUEST_DECENTRALIZATION_HISTORY: IntCounter = register_int_counter!(
+        "ds_request_count_decentralization_history",
+        "How many times /cluster-stats/decentralization endpoint was requested"
+    )
+    .unwrap();
     pub static ref REQUEST_UNSTAKE_HINTS: IntCounter = register_int_counter!(
         "ds_request_count_unstake_hints",
         "How many times /unstake-hints endpoint was requested"
@@ -56,6 +61,16 @@ lazy_static! {
         "How many times /versions endpoint was requested"
     )
     .unwrap();
+    pub static ref REQUEST_COUNT_OPERATORS: IntCounter = register_int_counter!(
+        "ds_request_count_operators",
+        "How many times /operators endpoint was requested"
+    )
+    .unwrap();
+    pub static ref REQUEST_COUNT_VALIDATOR_OPERATOR: IntCounter = register_int_counter!(
+        "ds_request_count_validator_operator",
+        "How many times /validators/operator endpoint was requested"
+    )
+    .unwrap();
     pub static ref REQUEST_COUNT_UPTIMES: IntCounter = register_int_counter!(
         "ds_request_count_uptimes",
         "How many times /uptimes endpoint was requested"
@@ -75,6 +90,36 @@ lazy_static! {
     pub static ref JOB_DURATION: IntGaugeVec =
         register_int_gauge_vec!("ds_job_duration", "Workflow jobs duration", &["workflow"])
             .unwrap();
+    pub static ref DB_HEALTHY: IntGaugeVec = register_int_gauge_vec!(
+        "ds_db_healthy",
+        "Whether the last health check of the database succeeded",
+        &["database"]
+    )
+    .unwrap();
+    pub static ref DB_POOL_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "ds_db_pool_connections",
+        "Open and available connections of the pool and requests waiting for one",
+        &["database", "state"]
+    )
+    .unwrap();
+    pub static ref CACHE_LAST_WARM: IntGaugeVec = register_int_gauge_vec!(
+        "ds_cache_last_warm_timestamp",
+        "When the cache section was last reloaded, in Unix seconds",
+        &["section"]
+    )
+    .unwrap();
+    pub static ref CACHE_LAST_CHECK: IntGaugeVec = register_int_gauge_vec!(
+        "ds_cache_last_check_timestamp",
+        "When the tables of the cache section were last checked for changes, in Unix seconds",
+        &["section"]
+    )
+    .unwrap();
+    pub static ref CACHE_WARM_FAILED: IntGaugeVec = register_int_gauge_vec!(
+        "ds_cache_warm_failed",
+        "Whether the last check or reload of the cache section failed",
+        &["section"]
+    )
+    .unwrap();
 }
 
 fn collect_metrics() -> String {
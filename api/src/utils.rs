@@ -1,3 +1,7 @@
+use crate::context::WrappedContext;
+use crate::db;
+use deadpool_postgres::Object;
+use log::error;
 use serde::Serialize;
 use warp::{
     http::StatusCode,
@@ -16,3 +20,14 @@ pub fn response_error_500(message: String) -> WithStatus<Json> {
 pub fn response_error(status: StatusCode, message: String) -> WithStatus<Json> {
     warp::reply::with_status(json(&ErrorResponse { message }), status)
 }
+
+/// A connection for read-only queries, or the error reply if no database is reachable.
+pub async fn replica_connection(context: &WrappedContext) -> Result<Object, WithStatus<Json>> {
+    db::replica(context).await.map_err(|err| {
+        error!("Failed to get a database connection: {err}");
+        response_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Database is unavailable!".into(),
+        )
+    })
+}
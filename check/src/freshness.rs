@@ -0,0 +1,361 @@
+use chrono::{DateTime, Utc};
+use collect::rpc_service::SolanaRpc;
+use log::{info, warn};
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use structopt::StructOpt;
+use tokio_postgres::Client;
+
+#[derive(Debug, StructOpt)]
+pub struct FreshnessParams {
+    #[structopt(
+        long = "gap-epochs",
+        help = "How many past epochs to look for gaps in.",
+        default_value = "20"
+    )]
+    gap_epochs: u64,
+
+    #[structopt(
+        long = "max-age",
+        help = "How old the latest record of the tables updated several times per epoch may be (seconds).",
+        default_value = "10800"
+    )]
+    max_age: u64,
+
+    #[structopt(
+        long = "events-max-lag",
+        help = "How many epochs the latest validator event may be behind the current epoch. Settlements are sparse and arrive late.",
+        default_value = "10"
+    )]
+    events_max_lag: u64,
+
+    #[structopt(
+        long = "scores-max-lag",
+        help = "How many epochs the latest scoring run may be behind the current epoch.",
+        default_value = "1"
+    )]
+    scores_max_lag: u64,
+}
+
+struct TableSpec {
+    name: &'static str,
+    source: &'static str,
+    epoch_column: &'static str,
+    /// Column of tables written several times per epoch, checked against `--max-age`.
+    time_column: Option<&'static str>,
+    max_lag: u64,
+    check_gaps: bool,
+}
+
+fn table_specs(params: &FreshnessParams) -> Vec<TableSpec> {
+    let per_epoch = |name, max_lag| TableSpec {
+        name,
+        source: name,
+        epoch_column: "epoch",
+        time_column: None,
+        max_lag,
+        check_gaps: true,
+    };
+    let within_epoch = |name, time_column| TableSpec {
+        name,
+        source: name,
+        epoch_column: "epoch",
+        time_column: Some(time_column),
+        max_lag: 0,
+        check_gaps: true,
+    };
+
+    // commissions and versions only get a record when the value changes,
+    // so their latest record may be old while they are up to date
+    vec![
+        within_epoch("validators", "updated_at"),
+        within_epoch("uptimes", "end_at"),
+        per_epoch("commissions", 0),
+        per_epoch("versions", 0),
+        within_epoch("cluster_info", "created_at"),
+        per_epoch("epochs", 1),
+        per_epoch("mev", 1),
+        per_epoch("jito_priority_fee", 1),
+        per_epoch("validators_block_rewards", 1),
+        TableSpec {
+            check_gaps: false,
+            ..per_epoch("validators_events", params.events_max_lag)
+        },
+        TableSpec {
+            name: "scores",
+            source: "scores INNER JOIN scoring_runs ON scoring_runs.scoring_run_id = scores.scoring_run_id",
+            epoch_column: "scoring_runs.epoch",
+            time_column: None,
+            max_lag: params.scores_max_lag,
+            check_gaps: true,
+        },
+    ]
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableFreshness {
+    pub table: String,
+    pub expected_epoch: u64,
+    pub latest_epoch: Option<u64>,
+    pub latest_at: Option<DateTime<Utc>>,
+    pub fresh: bool,
+    pub stale_reason: Option<String>,
+    pub missing_epochs: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FreshnessStatus {
+    Fresh,
+    Gaps,
+    Stale,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FreshnessReport {
+    pub status: FreshnessStatus,
+    pub current_epoch: u64,
+    pub current_slot_index: u64,
+    pub tables: Vec<TableFreshness>,
+}
+
+fn stale_reason(
+    latest_epoch: Option<u64>,
+    latest_at: Option<DateTime<Utc>>,
+    expected_epoch: u64,
+    max_age: u64,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    match (latest_epoch, latest_at) {
+        (None, _) => Some("No records".to_string()),
+        (Some(latest_epoch), _) if latest_epoch < expected_epoch => Some(format!(
+            "Latest epoch {latest_epoch} is behind the expected epoch {expected_epoch}"
+        )),
+        (_, Some(latest_at)) if (now - latest_at).num_seconds() > max_age as i64 => Some(format!(
+            "Latest record from {latest_at} is older than {max_age} seconds"
+        )),
+        _ => None,
+    }
+}
+
+/// Epochs from `from_epoch` to `to_epoch` (inclusive) without a stored record.
+fn find_missing_epochs(stored_epochs: &BTreeSet<u64>, from_epoch: u64, to_epoch: u64) -> Vec<u64> {
+    (from_epoch..=to_epoch)
+        .filter(|epoch| !stored_epochs.contains(epoch))
+        .collect()
+}
+
+/// Stale tables weigh more than gaps in the recent epochs.
+fn freshness_status(tables: &[TableFreshness]) -> FreshnessStatus {
+    if tables.iter().any(|table| !table.fresh) {
+        FreshnessStatus::Stale
+    } else if tables.iter().any(|table| !table.missing_epochs.is_empty()) {
+        FreshnessStatus::Gaps
+    } else {
+        FreshnessStatus::Fresh
+    }
+}
+
+async fn check_table(
+    spec: &TableSpec,
+    params: &FreshnessParams,
+    psql_client: &Client,
+    current_epoch: u64,
+) -> anyhow::Result<TableFreshness> {
+    let TableSpec {
+        name,
+        source,
+        epoch_column,
+        ..
+    } = spec;
+    let time_column = spec.time_column.unwrap_or("NULL::TIMESTAMP WITH TIME ZONE");
+    let expected_epoch = current_epoch.saturating_sub(spec.max_lag);
+
+    let row = psql_client
+        .query_one(
+            format!(
+                "SELECT
+                    MIN({epoch_column})::NUMERIC AS first_epoch,
+                    MAX({epoch_column})::NUMERIC AS latest_epoch,
+                    MAX({time_column}) AS latest_at
+                FROM {source}"
+            )
+            .as_str(),
+            &[],
+        )
+        .await?;
+    let first_epoch: Option<u64> = row
+        .get::<_, Option<Decimal>>("first_epoch")
+        .map(|epoch| epoch.try_into())
+        .transpose()?;
+    let latest_epoch: Option<u64> = row
+        .get::<_, Option<Decimal>>("latest_epoch")
+        .map(|epoch| epoch.try_into())
+        .transpose()?;
+    let latest_at: Option<DateTime<Utc>> = row.get("latest_at");
+
+    let stale_reason = stale_reason(
+        latest_epoch,
+        latest_at,
+        expected_epoch,
+        params.max_age,
+        Utc::now(),
+    );
+
+    let mut missing_epochs = vec![];
+    if let (true, Some(first_epoch), Some(latest_epoch)) =
+        (spec.check_gaps, first_epoch, latest_epoch)
+    {
+        let from_epoch = first_epoch.max(expected_epoch.saturating_sub(params.gap_epochs));
+        let stored_epochs = psql_client
+            .query(
+                format!(
+                    "SELECT DISTINCT {epoch_column}::NUMERIC AS epoch
+                    FROM {source}
+                    WHERE {epoch_column} >= $1::NUMERIC"
+                )
+                .as_str(),
+                &[&Decimal::from(from_epoch)],
+            )
+            .await?
+            .iter()
+            .map(|row| row.get::<_, Decimal>("epoch").try_into())
+            .collect::<Result<BTreeSet<u64>, _>>()?;
+        missing_epochs =
+            find_missing_epochs(&stored_epochs, from_epoch, latest_epoch.min(expected_epoch));
+    }
+
+    Ok(TableFreshness {
+        table: name.to_string(),
+        expected_epoch,
+        latest_epoch,
+        latest_at,
+        fresh: stale_reason.is_none(),
+        stale_reason,
+        missing_epochs,
+    })
+}
+
+/// Reports for every table the store writes whether the latest expected epoch is present
+/// and which of the recent epochs are missing.
+pub async fn check_freshness(
+    params: FreshnessParams,
+    psql_client: &Client,
+    rpc_client: &dyn SolanaRpc,
+) -> anyhow::Result<FreshnessReport> {
+    let epoch_info = rpc_client.get_epoch_info()?;
+    info!(
+        "Checking freshness of the stored data at epoch {} slot index {}",
+        epoch_info.epoch, epoch_info.slot_index
+    );
+
+    let mut tables = vec![];
+    for spec in table_specs(&params) {
+        let table = check_table(&spec, &params, psql_client, epoch_info.epoch).await?;
+        if let Some(stale_reason) = &table.stale_reason {
+            warn!("Table {} is stale: {stale_reason}", table.table);
+        }
+        if !table.missing_epochs.is_empty() {
+            warn!(
+                "Table {} is missing epochs: {:?}",
+                table.table, table.missing_epochs
+            );
+        }
+        tables.push(table);
+    }
+
+    let status = freshness_status(&tables);
+    info!("Stored data is {status:?}");
+
+    Ok(FreshnessReport {
+        status,
+        current_epoch: epoch_info.epoch,
+        current_slot_index: epoch_info.slot_index,
+        tables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_missing_epochs, freshness_status, stale_reason, table_specs, FreshnessParams,
+        FreshnessStatus, TableFreshness,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use std::collections::BTreeSet;
+    use structopt::StructOpt;
+
+    #[test]
+    fn missing_epochs_within_the_range() {
+        let stored: BTreeSet<u64> = [10, 11, 13, 16].into();
+        assert_eq!(find_missing_epochs(&stored, 10, 16), vec![12, 14, 15]);
+        assert_eq!(find_missing_epochs(&stored, 13, 13), Vec::<u64>::new());
+        // epochs after the latest stored one are reported as stale, not as gaps
+        assert_eq!(find_missing_epochs(&stored, 16, 15), Vec::<u64>::new());
+        assert_eq!(find_missing_epochs(&BTreeSet::new(), 3, 4), vec![3, 4]);
+    }
+
+    #[test]
+    fn stale_tables() {
+        let now = Utc.with_ymd_and_hms(2024, 11, 20, 12, 0, 0).unwrap();
+        let an_hour_ago = Some(now - Duration::hours(1));
+
+        assert_eq!(stale_reason(Some(700), an_hour_ago, 700, 7200, now), None);
+        assert_eq!(stale_reason(Some(700), None, 700, 7200, now), None);
+        assert_eq!(
+            stale_reason(None, None, 700, 7200, now).as_deref(),
+            Some("No records")
+        );
+        assert!(stale_reason(Some(699), an_hour_ago, 700, 7200, now)
+            .unwrap()
+            .contains("behind the expected epoch 700"));
+        assert!(stale_reason(Some(700), an_hour_ago, 700, 1800, now)
+            .unwrap()
+            .contains("older than 1800 seconds"));
+    }
+
+    #[test]
+    fn change_logs_are_not_checked_for_age() {
+        let params = FreshnessParams::from_iter(["freshness"]);
+        for spec in table_specs(&params) {
+            match spec.name {
+                "commissions" | "versions" => {
+                    assert_eq!(spec.time_column, None);
+                    assert_eq!(spec.max_lag, 0);
+                }
+                "validators" | "uptimes" | "cluster_info" => assert!(spec.time_column.is_some()),
+                _ => {}
+            }
+        }
+    }
+
+    fn table(fresh: bool, missing_epochs: Vec<u64>) -> TableFreshness {
+        TableFreshness {
+            table: "validators".to_string(),
+            expected_epoch: 700,
+            latest_epoch: Some(700),
+            latest_at: None,
+            fresh,
+            stale_reason: None,
+            missing_epochs,
+        }
+    }
+
+    #[test]
+    fn status_of_the_tables() {
+        assert_eq!(freshness_status(&[]), FreshnessStatus::Fresh);
+        assert_eq!(
+            freshness_status(&[table(true, vec![]), table(true, vec![])]),
+            FreshnessStatus::Fresh
+        );
+        assert_eq!(
+            freshness_status(&[table(true, vec![]), table(true, vec![698])]),
+            FreshnessStatus::Gaps
+        );
+        assert_eq!(
+            freshness_status(&[table(false, vec![]), table(true, vec![698])]),
+            FreshnessStatus::Stale
+        );
+    }
+}
@@ -1,2 +1,3 @@
+pub mod freshness;
 pub mod validators_block_rewards;
 pub mod validators_jito;
@@ -1,4 +1,5 @@
 use crate::validators_jito::{check_jito, ValidatorsJitoCheckParams};
+use check::freshness::{check_freshness, FreshnessParams, FreshnessStatus};
 use check::validators_block_rewards::{check_block_rewards, BlockRewardsCheckParams};
 use collect::solana_service::solana_client;
 use env_logger::Env;
@@ -36,6 +37,37 @@ enum StoreCommand {
     JitoMev(ValidatorsJitoCheckParams),
     JitoPriority(ValidatorsJitoCheckParams),
     BlockRewards(BlockRewardsCheckParams),
+    Freshness(FreshnessParams),
+}
+
+const EXIT_PROCEED: i32 = 0;
+const EXIT_SKIP: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+const EXIT_STALE: i32 = 3;
+const EXIT_GAPS: i32 = 4;
+
+fn exit_code_from_check(proceed: bool) -> i32 {
+    if proceed {
+        EXIT_PROCEED
+    } else {
+        info!("Not a good time to collect, skipping");
+        EXIT_SKIP
+    }
+}
+
+fn exit_code_from_freshness(status: FreshnessStatus) -> i32 {
+    match status {
+        FreshnessStatus::Fresh => EXIT_PROCEED,
+        FreshnessStatus::Stale => EXIT_STALE,
+        FreshnessStatus::Gaps => EXIT_GAPS,
+    }
+}
+
+fn exit_code(result: anyhow::Result<i32>) -> i32 {
+    result.unwrap_or_else(|err| {
+        log::error!("Check failed: {err:?}");
+        EXIT_ERROR
+    })
 }
 
 pub mod validators_jito;
@@ -44,20 +76,13 @@ pub mod validators_jito;
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    match run().await {
-        Ok(true) => {}
-        Ok(false) => {
-            info!("Not a good time to collect, skipping");
-            std::process::exit(1);
-        }
-        Err(err) => {
-            log::error!("Check failed: {err:?}");
-            std::process::exit(2);
-        }
+    match exit_code(run().await) {
+        EXIT_PROCEED => {}
+        exit_code => std::process::exit(exit_code),
     }
 }
 
-async fn run() -> anyhow::Result<bool> {
+async fn run() -> anyhow::Result<i32> {
     let params = Params::from_args();
     info!(
         "Running check command {:?} with commitment {}",
@@ -73,39 +98,71 @@ async fn run() -> anyhow::Result<bool> {
     tokio::spawn(async move {
         if let Err(err) = psql_conn.await {
             log::error!("Connection error: {err}");
-            std::process::exit(2);
+            std::process::exit(EXIT_ERROR);
         }
     });
 
     let rpc_client = solana_client(params.common.rpc_url, params.common.commitment);
 
-    match params.command {
-        StoreCommand::JitoMev(mev_params) => {
+    let exit_code = match params.command {
+        StoreCommand::JitoMev(mev_params) => exit_code_from_check(
             check_jito(
                 mev_params,
                 &psql_client,
                 &rpc_client,
                 collect::validators_jito::JitoAccountType::MevTipDistribution.db_table_name(),
             )
-            .await
-        }
-        StoreCommand::JitoPriority(jito_params) => {
+            .await?,
+        ),
+        StoreCommand::JitoPriority(jito_params) => exit_code_from_check(
             check_jito(
                 jito_params,
                 &psql_client,
                 &rpc_client,
                 collect::validators_jito::JitoAccountType::PriorityFeeDistribution.db_table_name(),
             )
-            .await
-        }
-        StoreCommand::BlockRewards(rewards_params) => {
+            .await?,
+        ),
+        StoreCommand::BlockRewards(rewards_params) => exit_code_from_check(
             check_block_rewards(
                 rewards_params,
                 &psql_client,
                 &rpc_client,
                 store::validators_block_rewards::VALIDATORS_BLOCK_REWARDS_TABLE,
             )
-            .await
+            .await?,
+        ),
+        StoreCommand::Freshness(freshness_params) => {
+            let report = check_freshness(freshness_params, &psql_client, &rpc_client).await?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            exit_code_from_freshness(report.status)
         }
+    };
+
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_code, exit_code_from_check, exit_code_from_freshness};
+    use check::freshness::FreshnessStatus;
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code(Ok(exit_code_from_check(true))), 0);
+        assert_eq!(exit_code(Ok(exit_code_from_check(false))), 1);
+        assert_eq!(exit_code(Err(anyhow::anyhow!("RPC is down"))), 2);
+        assert_eq!(
+            exit_code(Ok(exit_code_from_freshness(FreshnessStatus::Fresh))),
+            0
+        );
+        assert_eq!(
+            exit_code(Ok(exit_code_from_freshness(FreshnessStatus::Stale))),
+            3
+        );
+        assert_eq!(
+            exit_code(Ok(exit_code_from_freshness(FreshnessStatus::Gaps))),
+            4
+        );
     }
 }
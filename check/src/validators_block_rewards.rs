@@ -1,7 +1,7 @@
 use crate::validators_jito::MILLISECONDS_PER_SLOT;
+use collect::rpc_service::SolanaRpc;
 use log::info;
 use rust_decimal::prelude::*;
-use solana_client::rpc_client::RpcClient;
 use structopt::StructOpt;
 use tokio_postgres::Client;
 use validator::Validate;
@@ -23,7 +23,7 @@ pub struct BlockRewardsCheckParams {
 pub async fn check_block_rewards(
     params: BlockRewardsCheckParams,
     psql_client: &Client,
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     db_table: &str,
 ) -> anyhow::Result<bool> {
     info!("Checking epoch data about epoch in DB table {db_table}");
@@ -1,6 +1,6 @@
+use collect::rpc_service::SolanaRpc;
 use log::info;
 use rust_decimal::prelude::*;
-use solana_client::rpc_client::RpcClient;
 use structopt::StructOpt;
 use tokio_postgres::Client;
 
@@ -21,7 +21,7 @@ pub struct ValidatorsJitoCheckParams {
 pub async fn check_jito(
     params: ValidatorsJitoCheckParams,
     psql_client: &Client,
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     db_table: &str,
 ) -> anyhow::Result<bool> {
     info!("Checking epoch data about epoch in DB table {db_table}");
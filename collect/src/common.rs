@@ -1,15 +1,115 @@
-use std::{thread, time::Duration};
+use crate::snapshot::SnapshotFormat;
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
 use structopt::StructOpt;
 
 pub const MILLISECONDS_PER_SLOT: u64 = 400; // 0.4 seconds per slot
 
 #[derive(Debug, StructOpt)]
 pub struct CommonParams {
-    #[structopt(short = "u", long = "url", env = "RPC_URL")]
-    pub rpc_url: String,
+    #[structopt(
+        short = "u",
+        long = "url",
+        env = "RPC_URL",
+        help = "RPC URL. Several comma-separated URLs are used for failover, in the given order."
+    )]
+    pub rpc_url: Option<String>,
 
     #[structopt(short = "c", long = "commitment", default_value = "finalized")]
     pub commitment: String,
+
+    #[structopt(
+        long = "format",
+        help = "Format of the snapshot written to stdout: yaml, json or json.zst.",
+        default_value = "yaml"
+    )]
+    pub format: SnapshotFormat,
+
+    #[structopt(
+        long = "rpc-record",
+        help = "Directory to record RPC responses to as fixtures.",
+        conflicts_with = "rpc-replay"
+    )]
+    pub rpc_record: Option<PathBuf>,
+
+    #[structopt(
+        long = "rpc-replay",
+        help = "Directory with recorded RPC fixtures to serve responses from instead of the RPC."
+    )]
+    pub rpc_replay: Option<PathBuf>,
+
+    #[structopt(
+        long = "rpc-failover-after",
+        help = "How many consecutive failures of an RPC make the collector switch to the next URL.",
+        default_value = "3"
+    )]
+    pub rpc_failover_after: usize,
+
+    #[structopt(
+        long = "rpc-quorum",
+        help = "Compare epoch info, vote accounts and leader schedule of the first two RPC URLs before collecting."
+    )]
+    pub rpc_quorum: bool,
+
+    #[structopt(
+        long = "rpc-quorum-slot-tolerance",
+        help = "How many slots the RPCs in quorum may be apart.",
+        default_value = "150"
+    )]
+    pub rpc_quorum_slot_tolerance: u64,
+
+    #[structopt(
+        long = "rpc-quorum-vote-accounts-tolerance",
+        help = "Share of vote accounts (0-1) whose stake or commission may differ between the RPCs in quorum.",
+        default_value = "0.01"
+    )]
+    pub rpc_quorum_vote_accounts_tolerance: f64,
+
+    #[structopt(
+        long = "rpc-rps",
+        help = "How many requests per second may be sent to the RPC (0 for no limit).",
+        default_value = "5"
+    )]
+    pub rpc_rps: f64,
+
+    #[structopt(
+        long = "rpc-concurrency",
+        help = "How many RPC requests may be in flight at once.",
+        default_value = "1"
+    )]
+    pub rpc_concurrency: usize,
+
+    #[structopt(
+        long = "rpc-rate-limit-retries",
+        help = "How many times to retry a request the RPC rejected with HTTP 429.",
+        default_value = "5"
+    )]
+    pub rpc_rate_limit_retries: usize,
+
+    #[structopt(
+        long = "rpc-rate-limit-backoff",
+        help = "How long to pause all requests after the first HTTP 429, doubled on each retry (seconds).",
+        default_value = "2"
+    )]
+    pub rpc_rate_limit_backoff: u64,
+}
+
+impl CommonParams {
+    pub fn rpc_urls(&self) -> Vec<String> {
+        self.rpc_url
+            .iter()
+            .flat_map(|rpc_url| rpc_url.split(','))
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
 }
 
 pub fn retry_blocking<F, T, E, ErrorCallback>(
@@ -34,6 +134,44 @@ where
     make_call()
 }
 
+/// Calls `make_call` for every item on up to `concurrency` threads.
+/// Results keep the order of the items; the first error (in item order) is returned.
+pub fn map_concurrently<I, T, E, F>(
+    items: &[I],
+    concurrency: usize,
+    make_call: F,
+) -> Result<Vec<T>, E>
+where
+    I: Sync,
+    T: Send,
+    E: Send,
+    F: Fn(&I) -> Result<T, E> + Sync,
+{
+    let next_item = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<T, E>>>> =
+        Mutex::new((0..items.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next_item.fetch_add(1, Ordering::SeqCst);
+                if index >= items.len() {
+                    break;
+                }
+                let result = make_call(&items[index]);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("Every item is processed"))
+        .collect()
+}
+
 pub struct QuadraticBackoffStrategy;
 
 impl QuadraticBackoffStrategy {
@@ -0,0 +1,94 @@
+use crate::whois_service::{Coordinates, IpInfo, IpInfoProvider};
+use log::info;
+use maxminddb::{geoip2, Reader};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+const CITY_DB_SUFFIX: &str = "-City.mmdb";
+const ASN_DB_SUFFIX: &str = "-ASN.mmdb";
+const NAMES_LANGUAGE: &str = "en";
+
+pub struct GeoIpClient {
+    city_reader: Reader<Vec<u8>>,
+    asn_reader: Reader<Vec<u8>>,
+}
+
+impl GeoIpClient {
+    pub fn new(city_db_path: &Path, asn_db_path: &Path) -> anyhow::Result<Self> {
+        info!(
+            "Opening GeoIP databases: {} and {}",
+            city_db_path.display(),
+            asn_db_path.display()
+        );
+        Ok(Self {
+            city_reader: Reader::open_readfile(city_db_path)?,
+            asn_reader: Reader::open_readfile(asn_db_path)?,
+        })
+    }
+
+    /// Opens GeoLite2/GeoIP2 City and ASN databases found in the given directory.
+    pub fn from_dir(db_dir: &Path) -> anyhow::Result<Self> {
+        let city_db_path = find_db_file(db_dir, CITY_DB_SUFFIX)?;
+        let asn_db_path = find_db_file(db_dir, ASN_DB_SUFFIX)?;
+
+        Self::new(&city_db_path, &asn_db_path)
+    }
+}
+
+fn find_db_file(db_dir: &Path, suffix: &str) -> anyhow::Result<PathBuf> {
+    for entry in std::fs::read_dir(db_dir)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(suffix))
+        {
+            return Ok(path);
+        }
+    }
+
+    anyhow::bail!(
+        "No GeoIP database matching *{} found in {}",
+        suffix,
+        db_dir.display()
+    )
+}
+
+fn english_name(names: Option<BTreeMap<&str, &str>>) -> Option<String> {
+    names.and_then(|names| names.get(NAMES_LANGUAGE).map(|name| name.to_string()))
+}
+
+impl IpInfoProvider for GeoIpClient {
+    fn get_ip_info(&self, ip: &str) -> anyhow::Result<IpInfo> {
+        let ip_addr: IpAddr = ip.parse()?;
+        let city: geoip2::City = self.city_reader.lookup(ip_addr)?;
+        let asn: Option<geoip2::Asn> = self.asn_reader.lookup(ip_addr).ok();
+
+        let coordinates =
+            city.location
+                .and_then(|location| match (location.latitude, location.longitude) {
+                    (Some(lat), Some(lon)) => Some(Coordinates { lat, lon }),
+                    _ => None,
+                });
+
+        Ok(IpInfo {
+            asn: asn.as_ref().and_then(|asn| asn.autonomous_system_number),
+            aso: asn
+                .as_ref()
+                .and_then(|asn| asn.autonomous_system_organization)
+                .map(|aso| aso.to_string()),
+            coordinates,
+            continent: city
+                .continent
+                .and_then(|continent| english_name(continent.names)),
+            country_iso: city
+                .country
+                .as_ref()
+                .and_then(|country| country.iso_code)
+                .map(|iso_code| iso_code.to_string()),
+            country: city.country.and_then(|country| english_name(country.names)),
+            city: city.city.and_then(|city| english_name(city.names)),
+        })
+    }
+}
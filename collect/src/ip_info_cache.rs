@@ -0,0 +1,243 @@
+use crate::whois_service::{IpInfo, IpInfoProvider};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Expired entries are kept as a fallback for failed lookups, until they are this many TTLs old.
+const FALLBACK_TTLS: i32 = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IpInfoCacheEntry {
+    looked_up_at: DateTime<Utc>,
+    ip_info: IpInfo,
+}
+
+/// Entries by the provider which looked them up and by the IP.
+type IpInfoCacheEntries = HashMap<String, HashMap<String, IpInfoCacheEntry>>;
+
+#[derive(Default)]
+struct IpInfoCacheState {
+    entries: IpInfoCacheEntries,
+    hits: usize,
+    misses: usize,
+    stale_fallbacks: usize,
+}
+
+/// Keeps results of IP lookups on disk so that unchanged node IPs are not looked up again
+/// until their entry is older than the TTL. Expired entries are still served when the
+/// underlying provider fails. Providers share the file, each with entries of its own.
+pub struct CachedIpInfoProvider<'a> {
+    provider: &'a dyn IpInfoProvider,
+    provider_name: String,
+    path: PathBuf,
+    ttl: chrono::Duration,
+    state: Mutex<IpInfoCacheState>,
+}
+
+impl<'a> CachedIpInfoProvider<'a> {
+    pub fn new(
+        provider: &'a dyn IpInfoProvider,
+        provider_name: &str,
+        path: &Path,
+        ttl: Duration,
+    ) -> anyhow::Result<Self> {
+        let entries: IpInfoCacheEntries = if path.exists() {
+            serde_json::from_reader(File::open(path)?).unwrap_or_else(|err| {
+                warn!(
+                    "Discarding IP info cache {} of an unknown format: {err}",
+                    path.display()
+                );
+                Default::default()
+            })
+        } else {
+            Default::default()
+        };
+        info!(
+            "Loaded {} {provider_name} entries from IP info cache {}",
+            entries.get(provider_name).map_or(0, HashMap::len),
+            path.display()
+        );
+
+        Ok(Self {
+            provider,
+            provider_name: provider_name.to_string(),
+            path: path.to_path_buf(),
+            ttl: chrono::Duration::from_std(ttl)?,
+            state: Mutex::new(IpInfoCacheState {
+                entries,
+                ..Default::default()
+            }),
+        })
+    }
+
+    /// Entries too old to serve even as a fallback are dropped, of all providers.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        info!(
+            "IP info cache hits: {}, misses: {}, stale fallbacks: {}",
+            state.hits, state.misses, state.stale_fallbacks
+        );
+
+        let now = Utc::now();
+        let max_age = self.ttl * FALLBACK_TTLS;
+        let mut pruned = 0;
+        for provider_entries in state.entries.values_mut() {
+            let entries = provider_entries.len();
+            provider_entries.retain(|_, entry| now - entry.looked_up_at < max_age);
+            pruned += entries - provider_entries.len();
+        }
+        state
+            .entries
+            .retain(|_, provider_entries| !provider_entries.is_empty());
+
+        let tmp_path = self.path.with_extension("tmp");
+        serde_json::to_writer(File::create(&tmp_path)?, &state.entries)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        info!(
+            "Stored {} entries to IP info cache {}, pruned {pruned} entries older than {max_age}",
+            state.entries.values().map(HashMap::len).sum::<usize>(),
+            self.path.display()
+        );
+
+        Ok(())
+    }
+}
+
+impl IpInfoProvider for CachedIpInfoProvider<'_> {
+    fn get_ip_info(&self, ip: &str) -> anyhow::Result<IpInfo> {
+        let cached = self
+            .state
+            .lock()
+            .unwrap()
+            .entries
+            .get(&self.provider_name)
+            .and_then(|provider_entries| provider_entries.get(ip))
+            .cloned();
+        let now = Utc::now();
+
+        if let Some(entry) = &cached {
+            if now - entry.looked_up_at < self.ttl {
+                self.state.lock().unwrap().hits += 1;
+                return Ok(entry.ip_info.clone());
+            }
+        }
+
+        self.state.lock().unwrap().misses += 1;
+        match self.provider.get_ip_info(ip) {
+            Ok(ip_info) => {
+                self.state
+                    .lock()
+                    .unwrap()
+                    .entries
+                    .entry(self.provider_name.clone())
+                    .or_default()
+                    .insert(
+                        ip.to_string(),
+                        IpInfoCacheEntry {
+                            looked_up_at: now,
+                            ip_info: ip_info.clone(),
+                        },
+                    );
+                Ok(ip_info)
+            }
+            Err(err) => match cached {
+                Some(entry) => {
+                    warn!(
+                        "Using expired IP info of {ip} looked up at {}: {err}",
+                        entry.looked_up_at
+                    );
+                    self.state.lock().unwrap().stale_fallbacks += 1;
+                    Ok(entry.ip_info)
+                }
+                None => Err(err),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CachedIpInfoProvider;
+    use crate::whois_service::{IpInfo, IpInfoProvider};
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    struct FakeProvider {
+        aso: &'static str,
+        lookups: Cell<usize>,
+    }
+
+    impl IpInfoProvider for FakeProvider {
+        fn get_ip_info(&self, _ip: &str) -> anyhow::Result<IpInfo> {
+            self.lookups.set(self.lookups.get() + 1);
+            Ok(IpInfo {
+                asn: None,
+                aso: Some(self.aso.to_string()),
+                coordinates: None,
+                continent: None,
+                country_iso: None,
+                country: None,
+                city: None,
+            })
+        }
+    }
+
+    fn provider(aso: &'static str) -> FakeProvider {
+        FakeProvider {
+            aso,
+            lookups: Cell::new(0),
+        }
+    }
+
+    #[test]
+    fn entries_are_kept_per_provider() {
+        let path = std::env::temp_dir().join(format!("ip-info-cache-{}.json", std::process::id()));
+        let ip = "10.0.0.1".to_string();
+        let ttl = Duration::from_secs(3600);
+
+        let whois = provider("whois");
+        let cached = CachedIpInfoProvider::new(&whois, "whois", &path, ttl).unwrap();
+        assert_eq!(cached.get_ip_info(&ip).unwrap().aso.unwrap(), "whois");
+        cached.save().unwrap();
+
+        // another provider does not get the entries of the first one
+        let geoip = provider("geoip");
+        let cached = CachedIpInfoProvider::new(&geoip, "geoip-db", &path, ttl).unwrap();
+        assert_eq!(cached.get_ip_info(&ip).unwrap().aso.unwrap(), "geoip");
+        assert_eq!(geoip.lookups.get(), 1);
+        cached.save().unwrap();
+
+        let cached = CachedIpInfoProvider::new(&whois, "whois", &path, ttl).unwrap();
+        assert_eq!(cached.get_ip_info(&ip).unwrap().aso.unwrap(), "whois");
+        assert_eq!(whois.lookups.get(), 1);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn old_entries_are_pruned() {
+        let path =
+            std::env::temp_dir().join(format!("ip-info-cache-prune-{}.json", std::process::id()));
+        let ip = "10.0.0.1".to_string();
+        let whois = provider("whois");
+
+        let cached =
+            CachedIpInfoProvider::new(&whois, "whois", &path, Duration::from_secs(3600)).unwrap();
+        cached.get_ip_info(&ip).unwrap();
+        cached.save().unwrap();
+        let cached = CachedIpInfoProvider::new(&whois, "whois", &path, Duration::ZERO).unwrap();
+        cached.save().unwrap();
+
+        let cached =
+            CachedIpInfoProvider::new(&whois, "whois", &path, Duration::from_secs(3600)).unwrap();
+        cached.get_ip_info(&ip).unwrap();
+        assert_eq!(whois.lookups.get(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
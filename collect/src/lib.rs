@@ -1,5 +1,12 @@
 pub mod common;
+pub mod geoip_service;
+pub mod ip_info_cache;
 pub mod marinade_service;
+pub mod rpc_budget;
+pub mod rpc_failover;
+pub mod rpc_service;
+pub mod snapshot;
+pub mod snapshot_diff;
 pub mod solana_service;
 pub mod validators;
 pub mod validators_block_rewards;
@@ -1,4 +1,5 @@
 use collect::common::*;
+use collect::snapshot_diff::{diff_snapshot_files, DiffParams};
 use collect::validators::*;
 use collect::validators_block_rewards::{collect_validator_block_rewards_info, BlockRewardsParams};
 use collect::validators_events::{collect_validator_events_info, EventsParams};
@@ -28,6 +29,7 @@ enum CollectCommand {
     JitoPriority(JitoParams),
     ValidatorsBlockRewards(BlockRewardsParams),
     ValidatorsEvents(EventsParams),
+    Diff(DiffParams),
 }
 
 impl Display for CollectCommand {
@@ -39,6 +41,7 @@ impl Display for CollectCommand {
             CollectCommand::JitoPriority(_) => write!(f, "jito-priority"),
             CollectCommand::ValidatorsBlockRewards(_) => write!(f, "validators-block-rewards"),
             CollectCommand::ValidatorsEvents(_) => write!(f, "validators-events"),
+            CollectCommand::Diff(_) => write!(f, "diff"),
         }
     }
 }
@@ -72,6 +75,7 @@ fn main() -> anyhow::Result<()> {
         CollectCommand::ValidatorsEvents(events_params) => {
             collect_validator_events_info(params.common, events_params)
         }
+        CollectCommand::Diff(diff_params) => diff_snapshot_files(diff_params),
     };
 
     match result {
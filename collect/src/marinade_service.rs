@@ -1,8 +1,8 @@
+use crate::rpc_service::SolanaRpc;
 use crate::validators::BondsResponse;
 use crate::validators::ValidatorBond;
 use solana_account_decoder::*;
 use solana_client::{
-    rpc_client::RpcClient,
     rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
 };
@@ -16,7 +16,7 @@ use solana_stake_interface as stake;
 use std::collections::*;
 
 pub fn get_marinade_stakes(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     epoch: Epoch,
     stake_history: &StakeHistory,
 ) -> anyhow::Result<HashMap<String, u64>> {
@@ -33,7 +33,7 @@ pub fn get_marinade_stakes(
 }
 
 pub fn get_institutional_stakes(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     epoch: Epoch,
     stake_history: &StakeHistory,
 ) -> anyhow::Result<HashMap<String, u64>> {
@@ -51,7 +51,7 @@ pub fn get_institutional_stakes(
 }
 
 pub fn get_foundation_stakes(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     epoch: Epoch,
     stake_history: &StakeHistory,
 ) -> anyhow::Result<HashMap<String, u64>> {
@@ -78,7 +78,7 @@ pub fn get_foundation_stakes(
 }
 
 pub fn get_marinade_native_stakes(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     epoch: Epoch,
     stake_history: &StakeHistory,
 ) -> anyhow::Result<HashMap<String, u64>> {
@@ -95,7 +95,7 @@ pub fn get_marinade_native_stakes(
 }
 
 fn get_stakes_grouped_by_validator(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     delegation_authority: &Pubkey,
     withdrawer_authority: Option<&Pubkey>,
     epoch: Epoch,
@@ -132,7 +132,7 @@ fn get_stakes_grouped_by_validator(
 }
 
 fn get_stake_accounts(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     delegation_authority: &Pubkey,
     withdrawer_authority: Option<&Pubkey>,
 ) -> anyhow::Result<HashMap<Pubkey, stake::state::StakeStateV2>> {
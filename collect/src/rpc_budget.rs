@@ -0,0 +1,299 @@
+use crate::rpc_service::{RpcResult, SolanaRpc};
+use log::warn;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_request::RpcError,
+    rpc_response::{
+        Response, RpcBlockProduction, RpcContactInfo, RpcInflationGovernor, RpcInflationRate,
+        RpcInflationReward, RpcLeaderSchedule, RpcSupply, RpcVoteAccountStatus,
+    },
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{
+    account::Account,
+    clock::{Epoch, Slot},
+    epoch_info::EpochInfo,
+    epoch_schedule::EpochSchedule,
+    pubkey::Pubkey,
+};
+use std::sync::{Condvar, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const HTTP_TOO_MANY_REQUESTS: u16 = 429;
+const MAX_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Requests-per-second and concurrency limits shared by all RPC calls of a collector run.
+pub struct RpcBudget {
+    request_interval: Duration,
+    next_request_at: Mutex<Instant>,
+    max_concurrency: usize,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+}
+
+pub struct RpcPermit<'a> {
+    budget: &'a RpcBudget,
+}
+
+impl RpcBudget {
+    pub fn new(requests_per_second: f64, max_concurrency: usize) -> Self {
+        Self {
+            request_interval: if requests_per_second > 0.0 {
+                Duration::from_secs_f64(1.0 / requests_per_second)
+            } else {
+                Duration::ZERO
+            },
+            next_request_at: Mutex::new(Instant::now()),
+            max_concurrency: max_concurrency.max(1),
+            in_flight: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a request may be sent without exceeding the budget.
+    pub fn acquire(&self) -> RpcPermit<'_> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while *in_flight >= self.max_concurrency {
+            in_flight = self.released.wait(in_flight).unwrap();
+        }
+        *in_flight += 1;
+        drop(in_flight);
+
+        let wait = {
+            let mut next_request_at = self.next_request_at.lock().unwrap();
+            let now = Instant::now();
+            let request_at = (*next_request_at).max(now);
+            *next_request_at = request_at + self.request_interval;
+            request_at - now
+        };
+        if !wait.is_zero() {
+            sleep(wait);
+        }
+
+        RpcPermit { budget: self }
+    }
+
+    /// Holds back all requests for the given duration, e.g. when the RPC reports a rate limit.
+    pub fn pause(&self, duration: Duration) {
+        let mut next_request_at = self.next_request_at.lock().unwrap();
+        *next_request_at = (*next_request_at).max(Instant::now() + duration);
+    }
+}
+
+impl Drop for RpcPermit<'_> {
+    fn drop(&mut self) {
+        *self.budget.in_flight.lock().unwrap() -= 1;
+        self.budget.released.notify_one();
+    }
+}
+
+pub fn is_rate_limited(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Reqwest(err) => {
+            err.status().map(|status| status.as_u16()) == Some(HTTP_TOO_MANY_REQUESTS)
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == HTTP_TOO_MANY_REQUESTS as i64
+        }
+        _ => false,
+    }
+}
+
+/// Sends all calls through the `RpcBudget`. When the RPC answers with HTTP 429 even after
+/// the transport has waited for its `Retry-After`, all calls are paused with an exponential
+/// backoff and the call is retried.
+pub struct BudgetedRpc {
+    inner: Box<dyn SolanaRpc>,
+    budget: RpcBudget,
+    rate_limit_retries: usize,
+    rate_limit_backoff: Duration,
+}
+
+impl BudgetedRpc {
+    pub fn new(
+        inner: Box<dyn SolanaRpc>,
+        budget: RpcBudget,
+        rate_limit_retries: usize,
+        rate_limit_backoff: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            budget,
+            rate_limit_retries,
+            rate_limit_backoff,
+        }
+    }
+
+    fn call<T>(&self, make_call: impl Fn(&dyn SolanaRpc) -> RpcResult<T>) -> RpcResult<T> {
+        let mut attempt = 0;
+        loop {
+            let result = {
+                let _permit = self.budget.acquire();
+                make_call(self.inner.as_ref())
+            };
+            match result {
+                Err(err) if is_rate_limited(&err) && attempt < self.rate_limit_retries => {
+                    let backoff = self
+                        .rate_limit_backoff
+                        .saturating_mul(2u32.saturating_pow(attempt as u32))
+                        .min(MAX_RATE_LIMIT_BACKOFF);
+                    attempt += 1;
+                    warn!(
+                        "RPC {} is rate limiting ({err}), pausing requests for {backoff:?}",
+                        self.inner.url()
+                    );
+                    self.budget.pause(backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl SolanaRpc for BudgetedRpc {
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_epoch_info(&self) -> RpcResult<EpochInfo> {
+        self.call(|rpc| rpc.get_epoch_info())
+    }
+
+    fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule> {
+        self.call(|rpc| rpc.get_epoch_schedule())
+    }
+
+    fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus> {
+        self.call(|rpc| rpc.get_vote_accounts())
+    }
+
+    fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
+        self.call(|rpc| rpc.get_cluster_nodes())
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> RpcResult<Vec<u8>> {
+        self.call(|rpc| rpc.get_account_data(pubkey))
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Response<Option<Account>>> {
+        self.call(|rpc| rpc.get_account_with_commitment(pubkey, commitment))
+    }
+
+    fn get_program_accounts(&self, program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.call(|rpc| rpc.get_program_accounts(program_id))
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.call(|rpc| rpc.get_program_accounts_with_config(program_id, config.clone()))
+    }
+
+    fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>> {
+        self.call(|rpc| rpc.get_block_production())
+    }
+
+    fn get_leader_schedule_with_commitment(
+        &self,
+        slot: Option<Slot>,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<RpcLeaderSchedule>> {
+        self.call(|rpc| rpc.get_leader_schedule_with_commitment(slot, commitment))
+    }
+
+    fn supply(&self) -> RpcResult<Response<RpcSupply>> {
+        self.call(|rpc| rpc.supply())
+    }
+
+    fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+        self.call(|rpc| rpc.get_inflation_rate())
+    }
+
+    fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor> {
+        self.call(|rpc| rpc.get_inflation_governor())
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+        self.call(|rpc| rpc.get_inflation_reward(addresses, epoch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RpcBudget;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread::{scope, sleep};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn requests_spaced_by_the_rate() {
+        let budget = RpcBudget::new(50.0, 4);
+        let started_at = Instant::now();
+        for _ in 0..6 {
+            drop(budget.acquire());
+        }
+        // the first request goes out at once, the next five 20 ms apart
+        assert!(started_at.elapsed() >= Duration::from_millis(95));
+    }
+
+    #[test]
+    fn no_rate_limit() {
+        let budget = RpcBudget::new(0.0, 1);
+        let started_at = Instant::now();
+        for _ in 0..1000 {
+            drop(budget.acquire());
+        }
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn requests_in_flight_bounded_by_the_concurrency() {
+        let budget = RpcBudget::new(0.0, 2);
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let _permit = budget.acquire();
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    sleep(Duration::from_millis(10));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn pause_holds_back_the_next_request() {
+        let budget = RpcBudget::new(0.0, 1);
+        budget.pause(Duration::from_millis(50));
+        let started_at = Instant::now();
+        drop(budget.acquire());
+        assert!(started_at.elapsed() >= Duration::from_millis(45));
+
+        // a shorter pause does not cut a longer one short
+        budget.pause(Duration::from_millis(80));
+        budget.pause(Duration::from_millis(10));
+        let started_at = Instant::now();
+        drop(budget.acquire());
+        assert!(started_at.elapsed() >= Duration::from_millis(75));
+    }
+}
@@ -0,0 +1,687 @@
+use crate::rpc_service::{RpcResult, SolanaRpc};
+use log::{info, warn};
+use solana_client::{
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_response::{
+        Response, RpcBlockProduction, RpcContactInfo, RpcInflationGovernor, RpcInflationRate,
+        RpcInflationReward, RpcLeaderSchedule, RpcSupply, RpcVoteAccountStatus,
+    },
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{
+    account::Account,
+    clock::{Epoch, Slot},
+    epoch_info::EpochInfo,
+    epoch_schedule::EpochSchedule,
+    pubkey::Pubkey,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+struct FailoverState {
+    active: usize,
+    consecutive_failures: usize,
+}
+
+/// Sends calls to one endpoint at a time and switches to the next endpoint once the active one
+/// has failed `failover_after` times in a row. The failing call is then retried on the new endpoint.
+pub struct FailoverRpc {
+    endpoints: Vec<Box<dyn SolanaRpc>>,
+    failover_after: usize,
+    state: Mutex<FailoverState>,
+}
+
+impl FailoverRpc {
+    pub fn new(endpoints: Vec<Box<dyn SolanaRpc>>, failover_after: usize) -> Self {
+        assert!(
+            !endpoints.is_empty(),
+            "At least one RPC endpoint is required"
+        );
+        Self {
+            endpoints,
+            failover_after: failover_after.max(1),
+            state: Mutex::new(FailoverState {
+                active: 0,
+                consecutive_failures: 0,
+            }),
+        }
+    }
+
+    fn call<T>(&self, make_call: impl Fn(&dyn SolanaRpc) -> RpcResult<T>) -> RpcResult<T> {
+        let mut attempted_endpoints = 0;
+        loop {
+            let active = self.state.lock().unwrap().active;
+            let endpoint = self.endpoints[active].as_ref();
+            attempted_endpoints += 1;
+
+            match make_call(endpoint) {
+                Ok(result) => {
+                    self.state.lock().unwrap().consecutive_failures = 0;
+                    return Ok(result);
+                }
+                Err(err) => {
+                    let mut state = self.state.lock().unwrap();
+                    if state.active != active {
+                        // another call has switched the endpoint meanwhile
+                        return Err(err);
+                    }
+                    state.consecutive_failures += 1;
+                    if state.consecutive_failures < self.failover_after || self.endpoints.len() == 1
+                    {
+                        return Err(err);
+                    }
+
+                    state.active = (active + 1) % self.endpoints.len();
+                    state.consecutive_failures = 0;
+                    warn!(
+                        "RPC {} failed {} times in a row ({err}), failing over to {}",
+                        endpoint.url(),
+                        self.failover_after,
+                        self.endpoints[state.active].url()
+                    );
+                    if attempted_endpoints >= self.endpoints.len() {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl SolanaRpc for FailoverRpc {
+    fn url(&self) -> String {
+        self.endpoints[self.state.lock().unwrap().active].url()
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        self.endpoints[self.state.lock().unwrap().active].commitment()
+    }
+
+    fn get_epoch_info(&self) -> RpcResult<EpochInfo> {
+        self.call(|rpc| rpc.get_epoch_info())
+    }
+
+    fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule> {
+        self.call(|rpc| rpc.get_epoch_schedule())
+    }
+
+    fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus> {
+        self.call(|rpc| rpc.get_vote_accounts())
+    }
+
+    fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
+        self.call(|rpc| rpc.get_cluster_nodes())
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> RpcResult<Vec<u8>> {
+        self.call(|rpc| rpc.get_account_data(pubkey))
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Response<Option<Account>>> {
+        self.call(|rpc| rpc.get_account_with_commitment(pubkey, commitment))
+    }
+
+    fn get_program_accounts(&self, program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.call(|rpc| rpc.get_program_accounts(program_id))
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.call(|rpc| rpc.get_program_accounts_with_config(program_id, config.clone()))
+    }
+
+    fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>> {
+        self.call(|rpc| rpc.get_block_production())
+    }
+
+    fn get_leader_schedule_with_commitment(
+        &self,
+        slot: Option<Slot>,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<RpcLeaderSchedule>> {
+        self.call(|rpc| rpc.get_leader_schedule_with_commitment(slot, commitment))
+    }
+
+    fn supply(&self) -> RpcResult<Response<RpcSupply>> {
+        self.call(|rpc| rpc.supply())
+    }
+
+    fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+        self.call(|rpc| rpc.get_inflation_rate())
+    }
+
+    fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor> {
+        self.call(|rpc| rpc.get_inflation_governor())
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+        self.call(|rpc| rpc.get_inflation_reward(addresses, epoch))
+    }
+}
+
+pub struct QuorumTolerance {
+    pub max_slot_difference: u64,
+    pub max_vote_accounts_mismatch: f64,
+}
+
+fn vote_account_stakes(vote_accounts: &RpcVoteAccountStatus) -> HashMap<&String, (u64, u8)> {
+    vote_accounts
+        .current
+        .iter()
+        .chain(vote_accounts.delinquent.iter())
+        .map(|v| (&v.vote_pubkey, (v.activated_stake, v.commission)))
+        .collect()
+}
+
+/// Counts the vote accounts whose stake or commission differ, or that only one provider knows,
+/// and relates them to the number of vote accounts of the primary provider.
+fn vote_accounts_mismatch(
+    primary: &RpcVoteAccountStatus,
+    secondary: &RpcVoteAccountStatus,
+) -> (usize, f64) {
+    let primary_stakes = vote_account_stakes(primary);
+    let secondary_stakes = vote_account_stakes(secondary);
+    let mismatched = primary_stakes
+        .iter()
+        .filter(|(vote_account, stake)| secondary_stakes.get(*vote_account) != Some(*stake))
+        .count()
+        + secondary_stakes
+            .keys()
+            .filter(|vote_account| !primary_stakes.contains_key(*vote_account))
+            .count();
+
+    (
+        mismatched,
+        mismatched as f64 / primary_stakes.len().max(1) as f64,
+    )
+}
+
+/// Fetches epoch info, vote accounts and the leader schedule of the collected epoch
+/// (the current one when none is given) from two providers and fails when they disagree
+/// beyond the tolerance.
+pub fn check_rpc_quorum(
+    primary: &dyn SolanaRpc,
+    secondary: &dyn SolanaRpc,
+    epoch: Option<Epoch>,
+    tolerance: &QuorumTolerance,
+) -> anyhow::Result<()> {
+    info!(
+        "Checking RPC quorum of {} and {}",
+        primary.url(),
+        secondary.url()
+    );
+
+    let primary_epoch_info = primary.get_epoch_info()?;
+    let secondary_epoch_info = secondary.get_epoch_info()?;
+    if primary_epoch_info.epoch != secondary_epoch_info.epoch {
+        anyhow::bail!(
+            "RPC providers disagree on epoch: {} vs. {}",
+            primary_epoch_info.epoch,
+            secondary_epoch_info.epoch
+        );
+    }
+    let slot_difference = primary_epoch_info
+        .absolute_slot
+        .abs_diff(secondary_epoch_info.absolute_slot);
+    if slot_difference > tolerance.max_slot_difference {
+        anyhow::bail!(
+            "RPC providers are {slot_difference} slots apart, tolerated is {}",
+            tolerance.max_slot_difference
+        );
+    }
+
+    let (mismatched, mismatch_ratio) = vote_accounts_mismatch(
+        &primary.get_vote_accounts()?,
+        &secondary.get_vote_accounts()?,
+    );
+    if mismatch_ratio > tolerance.max_vote_accounts_mismatch {
+        anyhow::bail!(
+            "RPC providers disagree on {mismatched} vote accounts ({:.2}%), tolerated is {:.2}%",
+            mismatch_ratio * 100.0,
+            tolerance.max_vote_accounts_mismatch * 100.0
+        );
+    }
+
+    let epoch = epoch.unwrap_or(primary_epoch_info.epoch);
+    let epoch_first_slot = primary.get_epoch_schedule()?.get_first_slot_in_epoch(epoch);
+    let primary_leader_schedule = primary
+        .get_leader_schedule_with_commitment(Some(epoch_first_slot), primary.commitment())?;
+    let secondary_leader_schedule = secondary
+        .get_leader_schedule_with_commitment(Some(epoch_first_slot), secondary.commitment())?;
+    if primary_leader_schedule != secondary_leader_schedule {
+        anyhow::bail!("RPC providers disagree on the leader schedule of epoch {epoch}");
+    }
+
+    info!(
+        "RPC providers agree: epoch {}, {slot_difference} slots apart, {mismatched} vote accounts differ, leader schedule of epoch {epoch}",
+        primary_epoch_info.epoch
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_rpc_quorum, vote_accounts_mismatch, FailoverRpc, QuorumTolerance};
+    use crate::rpc_service::{RpcResult, SolanaRpc};
+    use solana_client::{
+        client_error::ClientErrorKind,
+        rpc_config::RpcProgramAccountsConfig,
+        rpc_response::{
+            Response, RpcBlockProduction, RpcBlockProductionRange, RpcContactInfo,
+            RpcInflationGovernor, RpcInflationRate, RpcInflationReward, RpcLeaderSchedule,
+            RpcResponseContext, RpcSupply, RpcVoteAccountInfo, RpcVoteAccountStatus,
+        },
+    };
+    use solana_commitment_config::CommitmentConfig;
+    use solana_sdk::{
+        account::Account,
+        clock::{Epoch, Slot},
+        epoch_info::EpochInfo,
+        epoch_schedule::EpochSchedule,
+        pubkey::Pubkey,
+    };
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const SLOTS_PER_EPOCH: u64 = 32;
+
+    /// Answers epoch info, vote accounts and leader schedules, empty responses to the other calls,
+    /// or fails every call when told to.
+    #[derive(Clone, Default)]
+    struct FakeRpc {
+        url: &'static str,
+        epoch: Epoch,
+        absolute_slot: Slot,
+        vote_accounts: Vec<(&'static str, u64, u8)>,
+        /// Leader schedules by the first slot of their epoch
+        leader_schedules: HashMap<Slot, RpcLeaderSchedule>,
+        failing: Arc<AtomicBool>,
+        calls: Arc<AtomicUsize>,
+        leader_schedule_slots: Arc<Mutex<Vec<Option<Slot>>>>,
+    }
+
+    impl FakeRpc {
+        fn new(url: &'static str) -> Self {
+            Self {
+                url,
+                ..Default::default()
+            }
+        }
+
+        fn answer<T>(&self, result: impl FnOnce() -> T) -> RpcResult<T> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(Box::new(
+                    ClientErrorKind::Custom(format!("{} is down", self.url)).into(),
+                ));
+            }
+            Ok(result())
+        }
+
+        fn response<T>(&self, value: T) -> Response<T> {
+            Response {
+                context: RpcResponseContext {
+                    slot: self.absolute_slot,
+                    api_version: None,
+                },
+                value,
+            }
+        }
+    }
+
+    impl SolanaRpc for FakeRpc {
+        fn url(&self) -> String {
+            self.url.to_string()
+        }
+
+        fn commitment(&self) -> CommitmentConfig {
+            CommitmentConfig::finalized()
+        }
+
+        fn get_epoch_info(&self) -> RpcResult<EpochInfo> {
+            self.answer(|| EpochInfo {
+                epoch: self.epoch,
+                slot_index: self.absolute_slot % SLOTS_PER_EPOCH,
+                slots_in_epoch: SLOTS_PER_EPOCH,
+                absolute_slot: self.absolute_slot,
+                block_height: self.absolute_slot,
+                transaction_count: None,
+            })
+        }
+
+        fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule> {
+            self.answer(|| EpochSchedule::custom(SLOTS_PER_EPOCH, SLOTS_PER_EPOCH, false))
+        }
+
+        fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus> {
+            self.answer(|| vote_account_status(&self.vote_accounts))
+        }
+
+        fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
+            self.answer(Vec::new)
+        }
+
+        fn get_account_data(&self, _pubkey: &Pubkey) -> RpcResult<Vec<u8>> {
+            self.answer(Vec::new)
+        }
+
+        fn get_account_with_commitment(
+            &self,
+            _pubkey: &Pubkey,
+            _commitment: CommitmentConfig,
+        ) -> RpcResult<Response<Option<Account>>> {
+            self.answer(|| self.response(None))
+        }
+
+        fn get_program_accounts(&self, _program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>> {
+            self.answer(Vec::new)
+        }
+
+        fn get_program_accounts_with_config(
+            &self,
+            _program_id: &Pubkey,
+            _config: RpcProgramAccountsConfig,
+        ) -> RpcResult<Vec<(Pubkey, Account)>> {
+            self.answer(Vec::new)
+        }
+
+        fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>> {
+            self.answer(|| {
+                self.response(RpcBlockProduction {
+                    by_identity: HashMap::new(),
+                    range: RpcBlockProductionRange {
+                        first_slot: self.epoch * SLOTS_PER_EPOCH,
+                        last_slot: self.absolute_slot,
+                    },
+                })
+            })
+        }
+
+        fn get_leader_schedule_with_commitment(
+            &self,
+            slot: Option<Slot>,
+            _commitment: CommitmentConfig,
+        ) -> RpcResult<Option<RpcLeaderSchedule>> {
+            self.leader_schedule_slots.lock().unwrap().push(slot);
+            self.answer(|| {
+                let epoch_first_slot = slot.map_or(self.epoch * SLOTS_PER_EPOCH, |slot| {
+                    slot - slot % SLOTS_PER_EPOCH
+                });
+                self.leader_schedules.get(&epoch_first_slot).cloned()
+            })
+        }
+
+        fn supply(&self) -> RpcResult<Response<RpcSupply>> {
+            self.answer(|| {
+                self.response(RpcSupply {
+                    total: 0,
+                    circulating: 0,
+                    non_circulating: 0,
+                    non_circulating_accounts: vec![],
+                })
+            })
+        }
+
+        fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+            self.answer(|| RpcInflationRate {
+                total: 0.0,
+                validator: 0.0,
+                foundation: 0.0,
+                epoch: self.epoch,
+            })
+        }
+
+        fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor> {
+            self.answer(|| RpcInflationGovernor {
+                initial: 0.0,
+                terminal: 0.0,
+                taper: 0.0,
+                foundation: 0.0,
+                foundation_term: 0.0,
+            })
+        }
+
+        fn get_inflation_reward(
+            &self,
+            addresses: &[Pubkey],
+            _epoch: Option<Epoch>,
+        ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+            self.answer(|| vec![None; addresses.len()])
+        }
+    }
+
+    fn vote_account_status(vote_accounts: &[(&str, u64, u8)]) -> RpcVoteAccountStatus {
+        RpcVoteAccountStatus {
+            current: vote_accounts
+                .iter()
+                .map(
+                    |(vote_pubkey, activated_stake, commission)| RpcVoteAccountInfo {
+                        vote_pubkey: vote_pubkey.to_string(),
+                        node_pubkey: format!("node-{vote_pubkey}"),
+                        activated_stake: *activated_stake,
+                        commission: *commission,
+                        epoch_vote_account: true,
+                        epoch_credits: vec![],
+                        last_vote: 0,
+                        root_slot: 0,
+                    },
+                )
+                .collect(),
+            delinquent: vec![],
+        }
+    }
+
+    fn failover(endpoints: &[&FakeRpc], failover_after: usize) -> FailoverRpc {
+        FailoverRpc::new(
+            endpoints
+                .iter()
+                .map(|endpoint| -> Box<dyn SolanaRpc> { Box::new((*endpoint).clone()) })
+                .collect(),
+            failover_after,
+        )
+    }
+
+    #[test]
+    fn fails_over_after_consecutive_failures() {
+        let primary = FakeRpc::new("primary");
+        let secondary = FakeRpc::new("secondary");
+        let rpc = failover(&[&primary, &secondary], 2);
+
+        primary.failing.store(true, Ordering::SeqCst);
+        assert!(rpc.get_epoch_info().is_err());
+        assert_eq!(rpc.url(), "primary");
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 0);
+
+        // the second failure in a row switches the endpoint and retries the call there
+        assert!(rpc.get_epoch_info().is_ok());
+        assert_eq!(rpc.url(), "secondary");
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 1);
+
+        assert!(rpc.get_epoch_info().is_ok());
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn success_resets_the_failures() {
+        let primary = FakeRpc::new("primary");
+        let secondary = FakeRpc::new("secondary");
+        let rpc = failover(&[&primary, &secondary], 2);
+
+        for _ in 0..3 {
+            primary.failing.store(true, Ordering::SeqCst);
+            assert!(rpc.get_epoch_info().is_err());
+            primary.failing.store(false, Ordering::SeqCst);
+            assert!(rpc.get_epoch_info().is_ok());
+        }
+        assert_eq!(rpc.url(), "primary");
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn fails_back_to_the_first_endpoint() {
+        let primary = FakeRpc::new("primary");
+        let secondary = FakeRpc::new("secondary");
+        let rpc = failover(&[&primary, &secondary], 1);
+
+        primary.failing.store(true, Ordering::SeqCst);
+        assert!(rpc.get_epoch_info().is_ok());
+        assert_eq!(rpc.url(), "secondary");
+
+        primary.failing.store(false, Ordering::SeqCst);
+        secondary.failing.store(true, Ordering::SeqCst);
+        assert!(rpc.get_epoch_info().is_ok());
+        assert_eq!(rpc.url(), "primary");
+    }
+
+    #[test]
+    fn fails_when_every_endpoint_fails() {
+        let primary = FakeRpc::new("primary");
+        let secondary = FakeRpc::new("secondary");
+        let rpc = failover(&[&primary, &secondary], 1);
+
+        primary.failing.store(true, Ordering::SeqCst);
+        secondary.failing.store(true, Ordering::SeqCst);
+        assert!(rpc.get_epoch_info().is_err());
+        // each endpoint is tried once per call, not in circles
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn single_endpoint_is_kept() {
+        let only = FakeRpc::new("only");
+        let rpc = failover(&[&only], 1);
+
+        only.failing.store(true, Ordering::SeqCst);
+        assert!(rpc.get_epoch_info().is_err());
+        assert!(rpc.get_epoch_info().is_err());
+        assert_eq!(only.calls.load(Ordering::SeqCst), 2);
+
+        only.failing.store(false, Ordering::SeqCst);
+        assert!(rpc.get_epoch_info().is_ok());
+        assert_eq!(rpc.url(), "only");
+    }
+
+    #[test]
+    fn vote_accounts_mismatch_counts_changed_and_missing_accounts() {
+        let primary =
+            vote_account_status(&[("a", 100, 5), ("b", 200, 5), ("c", 300, 5), ("d", 1, 0)]);
+        assert_eq!(vote_accounts_mismatch(&primary, &primary), (0, 0.0));
+
+        // "b" has another stake, "c" another commission, "d" is missing and "e" is unknown
+        let secondary =
+            vote_account_status(&[("a", 100, 5), ("b", 201, 5), ("c", 300, 6), ("e", 1, 0)]);
+        assert_eq!(vote_accounts_mismatch(&primary, &secondary), (4, 1.0));
+        assert_eq!(
+            vote_accounts_mismatch(
+                &primary,
+                &vote_account_status(&[("a", 100, 5), ("b", 200, 5), ("c", 300, 5)])
+            ),
+            (1, 0.25)
+        );
+        assert_eq!(
+            vote_accounts_mismatch(
+                &vote_account_status(&[]),
+                &vote_account_status(&[("a", 1, 0)])
+            ),
+            (1, 1.0)
+        );
+    }
+
+    fn agreeing_providers() -> (FakeRpc, FakeRpc) {
+        let mut primary = FakeRpc::new("primary");
+        primary.epoch = 10;
+        primary.absolute_slot = 10 * SLOTS_PER_EPOCH + 5;
+        primary.vote_accounts = vec![("a", 100, 5), ("b", 200, 5)];
+        for epoch in [9, 10] {
+            primary.leader_schedules.insert(
+                epoch * SLOTS_PER_EPOCH,
+                HashMap::from([(format!("leader-{epoch}"), vec![0, 1, 2])]),
+            );
+        }
+        let mut secondary = primary.clone();
+        secondary.url = "secondary";
+        secondary.leader_schedule_slots = Default::default();
+
+        (primary, secondary)
+    }
+
+    const TOLERANCE: QuorumTolerance = QuorumTolerance {
+        max_slot_difference: 3,
+        max_vote_accounts_mismatch: 0.5,
+    };
+
+    #[test]
+    fn quorum_of_agreeing_providers() {
+        let (primary, secondary) = agreeing_providers();
+        check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap();
+        assert_eq!(
+            *primary.leader_schedule_slots.lock().unwrap(),
+            vec![Some(10 * SLOTS_PER_EPOCH)]
+        );
+    }
+
+    #[test]
+    fn quorum_checks_the_requested_epoch() {
+        let (primary, mut secondary) = agreeing_providers();
+        check_rpc_quorum(&primary, &secondary, Some(9), &TOLERANCE).unwrap();
+        assert_eq!(
+            *primary.leader_schedule_slots.lock().unwrap(),
+            vec![Some(9 * SLOTS_PER_EPOCH)]
+        );
+        assert_eq!(
+            *secondary.leader_schedule_slots.lock().unwrap(),
+            vec![Some(9 * SLOTS_PER_EPOCH)]
+        );
+
+        // the current epoch agrees, the collected one does not
+        secondary.leader_schedules.insert(
+            9 * SLOTS_PER_EPOCH,
+            HashMap::from([("another-leader".to_string(), vec![0, 1, 2])]),
+        );
+        check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap();
+        let err = check_rpc_quorum(&primary, &secondary, Some(9), &TOLERANCE).unwrap_err();
+        assert!(err.to_string().contains("leader schedule of epoch 9"));
+    }
+
+    #[test]
+    fn quorum_tolerates_slots_and_vote_accounts() {
+        let (primary, mut secondary) = agreeing_providers();
+        secondary.absolute_slot += TOLERANCE.max_slot_difference;
+        secondary.vote_accounts = vec![("a", 100, 5), ("b", 201, 5)];
+        check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap();
+
+        secondary.absolute_slot += 1;
+        let err = check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap_err();
+        assert!(err.to_string().contains("4 slots apart"));
+
+        secondary.absolute_slot = primary.absolute_slot;
+        secondary.vote_accounts = vec![("a", 100, 5)];
+        check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap();
+        secondary.vote_accounts = vec![("a", 101, 5)];
+        let err = check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap_err();
+        assert!(err.to_string().contains("2 vote accounts"));
+    }
+
+    #[test]
+    fn quorum_requires_the_same_epoch() {
+        let (primary, mut secondary) = agreeing_providers();
+        secondary.epoch = 11;
+        let err = check_rpc_quorum(&primary, &secondary, None, &TOLERANCE).unwrap_err();
+        assert!(err.to_string().contains("disagree on epoch: 10 vs. 11"));
+    }
+}
@@ -0,0 +1,702 @@
+use crate::common::CommonParams;
+use crate::rpc_budget::{BudgetedRpc, RpcBudget};
+use crate::rpc_failover::{check_rpc_quorum, FailoverRpc, QuorumTolerance};
+use crate::solana_service::solana_client_with_timeout;
+use anyhow::Context;
+use log::{error, info};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_response::{
+        Response, RpcBlockProduction, RpcContactInfo, RpcInflationGovernor, RpcInflationRate,
+        RpcInflationReward, RpcLeaderSchedule, RpcSupply, RpcVoteAccountStatus,
+    },
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{
+    account::Account,
+    clock::{Epoch, Slot},
+    epoch_info::EpochInfo,
+    epoch_schedule::EpochSchedule,
+    pubkey::Pubkey,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// `ClientError` is large, it is boxed to keep the results of the RPC calls small.
+pub type RpcResult<T> = Result<T, Box<ClientError>>;
+
+/// RPC calls used by the collectors. Implemented by `RpcClient` and by the fixture
+/// recording/replaying clients so that collectors can run without a live cluster.
+pub trait SolanaRpc: Send + Sync {
+    fn url(&self) -> String;
+    fn commitment(&self) -> CommitmentConfig;
+    fn get_epoch_info(&self) -> RpcResult<EpochInfo>;
+    fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule>;
+    fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus>;
+    fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>>;
+    fn get_account_data(&self, pubkey: &Pubkey) -> RpcResult<Vec<u8>>;
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Response<Option<Account>>>;
+    fn get_program_accounts(&self, program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>>;
+    fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> RpcResult<Vec<(Pubkey, Account)>>;
+    fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>>;
+    fn get_leader_schedule_with_commitment(
+        &self,
+        slot: Option<Slot>,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<RpcLeaderSchedule>>;
+    fn supply(&self) -> RpcResult<Response<RpcSupply>>;
+    fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate>;
+    fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor>;
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>>;
+}
+
+impl SolanaRpc for RpcClient {
+    fn url(&self) -> String {
+        RpcClient::url(self)
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        RpcClient::commitment(self)
+    }
+
+    fn get_epoch_info(&self) -> RpcResult<EpochInfo> {
+        RpcClient::get_epoch_info(self).map_err(Box::new)
+    }
+
+    fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule> {
+        RpcClient::get_epoch_schedule(self).map_err(Box::new)
+    }
+
+    fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus> {
+        RpcClient::get_vote_accounts(self).map_err(Box::new)
+    }
+
+    fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
+        RpcClient::get_cluster_nodes(self).map_err(Box::new)
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> RpcResult<Vec<u8>> {
+        RpcClient::get_account_data(self, pubkey).map_err(Box::new)
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Response<Option<Account>>> {
+        RpcClient::get_account_with_commitment(self, pubkey, commitment).map_err(Box::new)
+    }
+
+    fn get_program_accounts(&self, program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>> {
+        RpcClient::get_program_accounts(self, program_id).map_err(Box::new)
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> RpcResult<Vec<(Pubkey, Account)>> {
+        RpcClient::get_program_accounts_with_config(self, program_id, config).map_err(Box::new)
+    }
+
+    fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>> {
+        RpcClient::get_block_production(self).map_err(Box::new)
+    }
+
+    fn get_leader_schedule_with_commitment(
+        &self,
+        slot: Option<Slot>,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<RpcLeaderSchedule>> {
+        RpcClient::get_leader_schedule_with_commitment(self, slot, commitment).map_err(Box::new)
+    }
+
+    fn supply(&self) -> RpcResult<Response<RpcSupply>> {
+        RpcClient::supply(self).map_err(Box::new)
+    }
+
+    fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+        RpcClient::get_inflation_rate(self).map_err(Box::new)
+    }
+
+    fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor> {
+        RpcClient::get_inflation_governor(self).map_err(Box::new)
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+        RpcClient::get_inflation_reward(self, addresses, epoch).map_err(Box::new)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct RpcFixture {
+    params: Value,
+    result: Value,
+}
+
+/// Fixtures are kept in one JSON file per RPC method, each holding the recorded params
+/// and results in the order of the calls.
+struct RpcFixtures {
+    dir: PathBuf,
+    fixtures: Mutex<HashMap<String, Vec<RpcFixture>>>,
+    /// Per method, which of the fixtures were replayed already
+    replayed: Mutex<HashMap<String, Vec<bool>>>,
+}
+
+fn fixture_error(message: String) -> Box<ClientError> {
+    Box::new(ClientErrorKind::Custom(message).into())
+}
+
+impl RpcFixtures {
+    fn new(dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            fixtures: Default::default(),
+            replayed: Default::default(),
+        })
+    }
+
+    fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut fixtures = HashMap::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(method) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    let method_fixtures: Vec<RpcFixture> =
+                        serde_json::from_reader(File::open(&path)?)?;
+                    fixtures.insert(method.to_string(), method_fixtures);
+                }
+            }
+        }
+        info!(
+            "Loaded RPC fixtures of {} methods from {}",
+            fixtures.len(),
+            dir.display()
+        );
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            fixtures: Mutex::new(fixtures),
+            replayed: Default::default(),
+        })
+    }
+
+    fn record<T: Serialize>(&self, method: &str, params: Value, result: &T) -> RpcResult<()> {
+        let result = serde_json::to_value(result).map_err(ClientError::from)?;
+        self.fixtures
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push(RpcFixture { params, result });
+
+        Ok(())
+    }
+
+    /// Writes the recorded fixtures, replacing the files of the recorded methods.
+    fn save(&self) -> anyhow::Result<()> {
+        let fixtures = self.fixtures.lock().unwrap();
+        for (method, method_fixtures) in fixtures.iter() {
+            let path = self.dir.join(format!("{method}.json"));
+            let file = File::create(&path)
+                .map_err(|err| anyhow::anyhow!("Failed to create {}: {err}", path.display()))?;
+            serde_json::to_writer(BufWriter::new(file), method_fixtures)?;
+        }
+        info!(
+            "Saved RPC fixtures of {} methods to {}",
+            fixtures.len(),
+            self.dir.display()
+        );
+
+        Ok(())
+    }
+
+    /// Serves the calls of a method in the recorded order: the first fixture with the same params
+    /// that was not replayed yet. Once all of them were, the last one is served again.
+    fn replay<T: DeserializeOwned>(&self, method: &str, params: &Value) -> RpcResult<T> {
+        let fixtures = self.fixtures.lock().unwrap();
+        let method_fixtures = fixtures.get(method).map(Vec::as_slice).unwrap_or_default();
+        let mut replayed = self.replayed.lock().unwrap();
+        let replayed = replayed
+            .entry(method.to_string())
+            .or_insert_with(|| vec![false; method_fixtures.len()]);
+
+        let matching: Vec<_> = method_fixtures
+            .iter()
+            .enumerate()
+            .filter(|(_, fixture)| &fixture.params == params)
+            .map(|(index, _)| index)
+            .collect();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|index| !replayed[*index])
+            .or(matching.last().copied())
+            .ok_or_else(|| fixture_error(format!("No fixture for {method} with {params}")))?;
+        replayed[index] = true;
+
+        Ok(
+            serde_json::from_value(method_fixtures[index].result.clone())
+                .map_err(ClientError::from)?,
+        )
+    }
+}
+
+/// Forwards calls to another client and records every successful response as a fixture.
+/// The fixtures are written once the client is dropped.
+pub struct RecordingRpc {
+    inner: Box<dyn SolanaRpc>,
+    fixtures: RpcFixtures,
+}
+
+impl RecordingRpc {
+    pub fn new(inner: Box<dyn SolanaRpc>, fixtures_dir: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            inner,
+            fixtures: RpcFixtures::new(fixtures_dir)?,
+        })
+    }
+
+    fn call<T: Serialize>(
+        &self,
+        method: &str,
+        params: Value,
+        make_call: impl FnOnce(&dyn SolanaRpc) -> RpcResult<T>,
+    ) -> RpcResult<T> {
+        let result = make_call(self.inner.as_ref())?;
+        self.fixtures.record(method, params, &result)?;
+        Ok(result)
+    }
+}
+
+impl Drop for RecordingRpc {
+    fn drop(&mut self) {
+        if let Err(err) = self.fixtures.save() {
+            error!("Failed to save the RPC fixtures: {err}");
+        }
+    }
+}
+
+/// Serves responses previously stored by `RecordingRpc`.
+pub struct ReplayRpc {
+    url: String,
+    commitment: CommitmentConfig,
+    fixtures: RpcFixtures,
+}
+
+impl ReplayRpc {
+    pub fn new(
+        url: String,
+        commitment: CommitmentConfig,
+        fixtures_dir: &Path,
+    ) -> anyhow::Result<Self> {
+        if !fixtures_dir.exists() {
+            anyhow::bail!(
+                "RPC fixtures directory {} does not exist",
+                fixtures_dir.display()
+            );
+        }
+        Ok(Self {
+            url,
+            commitment,
+            fixtures: RpcFixtures::load(fixtures_dir)?,
+        })
+    }
+}
+
+macro_rules! fixture_params {
+    ($($param:expr),*) => {
+        serde_json::to_value(($($param,)*)).map_err(ClientError::from)?
+    };
+}
+
+impl SolanaRpc for RecordingRpc {
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_epoch_info(&self) -> RpcResult<EpochInfo> {
+        self.call("get_epoch_info", fixture_params!(), |rpc| {
+            rpc.get_epoch_info()
+        })
+    }
+
+    fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule> {
+        self.call("get_epoch_schedule", fixture_params!(), |rpc| {
+            rpc.get_epoch_schedule()
+        })
+    }
+
+    fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus> {
+        self.call("get_vote_accounts", fixture_params!(), |rpc| {
+            rpc.get_vote_accounts()
+        })
+    }
+
+    fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
+        self.call("get_cluster_nodes", fixture_params!(), |rpc| {
+            rpc.get_cluster_nodes()
+        })
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> RpcResult<Vec<u8>> {
+        self.call("get_account_data", fixture_params!(pubkey), |rpc| {
+            rpc.get_account_data(pubkey)
+        })
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Response<Option<Account>>> {
+        self.call(
+            "get_account_with_commitment",
+            fixture_params!(pubkey, commitment),
+            |rpc| rpc.get_account_with_commitment(pubkey, commitment),
+        )
+    }
+
+    fn get_program_accounts(&self, program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.call("get_program_accounts", fixture_params!(program_id), |rpc| {
+            rpc.get_program_accounts(program_id)
+        })
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.call(
+            "get_program_accounts_with_config",
+            fixture_params!(program_id, &config),
+            |rpc| rpc.get_program_accounts_with_config(program_id, config),
+        )
+    }
+
+    fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>> {
+        self.call("get_block_production", fixture_params!(), |rpc| {
+            rpc.get_block_production()
+        })
+    }
+
+    fn get_leader_schedule_with_commitment(
+        &self,
+        slot: Option<Slot>,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<RpcLeaderSchedule>> {
+        self.call(
+            "get_leader_schedule_with_commitment",
+            fixture_params!(slot, commitment),
+            |rpc| rpc.get_leader_schedule_with_commitment(slot, commitment),
+        )
+    }
+
+    fn supply(&self) -> RpcResult<Response<RpcSupply>> {
+        self.call("supply", fixture_params!(), |rpc| rpc.supply())
+    }
+
+    fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+        self.call("get_inflation_rate", fixture_params!(), |rpc| {
+            rpc.get_inflation_rate()
+        })
+    }
+
+    fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor> {
+        self.call("get_inflation_governor", fixture_params!(), |rpc| {
+            rpc.get_inflation_governor()
+        })
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+        self.call(
+            "get_inflation_reward",
+            fixture_params!(addresses, epoch),
+            |rpc| rpc.get_inflation_reward(addresses, epoch),
+        )
+    }
+}
+
+impl SolanaRpc for ReplayRpc {
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+
+    fn get_epoch_info(&self) -> RpcResult<EpochInfo> {
+        self.fixtures.replay("get_epoch_info", &fixture_params!())
+    }
+
+    fn get_epoch_schedule(&self) -> RpcResult<EpochSchedule> {
+        self.fixtures
+            .replay("get_epoch_schedule", &fixture_params!())
+    }
+
+    fn get_vote_accounts(&self) -> RpcResult<RpcVoteAccountStatus> {
+        self.fixtures
+            .replay("get_vote_accounts", &fixture_params!())
+    }
+
+    fn get_cluster_nodes(&self) -> RpcResult<Vec<RpcContactInfo>> {
+        self.fixtures
+            .replay("get_cluster_nodes", &fixture_params!())
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> RpcResult<Vec<u8>> {
+        self.fixtures
+            .replay("get_account_data", &fixture_params!(pubkey))
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Response<Option<Account>>> {
+        self.fixtures.replay(
+            "get_account_with_commitment",
+            &fixture_params!(pubkey, commitment),
+        )
+    }
+
+    fn get_program_accounts(&self, program_id: &Pubkey) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.fixtures
+            .replay("get_program_accounts", &fixture_params!(program_id))
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> RpcResult<Vec<(Pubkey, Account)>> {
+        self.fixtures.replay(
+            "get_program_accounts_with_config",
+            &fixture_params!(program_id, &config),
+        )
+    }
+
+    fn get_block_production(&self) -> RpcResult<Response<RpcBlockProduction>> {
+        self.fixtures
+            .replay("get_block_production", &fixture_params!())
+    }
+
+    fn get_leader_schedule_with_commitment(
+        &self,
+        slot: Option<Slot>,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<RpcLeaderSchedule>> {
+        self.fixtures.replay(
+            "get_leader_schedule_with_commitment",
+            &fixture_params!(slot, commitment),
+        )
+    }
+
+    fn supply(&self) -> RpcResult<Response<RpcSupply>> {
+        self.fixtures.replay("supply", &fixture_params!())
+    }
+
+    fn get_inflation_rate(&self) -> RpcResult<RpcInflationRate> {
+        self.fixtures
+            .replay("get_inflation_rate", &fixture_params!())
+    }
+
+    fn get_inflation_governor(&self) -> RpcResult<RpcInflationGovernor> {
+        self.fixtures
+            .replay("get_inflation_governor", &fixture_params!())
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> RpcResult<Vec<Option<RpcInflationReward>>> {
+        self.fixtures
+            .replay("get_inflation_reward", &fixture_params!(addresses, epoch))
+    }
+}
+
+/// Creates the RPC client for collectors: live, recording into or replaying from fixtures.
+/// The RPC quorum is checked at the collected `epoch`, the current one when none is given.
+pub fn solana_rpc(
+    common_params: &CommonParams,
+    timeout: Duration,
+    epoch: Option<Epoch>,
+) -> anyhow::Result<Box<dyn SolanaRpc>> {
+    let rpc_urls = common_params.rpc_urls();
+    if rpc_urls.is_empty() {
+        anyhow::bail!("No RPC URL provided, use --url or RPC_URL");
+    }
+
+    let commitment = CommitmentConfig::from_str(&common_params.commitment).context(format!(
+        "Unknown commitment '{}', expected processed, confirmed or finalized",
+        common_params.commitment
+    ))?;
+
+    if let Some(fixtures_dir) = &common_params.rpc_replay {
+        info!("Replaying RPC responses from {}", fixtures_dir.display());
+        return Ok(Box::new(ReplayRpc::new(
+            rpc_urls[0].clone(),
+            commitment,
+            fixtures_dir,
+        )?));
+    }
+
+    let endpoints: Vec<Box<dyn SolanaRpc>> = rpc_urls
+        .iter()
+        .map(|url| -> Box<dyn SolanaRpc> {
+            Box::new(solana_client_with_timeout(
+                url.clone(),
+                timeout,
+                common_params.commitment.clone(),
+            ))
+        })
+        .collect();
+
+    if common_params.rpc_quorum {
+        if endpoints.len() < 2 {
+            anyhow::bail!("RPC quorum requires at least two RPC URLs");
+        }
+        check_rpc_quorum(
+            endpoints[0].as_ref(),
+            endpoints[1].as_ref(),
+            epoch,
+            &QuorumTolerance {
+                max_slot_difference: common_params.rpc_quorum_slot_tolerance,
+                max_vote_accounts_mismatch: common_params.rpc_quorum_vote_accounts_tolerance,
+            },
+        )?;
+    }
+
+    let client: Box<dyn SolanaRpc> = if endpoints.len() == 1 {
+        endpoints.into_iter().next().unwrap()
+    } else {
+        Box::new(FailoverRpc::new(
+            endpoints,
+            common_params.rpc_failover_after,
+        ))
+    };
+    let client: Box<dyn SolanaRpc> = Box::new(BudgetedRpc::new(
+        client,
+        RpcBudget::new(common_params.rpc_rps, common_params.rpc_concurrency),
+        common_params.rpc_rate_limit_retries,
+        Duration::from_secs(common_params.rpc_rate_limit_backoff),
+    ));
+    match &common_params.rpc_record {
+        Some(fixtures_dir) => {
+            info!("Recording RPC responses to {}", fixtures_dir.display());
+            Ok(Box::new(RecordingRpc::new(client, fixtures_dir)?))
+        }
+        None => Ok(client),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solana_rpc, RpcFixtures};
+    use crate::common::CommonParams;
+    use serde_json::json;
+    use solana_sdk::clock::Epoch;
+    use std::time::Duration;
+    use structopt::StructOpt;
+
+    #[test]
+    fn unknown_commitment_is_an_error() {
+        let params = CommonParams::from_iter([
+            "collect",
+            "--url",
+            "http://localhost:8899",
+            "--commitment",
+            "final",
+        ]);
+        let err = solana_rpc(&params, Duration::from_secs(1), None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Unknown commitment 'final'"));
+    }
+
+    #[test]
+    fn recorded_fixtures_are_replayed_from_disk() {
+        let dir = std::env::temp_dir().join(format!("rpc-fixtures-{}", std::process::id()));
+        let recorded = RpcFixtures::new(&dir).unwrap();
+        let epoch: Epoch = 700;
+        recorded.record("get_epoch", json!([1]), &epoch).unwrap();
+        recorded.save().unwrap();
+
+        let replayed = RpcFixtures::load(&dir).unwrap();
+        let replayed_epoch: Epoch = replayed.replay("get_epoch", &json!([1])).unwrap();
+        assert_eq!(replayed_epoch, epoch);
+        assert!(replayed.replay::<Epoch>("get_epoch", &json!([2])).is_err());
+        assert!(replayed.replay::<Epoch>("get_slot", &json!([1])).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn repeated_calls_are_replayed_in_order() {
+        let dir = std::env::temp_dir().join(format!("rpc-fixtures-order-{}", std::process::id()));
+        let recorded = RpcFixtures::new(&dir).unwrap();
+        for (params, epoch) in [
+            (json!([]), 700),
+            (json!([]), 701),
+            (json!([5]), 5),
+            (json!([]), 702),
+        ] {
+            recorded.record("get_epoch", params, &epoch).unwrap();
+        }
+        recorded.save().unwrap();
+
+        let replayed = RpcFixtures::load(&dir).unwrap();
+        let replay = |params| replayed.replay::<Epoch>("get_epoch", &params).unwrap();
+        assert_eq!(replay(json!([])), 700);
+        // calls with other params are matched by their params, wherever they were recorded
+        assert_eq!(replay(json!([5])), 5);
+        assert_eq!(replay(json!([])), 701);
+        assert_eq!(replay(json!([])), 702);
+        // once the recorded calls ran out, the last response is served again
+        assert_eq!(replay(json!([])), 702);
+        assert_eq!(replay(json!([5])), 5);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
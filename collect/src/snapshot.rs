@@ -0,0 +1,109 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Yaml,
+    Json,
+    JsonZst,
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "yaml" => Ok(SnapshotFormat::Yaml),
+            "json" => Ok(SnapshotFormat::Json),
+            "json.zst" => Ok(SnapshotFormat::JsonZst),
+            _ => {
+                anyhow::bail!("Unknown snapshot format '{format}', expected yaml, json or json.zst")
+            }
+        }
+    }
+}
+
+impl fmt::Display for SnapshotFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotFormat::Yaml => write!(f, "yaml"),
+            SnapshotFormat::Json => write!(f, "json"),
+            SnapshotFormat::JsonZst => write!(f, "json.zst"),
+        }
+    }
+}
+
+impl SnapshotFormat {
+    /// Zstandard frames start with a magic number, JSON snapshots with an object, anything else is YAML.
+    pub fn detect(data: &[u8]) -> SnapshotFormat {
+        if data.starts_with(&ZSTD_MAGIC) {
+            return SnapshotFormat::JsonZst;
+        }
+        match data.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => SnapshotFormat::Json,
+            _ => SnapshotFormat::Yaml,
+        }
+    }
+}
+
+pub fn write_snapshot<T: Serialize>(
+    writer: impl Write,
+    format: SnapshotFormat,
+    snapshot: &T,
+) -> anyhow::Result<()> {
+    match format {
+        SnapshotFormat::Yaml => serde_yaml::to_writer(writer, snapshot)?,
+        SnapshotFormat::Json => {
+            let mut writer = BufWriter::new(writer);
+            serde_json::to_writer(&mut writer, snapshot)?;
+            writer.flush()?;
+        }
+        SnapshotFormat::JsonZst => {
+            let mut encoder = zstd::Encoder::new(writer, ZSTD_LEVEL)?;
+            serde_json::to_writer(&mut encoder, snapshot)?;
+            encoder.finish()?.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn read_snapshot<T: DeserializeOwned>(path: impl AsRef<Path>) -> anyhow::Result<T> {
+    parse_snapshot(&std::fs::read(path)?)
+}
+
+pub fn parse_snapshot<T: DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+    let snapshot = match SnapshotFormat::detect(data) {
+        SnapshotFormat::Yaml => serde_yaml::from_slice(data)?,
+        SnapshotFormat::Json => serde_json::from_slice(data)?,
+        SnapshotFormat::JsonZst => serde_json::from_slice(&zstd::decode_all(data)?)?,
+    };
+
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_snapshot, SnapshotFormat};
+    use std::collections::HashMap;
+
+    #[test]
+    fn written_snapshots_are_detected() {
+        let snapshot: HashMap<String, u64> = [("epoch".to_string(), 700)].into();
+        for format in [
+            SnapshotFormat::Yaml,
+            SnapshotFormat::Json,
+            SnapshotFormat::JsonZst,
+        ] {
+            let mut data = vec![];
+            write_snapshot(&mut data, format, &snapshot).unwrap();
+            assert_eq!(SnapshotFormat::detect(&data), format);
+        }
+    }
+}
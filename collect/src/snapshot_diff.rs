@@ -0,0 +1,455 @@
+use crate::snapshot::read_snapshot;
+use crate::validators::{Snapshot, ValidatorDataCenter};
+use crate::validators_jito::{
+    JitoAccountType, JitoSnapshot, ValidatorSnapshot as JitoValidatorSnapshot,
+};
+use crate::validators_performance::ValidatorsPerformanceSnapshot;
+use log::info;
+use serde::Serialize;
+use serde_json::Value;
+use solana_sdk::clock::Epoch;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+pub struct DiffParams {
+    #[structopt(
+        help = "Older snapshot file (validators, validators-performance, jito-mev or jito-priority)."
+    )]
+    snapshot_a: PathBuf,
+
+    #[structopt(help = "Newer snapshot file of the same kind.")]
+    snapshot_b: PathBuf,
+
+    #[structopt(
+        long = "stake-threshold",
+        help = "Report stake changes larger than this share of the older stake (0-1).",
+        default_value = "0.05"
+    )]
+    stake_threshold: f64,
+
+    #[structopt(long = "json", help = "Print the differences as JSON.")]
+    json: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SnapshotKind {
+    Validators,
+    ValidatorsPerformance,
+    JitoMev,
+    JitoPriority,
+}
+
+impl fmt::Display for SnapshotKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotKind::Validators => write!(f, "validators"),
+            SnapshotKind::ValidatorsPerformance => write!(f, "validators-performance"),
+            SnapshotKind::JitoMev => write!(f, "jito-mev"),
+            SnapshotKind::JitoPriority => write!(f, "jito-priority"),
+        }
+    }
+}
+
+#[derive(Default, Clone, PartialEq)]
+struct ComparedValidator {
+    stake: Option<u64>,
+    commission: Option<u8>,
+    version: Option<String>,
+    data_center: Option<String>,
+    delinquent: Option<bool>,
+    jito_commission: Option<u16>,
+}
+
+struct ComparedSnapshot {
+    kind: SnapshotKind,
+    epoch: Epoch,
+    validators: BTreeMap<String, ComparedValidator>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidatorChange {
+    pub vote_account: String,
+    pub field: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotDiff {
+    pub kind: SnapshotKind,
+    pub epoch_a: Epoch,
+    pub epoch_b: Epoch,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changes: Vec<ValidatorChange>,
+}
+
+fn data_center_key(data_center: &ValidatorDataCenter) -> String {
+    format!(
+        "{}/{}/{}",
+        data_center
+            .asn
+            .map(|asn| asn.to_string())
+            .unwrap_or_default(),
+        data_center.country_iso.clone().unwrap_or_default(),
+        data_center.city.clone().unwrap_or_default()
+    )
+}
+
+fn detect_snapshot_kind(snapshot: &Value) -> anyhow::Result<SnapshotKind> {
+    if let Some(account_type) = snapshot.get("account_type") {
+        Ok(
+            match serde_json::from_value::<JitoAccountType>(account_type.clone())? {
+                JitoAccountType::MevTipDistribution => SnapshotKind::JitoMev,
+                JitoAccountType::PriorityFeeDistribution => SnapshotKind::JitoPriority,
+            },
+        )
+    } else if snapshot.get("epoch_slot").is_some() {
+        Ok(SnapshotKind::ValidatorsPerformance)
+    } else if snapshot.get("validators").is_some_and(Value::is_array) {
+        Ok(SnapshotKind::Validators)
+    } else {
+        anyhow::bail!("Unsupported snapshot kind")
+    }
+}
+
+fn load_compared_snapshot(path: &PathBuf) -> anyhow::Result<ComparedSnapshot> {
+    let snapshot: Value = read_snapshot(path)?;
+    let kind = detect_snapshot_kind(&snapshot)
+        .map_err(|e| anyhow::anyhow!("Failed to read '{}': {e}", path.display()))?;
+    info!("Loaded {kind} snapshot from {}", path.display());
+
+    Ok(match kind {
+        SnapshotKind::Validators => {
+            let snapshot: Snapshot = serde_json::from_value(snapshot)?;
+            ComparedSnapshot {
+                kind,
+                epoch: snapshot.epoch,
+                validators: snapshot
+                    .validators
+                    .into_iter()
+                    .map(|v| {
+                        (
+                            v.vote_account,
+                            ComparedValidator {
+                                stake: Some(v.activated_stake),
+                                commission: Some(v.performance.commission),
+                                version: v.performance.version,
+                                data_center: v.data_center.as_ref().map(data_center_key),
+                                delinquent: Some(v.performance.delinquent),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            }
+        }
+        SnapshotKind::ValidatorsPerformance => {
+            let snapshot: ValidatorsPerformanceSnapshot = serde_json::from_value(snapshot)?;
+            ComparedSnapshot {
+                kind,
+                epoch: snapshot.epoch,
+                validators: snapshot
+                    .validators
+                    .into_iter()
+                    .map(|(vote_account, v)| {
+                        (
+                            vote_account,
+                            ComparedValidator {
+                                commission: Some(v.commission),
+                                version: v.version,
+                                delinquent: Some(v.delinquent),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            }
+        }
+        SnapshotKind::JitoMev | SnapshotKind::JitoPriority => {
+            let snapshot: JitoSnapshot = serde_json::from_value(snapshot)?;
+            ComparedSnapshot {
+                kind,
+                epoch: snapshot.epoch,
+                validators: snapshot
+                    .validators
+                    .into_iter()
+                    .map(|(vote_account, v)| {
+                        let jito_commission = match v {
+                            JitoValidatorSnapshot::MevTipDistribution(v) => v.mev_commission,
+                            JitoValidatorSnapshot::PriorityFeeDistribution(v) => {
+                                v.priority_commission
+                            }
+                        };
+                        (
+                            vote_account,
+                            ComparedValidator {
+                                jito_commission: Some(jito_commission),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect(),
+            }
+        }
+    })
+}
+
+fn push_change<T: ToString + PartialEq>(
+    changes: &mut Vec<ValidatorChange>,
+    vote_account: &str,
+    field: &str,
+    from: &Option<T>,
+    to: &Option<T>,
+) {
+    if from != to {
+        changes.push(ValidatorChange {
+            vote_account: vote_account.to_string(),
+            field: field.to_string(),
+            from: from.as_ref().map(|value| value.to_string()),
+            to: to.as_ref().map(|value| value.to_string()),
+        });
+    }
+}
+
+fn diff_snapshots(
+    a: &ComparedSnapshot,
+    b: &ComparedSnapshot,
+    stake_threshold: f64,
+) -> anyhow::Result<SnapshotDiff> {
+    if a.kind != b.kind {
+        anyhow::bail!(
+            "Cannot compare {} snapshot with {} snapshot",
+            a.kind,
+            b.kind
+        );
+    }
+
+    let vote_accounts_a: BTreeSet<_> = a.validators.keys().collect();
+    let vote_accounts_b: BTreeSet<_> = b.validators.keys().collect();
+
+    let mut changes = vec![];
+    for vote_account in vote_accounts_a.intersection(&vote_accounts_b) {
+        let validator_a = &a.validators[*vote_account];
+        let validator_b = &b.validators[*vote_account];
+        if validator_a == validator_b {
+            continue;
+        }
+
+        if let (Some(stake_a), Some(stake_b)) = (validator_a.stake, validator_b.stake) {
+            let stake_change = stake_a.abs_diff(stake_b) as f64 / stake_a.max(1) as f64;
+            if stake_change > stake_threshold {
+                push_change(
+                    &mut changes,
+                    vote_account,
+                    "stake",
+                    &validator_a.stake,
+                    &validator_b.stake,
+                );
+            }
+        }
+        push_change(
+            &mut changes,
+            vote_account,
+            "commission",
+            &validator_a.commission,
+            &validator_b.commission,
+        );
+        push_change(
+            &mut changes,
+            vote_account,
+            "version",
+            &validator_a.version,
+            &validator_b.version,
+        );
+        push_change(
+            &mut changes,
+            vote_account,
+            "data_center",
+            &validator_a.data_center,
+            &validator_b.data_center,
+        );
+        push_change(
+            &mut changes,
+            vote_account,
+            "delinquent",
+            &validator_a.delinquent,
+            &validator_b.delinquent,
+        );
+        push_change(
+            &mut changes,
+            vote_account,
+            "jito_commission",
+            &validator_a.jito_commission,
+            &validator_b.jito_commission,
+        );
+    }
+
+    Ok(SnapshotDiff {
+        kind: a.kind,
+        epoch_a: a.epoch,
+        epoch_b: b.epoch,
+        added: vote_accounts_b
+            .difference(&vote_accounts_a)
+            .map(|vote_account| vote_account.to_string())
+            .collect(),
+        removed: vote_accounts_a
+            .difference(&vote_accounts_b)
+            .map(|vote_account| vote_account.to_string())
+            .collect(),
+        changes,
+    })
+}
+
+fn print_diff(diff: &SnapshotDiff) {
+    println!(
+        "Comparing {} snapshots of epochs {} and {}",
+        diff.kind, diff.epoch_a, diff.epoch_b
+    );
+
+    println!("Added validators: {}", diff.added.len());
+    for vote_account in diff.added.iter() {
+        println!("  + {vote_account}");
+    }
+
+    println!("Removed validators: {}", diff.removed.len());
+    for vote_account in diff.removed.iter() {
+        println!("  - {vote_account}");
+    }
+
+    println!("Changes: {}", diff.changes.len());
+    for change in diff.changes.iter() {
+        println!(
+            "  {} {}: {} -> {}",
+            change.vote_account,
+            change.field,
+            change.from.as_deref().unwrap_or("none"),
+            change.to.as_deref().unwrap_or("none")
+        );
+    }
+}
+
+pub fn diff_snapshot_files(diff_params: DiffParams) -> anyhow::Result<()> {
+    let a = load_compared_snapshot(&diff_params.snapshot_a)?;
+    let b = load_compared_snapshot(&diff_params.snapshot_b)?;
+    let diff = diff_snapshots(&a, &b, diff_params.stake_threshold)?;
+
+    if diff_params.json {
+        serde_json::to_writer_pretty(std::io::stdout(), &diff)?;
+        println!();
+    } else {
+        print_diff(&diff);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        detect_snapshot_kind, diff_snapshots, ComparedSnapshot, ComparedValidator, SnapshotKind,
+    };
+    use serde_json::json;
+    use solana_sdk::clock::Epoch;
+
+    fn compared_snapshot(
+        kind: SnapshotKind,
+        epoch: Epoch,
+        validators: &[(&str, ComparedValidator)],
+    ) -> ComparedSnapshot {
+        ComparedSnapshot {
+            kind,
+            epoch,
+            validators: validators
+                .iter()
+                .map(|(vote_account, v)| (vote_account.to_string(), v.clone()))
+                .collect(),
+        }
+    }
+
+    fn validator(stake: u64, commission: u8, version: &str) -> ComparedValidator {
+        ComparedValidator {
+            stake: Some(stake),
+            commission: Some(commission),
+            version: Some(version.to_string()),
+            delinquent: Some(false),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn jito_snapshot_kinds() {
+        assert_eq!(
+            detect_snapshot_kind(&json!({"account_type": "MevTipDistribution"})).unwrap(),
+            SnapshotKind::JitoMev
+        );
+        assert_eq!(
+            detect_snapshot_kind(&json!({"account_type": "PriorityFeeDistribution"})).unwrap(),
+            SnapshotKind::JitoPriority
+        );
+        assert!(detect_snapshot_kind(&json!({"account_type": "Unknown"})).is_err());
+    }
+
+    #[test]
+    fn diff_of_validators() {
+        let a = compared_snapshot(
+            SnapshotKind::Validators,
+            800,
+            &[
+                ("kept", validator(1000, 5, "2.2.14")),
+                ("changed", validator(1000, 5, "2.2.14")),
+                ("removed", validator(1000, 5, "2.2.14")),
+            ],
+        );
+        let b = compared_snapshot(
+            SnapshotKind::Validators,
+            801,
+            &[
+                ("kept", validator(1040, 5, "2.2.14")),
+                ("changed", validator(2000, 10, "2.3.1")),
+                ("added", validator(1000, 5, "2.2.14")),
+            ],
+        );
+
+        let diff = diff_snapshots(&a, &b, 0.05).unwrap();
+        assert_eq!(diff.kind, SnapshotKind::Validators);
+        assert_eq!((diff.epoch_a, diff.epoch_b), (800, 801));
+        assert_eq!(diff.added, vec!["added"]);
+        assert_eq!(diff.removed, vec!["removed"]);
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| {
+                (
+                    change.vote_account.as_str(),
+                    change.field.as_str(),
+                    change.from.as_deref(),
+                    change.to.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("changed", "stake", Some("1000"), Some("2000")),
+                ("changed", "commission", Some("5"), Some("10")),
+                ("changed", "version", Some("2.2.14"), Some("2.3.1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_mismatched_kinds() {
+        let mev = compared_snapshot(SnapshotKind::JitoMev, 800, &[]);
+        let priority = compared_snapshot(SnapshotKind::JitoPriority, 800, &[]);
+        let validators = compared_snapshot(SnapshotKind::Validators, 800, &[]);
+
+        assert!(diff_snapshots(&mev, &priority, 0.05).is_err());
+        assert!(diff_snapshots(&validators, &mev, 0.05).is_err());
+        assert!(diff_snapshots(&mev, &mev, 0.05).is_ok());
+    }
+}
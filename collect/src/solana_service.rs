@@ -1,7 +1,10 @@
+use crate::common::map_concurrently;
 use crate::common::retry_blocking;
 use crate::common::QuadraticBackoffStrategy;
 use crate::marinade_service::fetch_bonds;
+use crate::rpc_service::SolanaRpc;
 use crate::validators::*;
+use crate::validators_performance::NodeClient;
 use bincode::deserialize;
 use log::{info, warn};
 use rust_decimal::{prelude::ToPrimitive, Decimal};
@@ -32,11 +35,10 @@ use solana_stake_interface::{self as stake, state::StakeStateV2};
 use std::{
     collections::{HashMap, HashSet},
     str::FromStr,
-    thread::sleep,
     time::Duration,
 };
 
-const RPC_STAKE_ACCOUNTS_FETCH_BACKOFF_MS: u64 = 200;
+const INFLATION_REWARD_ADDRESSES_PER_REQUEST: usize = 100;
 const WITHDRAW_AUTHORITY_OFFSET: usize = 4 + 8 + 32;
 
 pub fn solana_client(url: String, commitment: String) -> RpcClient {
@@ -51,13 +53,16 @@ pub fn solana_client_with_timeout(url: String, timeout: Duration, commitment: St
     )
 }
 
-pub fn get_stake_history(rpc_client: &RpcClient) -> anyhow::Result<StakeHistory> {
+pub fn get_stake_history(rpc_client: &dyn SolanaRpc) -> anyhow::Result<StakeHistory> {
     Ok(bincode::deserialize(
         &rpc_client.get_account_data(&stake_history::ID)?,
     )?)
 }
 
-pub fn get_credits(rpc_client: &RpcClient, epoch: Epoch) -> anyhow::Result<HashMap<String, u64>> {
+pub fn get_credits(
+    rpc_client: &dyn SolanaRpc,
+    epoch: Epoch,
+) -> anyhow::Result<HashMap<String, u64>> {
     info!("Getting credits");
     let vote_accounts = rpc_client.get_vote_accounts()?;
 
@@ -81,9 +86,16 @@ pub fn get_credits(rpc_client: &RpcClient, epoch: Epoch) -> anyhow::Result<HashM
     Ok(credits)
 }
 
+pub struct NodeVersion {
+    pub version: String,
+    /// Extra info following the version, e.g. the client id
+    pub extra: Option<String>,
+    pub feature_set: Option<u32>,
+}
+
 pub fn get_cluster_nodes_versions(
-    rpc_client: &RpcClient,
-) -> anyhow::Result<HashMap<String, String>> {
+    rpc_client: &dyn SolanaRpc,
+) -> anyhow::Result<HashMap<String, NodeVersion>> {
     info!("Getting cluster nodes versions");
     let cluster_nodes = rpc_client.get_cluster_nodes()?;
 
@@ -91,16 +103,16 @@ pub fn get_cluster_nodes_versions(
         .iter()
         .filter_map(|node| {
             node.version.clone().and_then(|version| {
-                let version = version
-                    .split_once(char::is_whitespace)
-                    .map(|(version, extra)| {
+                let (version, extra) = match version.split_once(char::is_whitespace) {
+                    Some((version, extra)) => {
                         warn!(
                             "Node {} has version: {version} with extra info: {extra}",
                             node.pubkey
                         );
-                        version.to_string()
-                    })
-                    .unwrap_or(version);
+                        (version.to_string(), Some(extra.to_string()))
+                    }
+                    None => (version, None),
+                };
                 if !is_plausible_node_version(&version) {
                     warn!(
                         "Node {} reports malformed version: '{version}', ignoring",
@@ -108,12 +120,50 @@ pub fn get_cluster_nodes_versions(
                     );
                     return None;
                 }
-                Some((node.pubkey.clone(), version))
+                Some((
+                    node.pubkey.clone(),
+                    NodeVersion {
+                        version,
+                        extra,
+                        feature_set: node.feature_set,
+                    },
+                ))
             })
         })
         .collect())
 }
 
+// Gossip carries the client id, but the RPC only passes it on in the extra info of the version
+// (e.g. `(src:1a2b3c4d; feat:3294202862, client:JitoLabs)`) when the node is on a release that reports it.
+// Without it only Firedancer tells by its version, Jito-Solana reports the Agave release it is based on
+// and is told by the tip distribution account it creates for the epoch, other nodes stay unknown.
+// The feature set is no help: Jito-Solana runs the features of the Agave release it is based on and Firedancer
+// runs the ones of the Agave release it is compatible with, so nodes of any client share the same feature sets.
+pub fn classify_node_client(
+    version: &str,
+    extra: Option<&str>,
+    has_tip_distribution: bool,
+) -> Option<NodeClient> {
+    let client_id = extra.and_then(|extra| {
+        extra.split_once("client:").map(|(_, client_id)| {
+            client_id
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .next()
+                .unwrap_or_default()
+        })
+    });
+    match client_id {
+        Some("Agave") => Some(NodeClient::Agave),
+        Some("JitoLabs") => Some(NodeClient::JitoSolana),
+        Some("Firedancer") | Some("Frankendancer") => Some(NodeClient::Firedancer),
+        Some(_) => Some(NodeClient::Other),
+        // Firedancer releases are numbered 0.x, they may run the Jito tip distribution too
+        None if version.starts_with("0.") => Some(NodeClient::Firedancer),
+        None if has_tip_distribution => Some(NodeClient::JitoSolana),
+        None => None,
+    }
+}
+
 // A malformed gossip version is dropped so store never replaces the last known good version with it.
 fn is_plausible_node_version(version: &str) -> bool {
     let numeric = |p: &str| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit());
@@ -132,7 +182,9 @@ fn is_plausible_node_version(version: &str) -> bool {
         })
 }
 
-pub fn get_cluster_nodes_ips(rpc_client: &RpcClient) -> anyhow::Result<HashMap<String, String>> {
+pub fn get_cluster_nodes_ips(
+    rpc_client: &dyn SolanaRpc,
+) -> anyhow::Result<HashMap<String, String>> {
     info!("Getting cluster nodes IPs");
     let cluster_nodes = rpc_client.get_cluster_nodes()?;
 
@@ -186,7 +238,7 @@ pub fn get_minimum_superminority_stake(vote_accounts: &RpcVoteAccountStatus) ->
 }
 
 pub fn get_block_production_by_validator(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     epoch: Epoch,
 ) -> anyhow::Result<HashMap<String, (usize, usize)>> {
     info!("Getting block production by validator");
@@ -231,7 +283,7 @@ pub fn get_block_production_by_validator(
 }
 
 fn get_confirmed_blocks(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     start_slot: Slot,
     end_slot: Slot,
 ) -> anyhow::Result<HashSet<Slot>> {
@@ -280,7 +332,7 @@ fn parse_validator_info(
     }
 }
 pub fn get_validators_info(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
 ) -> anyhow::Result<HashMap<String, ValidatorInfo>> {
     info!("Getting validator info");
     let validator_info = rpc_client.get_program_accounts(&solana_config_program::id())?;
@@ -307,7 +359,7 @@ fn extract_json_value(json: &Map<String, Value>, key: String) -> Option<String>
 }
 
 pub fn get_apy(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     vote_accounts: &RpcVoteAccountStatus,
     credits: &HashMap<String, u64>,
 ) -> anyhow::Result<HashMap<String, f64>> {
@@ -369,7 +421,7 @@ pub fn get_apy(
 
 // Relies on vote account layout and needs updating in case the authorized withdrawer position would change
 pub fn get_withdraw_authorities(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
 ) -> anyhow::Result<HashSet<(String, String)>> {
     let mut withdraw_authorities: HashSet<(String, String)> = HashSet::default();
     let vote_program_id = solana_vote_program::id();
@@ -394,9 +446,10 @@ pub fn get_withdraw_authorities(
 }
 
 pub fn get_commission_from_inflation_rewards(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     vote_accounts: &RpcVoteAccountStatus,
     epoch: Option<Epoch>,
+    rpc_concurrency: usize,
 ) -> anyhow::Result<HashMap<String, u8>> {
     let vote_addresses: Vec<_> = vote_accounts
         .current
@@ -404,9 +457,17 @@ pub fn get_commission_from_inflation_rewards(
         .chain(vote_accounts.delinquent.iter())
         .map(|v| Pubkey::from_str(&v.vote_pubkey).unwrap())
         .collect();
+    let vote_addresses_chunks: Vec<_> = vote_addresses
+        .chunks(INFLATION_REWARD_ADDRESSES_PER_REQUEST)
+        .collect();
+    let rewards_chunks = map_concurrently(
+        &vote_addresses_chunks,
+        rpc_concurrency,
+        |vote_addresses_chunk| rpc_client.get_inflation_reward(vote_addresses_chunk, epoch),
+    )?;
+
     let mut result: HashMap<String, u8> = Default::default();
-    for vote_addresses_chunk in vote_addresses.chunks(100) {
-        let rewards = rpc_client.get_inflation_reward(vote_addresses_chunk, epoch)?;
+    for (vote_addresses_chunk, rewards) in vote_addresses_chunks.iter().zip(rewards_chunks) {
         result.extend(vote_addresses_chunk.iter().zip(rewards).filter_map(
             |(vote_address, reward)| {
                 if let Some(reward) = reward {
@@ -423,23 +484,13 @@ pub fn get_commission_from_inflation_rewards(
     Ok(result)
 }
 
-pub fn get_self_stake(
-    rpc_client: &RpcClient,
+/// Adds the funded amounts of the bonds to the self stake fetched by `fetch_self_stake`.
+pub fn add_bonds_to_self_stake(
+    self_stake: &mut HashMap<String, u64>,
     epoch: Epoch,
-    stake_history: &StakeHistory,
     bonds_url: &str,
     allow_zero_funded_bonds: bool,
-    rpc_attempts: usize,
-) -> anyhow::Result<HashMap<String, u64>> {
-    let withdraw_authorities = get_withdraw_authorities(rpc_client)?;
-    let mut self_stake = fetch_self_stake(
-        rpc_client,
-        withdraw_authorities,
-        epoch,
-        stake_history,
-        rpc_attempts,
-    )?;
-
+) -> anyhow::Result<()> {
     assert!(!self_stake.is_empty(), "Failed to fetch self stake data");
 
     let bonds = fetch_bonds(bonds_url)?;
@@ -473,11 +524,11 @@ pub fn get_self_stake(
             .ok_or_else(|| anyhow::anyhow!("Failed to convert Bond Decimal value to u64"))?;
         *self_stake.entry(bond.vote_account).or_insert(0) += funded_amount_u64;
     }
-    Ok(self_stake)
+    Ok(())
 }
 
 fn fetch_stake_accounts_on_page(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     page: u8,
     rpc_attempts: usize,
 ) -> Result<Vec<(Pubkey, Account)>, Box<ClientError>> {
@@ -567,31 +618,36 @@ fn update_self_stake(self_stake: &mut HashMap<String, u64>, vote_key: &str, lamp
 }
 
 pub fn fetch_self_stake(
-    rpc_client: &RpcClient,
+    rpc_client: &dyn SolanaRpc,
     withdraw_authorities: HashSet<(String, String)>,
     epoch: Epoch,
     stake_history: &StakeHistory,
     rpc_attemtps: usize,
+    rpc_concurrency: usize,
 ) -> anyhow::Result<HashMap<String, u64>> {
+    let pages: Vec<u8> = (0..=u8::MAX).collect();
+    let self_stake_by_page = map_concurrently(&pages, rpc_concurrency, |page| {
+        let accounts =
+            fetch_stake_accounts_on_page(rpc_client, *page, rpc_attemtps).map_err(|err| {
+                anyhow::anyhow!("Failed to fetch stake accounts on page {page}: {err}")
+            })?;
+        let mut page_self_stake: HashMap<String, u64> = HashMap::default();
+        let processed = process_accounts_for_self_stake(
+            accounts,
+            &mut page_self_stake,
+            &withdraw_authorities,
+            epoch,
+            stake_history,
+        );
+        info!("Processed {processed} self stakes on page {page}");
+        Ok::<_, anyhow::Error>(page_self_stake)
+    })?;
+
     let mut self_stake: HashMap<String, u64> = HashMap::default();
-    for page in 0..=u8::MAX {
-        match fetch_stake_accounts_on_page(rpc_client, page, rpc_attemtps) {
-            Ok(accounts) => {
-                let processed = process_accounts_for_self_stake(
-                    accounts,
-                    &mut self_stake,
-                    &withdraw_authorities,
-                    epoch,
-                    stake_history,
-                );
-                info!("Processed {processed} self stakes on page {page}");
-            }
-            Err(err) => {
-                panic!("Failed to fetch stake accounts on page {page}: {err}");
-            }
+    for page_self_stake in self_stake_by_page {
+        for (vote_key, lamports) in page_self_stake {
+            update_self_stake(&mut self_stake, &vote_key, lamports);
         }
-
-        sleep(Duration::from_millis(RPC_STAKE_ACCOUNTS_FETCH_BACKOFF_MS));
     }
 
     Ok(self_stake)
@@ -599,7 +655,8 @@ pub fn fetch_self_stake(
 
 #[cfg(test)]
 mod tests {
-    use super::is_plausible_node_version;
+    use super::{classify_node_client, is_plausible_node_version};
+    use crate::validators_performance::NodeClient;
 
     #[test]
     fn plausible_node_versions() {
@@ -619,4 +676,66 @@ mod tests {
         assert!(!is_plausible_node_version("4.1.0-rc/1"));
         assert!(!is_plausible_node_version("4.1.0.1"));
     }
+
+    #[test]
+    fn node_clients() {
+        assert_eq!(
+            classify_node_client(
+                "2.2.14",
+                Some("(src:00000000; feat:3294202862, client:JitoLabs)"),
+                false
+            ),
+            Some(NodeClient::JitoSolana)
+        );
+        assert_eq!(
+            classify_node_client(
+                "2.2.14",
+                Some("(src:00000000; feat:3294202862, client:Agave)"),
+                true
+            ),
+            Some(NodeClient::Agave)
+        );
+        assert_eq!(
+            classify_node_client("0.505.20216", Some("client:Frankendancer"), true),
+            Some(NodeClient::Firedancer)
+        );
+        assert_eq!(
+            classify_node_client(
+                "1.18.26",
+                Some("(src:00000000; feat:4215500110, client:SolanaLabs)"),
+                false
+            ),
+            Some(NodeClient::Other)
+        );
+        assert_eq!(
+            classify_node_client("2.2.14", Some("(client:Unknown(7))"), false),
+            Some(NodeClient::Other)
+        );
+    }
+
+    #[test]
+    fn node_clients_without_client_id() {
+        assert_eq!(classify_node_client("2.2.14", None, false), None);
+        // the feature set is shared by the clients running the same release
+        assert_eq!(
+            classify_node_client("2.2.14", Some("(src:00000000; feat:3294202862)"), false),
+            None
+        );
+        assert_eq!(
+            classify_node_client("2.2.14", Some("(src:00000000; feat:3294202862)"), true),
+            Some(NodeClient::JitoSolana)
+        );
+        assert_eq!(
+            classify_node_client("2.2.14", None, true),
+            Some(NodeClient::JitoSolana)
+        );
+        assert_eq!(
+            classify_node_client("0.505.20216", None, false),
+            Some(NodeClient::Firedancer)
+        );
+        assert_eq!(
+            classify_node_client("0.505.20216", None, true),
+            Some(NodeClient::Firedancer)
+        );
+    }
 }
@@ -1,6 +1,9 @@
 use crate::common::*;
+use crate::geoip_service::GeoIpClient;
+use crate::ip_info_cache::CachedIpInfoProvider;
 use crate::marinade_service::*;
-use crate::solana_service::solana_client_with_timeout;
+use crate::rpc_service::solana_rpc;
+use crate::snapshot::write_snapshot;
 use crate::solana_service::*;
 use crate::validators_performance::{validators_performance, ValidatorPerformance};
 use crate::whois_service::*;
@@ -10,6 +13,8 @@ use log::info;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use solana_sdk::clock::Epoch;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
 use structopt::StructOpt;
 
@@ -24,6 +29,26 @@ pub struct ValidatorsParams {
     )]
     whois_bearer_token: Option<String>,
 
+    #[structopt(
+        long = "geoip-db",
+        help = "Directory with MaxMind GeoLite2/GeoIP2 City and ASN .mmdb files to be used instead of whois API.",
+        conflicts_with = "whois"
+    )]
+    geoip_db: Option<PathBuf>,
+
+    #[structopt(
+        long = "ip-info-cache",
+        help = "File to cache IP info lookups in between runs."
+    )]
+    ip_info_cache: Option<PathBuf>,
+
+    #[structopt(
+        long = "ip-info-cache-ttl",
+        help = "How long to keep cached IP info before looking it up again (seconds).",
+        default_value = "604800"
+    )]
+    ip_info_cache_ttl: u64,
+
     #[structopt(
         long = "bonds-url",
         default_value = "https://validator-bonds-api.marinade.finance/bonds/bidding"
@@ -118,6 +143,9 @@ pub struct ValidatorSnapshot {
     pub info_details: Option<String>,
     pub info_keybase: Option<String>,
     pub info_icon_url: Option<String>,
+    /// Missing in snapshots collected before the operators were clustered
+    #[serde(default)]
+    pub withdraw_authority: Option<String>,
     pub data_center: Option<ValidatorDataCenter>,
     pub activated_stake: u64,
     pub foundation_stake: u64,
@@ -130,8 +158,12 @@ pub struct ValidatorSnapshot {
     pub performance: ValidatorPerformance,
 }
 
+const DATA_VERSION: u16 = 1;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Snapshot {
+    #[serde(default)]
+    pub version: u16,
     pub epoch: Epoch,
     pub created_at: String,
     pub validators: Vec<ValidatorSnapshot>,
@@ -141,12 +173,22 @@ pub fn collect_validators_info(
     common_params: CommonParams,
     validator_params: ValidatorsParams,
 ) -> anyhow::Result<()> {
+    let snapshot = collect_validators_snapshot(&common_params, validator_params)?;
+    write_snapshot(std::io::stdout(), common_params.format, &snapshot)?;
+
+    Ok(())
+}
+
+pub fn collect_validators_snapshot(
+    common_params: &CommonParams,
+    validator_params: ValidatorsParams,
+) -> anyhow::Result<Snapshot> {
     info!("Collecting snaphost of validators: {:?}", &validator_params);
-    let client = solana_client_with_timeout(
-        common_params.rpc_url,
+    let client = solana_rpc(
+        common_params,
         Duration::from_secs(validator_params.rpc_timeout),
-        common_params.commitment,
-    );
+        validator_params.epoch,
+    )?;
 
     let created_at = chrono::Utc::now();
     let current_epoch_info = client.get_epoch_info()?;
@@ -174,27 +216,40 @@ pub fn collect_validators_info(
         total_activated_live_stake + total_activated_delinquent_stake
     );
     info!("Delinquent activated stake: {total_activated_delinquent_stake}");
-    let stake_history = get_stake_history(&client)?;
+    let stake_history = get_stake_history(client.as_ref())?;
     let minimum_superminority_stake = get_minimum_superminority_stake(&vote_accounts);
-    let marinade_stake = get_marinade_stakes(&client, epoch, &stake_history)?;
-    let foundation_stake = get_foundation_stakes(&client, epoch, &stake_history)?;
-    let institutional_stake = get_institutional_stakes(&client, epoch, &stake_history)?;
-    let marinade_native_stake = get_marinade_native_stakes(&client, epoch, &stake_history)?;
+    let marinade_stake = get_marinade_stakes(client.as_ref(), epoch, &stake_history)?;
+    let foundation_stake = get_foundation_stakes(client.as_ref(), epoch, &stake_history)?;
+    let institutional_stake = get_institutional_stakes(client.as_ref(), epoch, &stake_history)?;
+    let marinade_native_stake = get_marinade_native_stakes(client.as_ref(), epoch, &stake_history)?;
     let allow_zero_funded_bonds = validator_params.allow_zero_funded_bonds
         || std::env::var("ALLOW_ZERO_FUNDED_BONDS")
             .ok()
             .and_then(|v| v.parse::<bool>().ok())
             .unwrap_or(false);
-    let self_stake = get_self_stake(
-        &client,
+    let withdraw_authorities = get_withdraw_authorities(client.as_ref())?;
+    let withdraw_authority_by_vote_account: HashMap<_, _> = withdraw_authorities
+        .iter()
+        .map(|(withdraw_authority, vote_account)| {
+            (vote_account.clone(), withdraw_authority.clone())
+        })
+        .collect();
+    let mut self_stake = fetch_self_stake(
+        client.as_ref(),
+        withdraw_authorities,
         epoch,
         &stake_history,
+        validator_params.rpc_attempts,
+        common_params.rpc_concurrency,
+    )?;
+    add_bonds_to_self_stake(
+        &mut self_stake,
+        epoch,
         &validator_params.bonds_url,
         allow_zero_funded_bonds,
-        validator_params.rpc_attempts,
     )?;
-    let validators_info = get_validators_info(&client)?;
-    let node_ips = get_cluster_nodes_ips(&client)?;
+    let validators_info = get_validators_info(client.as_ref())?;
+    let node_ips = get_cluster_nodes_ips(client.as_ref())?;
 
     info!("Self stake: {}", self_stake.values().sum::<u64>());
     info!(
@@ -202,16 +257,35 @@ pub fn collect_validators_info(
         foundation_stake.values().sum::<u64>()
     );
 
-    let data_centers = match validator_params.whois {
-        Some(whois) => get_data_centers(
-            WhoisClient::new(whois, validator_params.whois_bearer_token),
-            node_ips.clone(),
-        )?,
+    let ip_info_provider: Option<(Box<dyn IpInfoProvider>, &str)> =
+        match (validator_params.whois, validator_params.geoip_db) {
+            (Some(whois), _) => Some((
+                Box::new(WhoisClient::new(whois, validator_params.whois_bearer_token)),
+                "whois",
+            )),
+            (_, Some(geoip_db)) => Some((Box::new(GeoIpClient::from_dir(&geoip_db)?), "geoip-db")),
+            _ => None,
+        };
+    let data_centers = match (ip_info_provider, validator_params.ip_info_cache) {
+        (Some((ip_info_provider, provider_name)), Some(ip_info_cache)) => {
+            let cached_provider = CachedIpInfoProvider::new(
+                ip_info_provider.as_ref(),
+                provider_name,
+                &ip_info_cache,
+                Duration::from_secs(validator_params.ip_info_cache_ttl),
+            )?;
+            let data_centers = get_data_centers(&cached_provider, node_ips.clone())?;
+            cached_provider.save()?;
+            data_centers
+        }
+        (Some((ip_info_provider, _)), None) => {
+            get_data_centers(ip_info_provider.as_ref(), node_ips.clone())?
+        }
         _ => Default::default(),
     };
 
     let performance = validators_performance(
-        &client,
+        client.as_ref(),
         epoch,
         &vote_accounts,
         validator_params.rpc_attempts,
@@ -249,6 +323,9 @@ pub fn collect_validators_info(
             info_keybase: keybase,
             info_details: details,
             info_icon_url: icon_url,
+            withdraw_authority: withdraw_authority_by_vote_account
+                .get(&vote_pubkey)
+                .cloned(),
 
             activated_stake: vote_account.activated_stake,
             marinade_stake: *marinade_stake.get(&vote_pubkey).unwrap_or(&0),
@@ -264,14 +341,111 @@ pub fn collect_validators_info(
         });
     }
 
-    serde_yaml::to_writer(
-        std::io::stdout(),
-        &Snapshot {
-            epoch,
-            created_at: created_at.to_string(),
-            validators,
-        },
-    )?;
+    Ok(Snapshot {
+        version: DATA_VERSION,
+        epoch,
+        created_at: created_at.to_string(),
+        validators,
+    })
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::{collect_validators_snapshot, ValidatorsParams};
+    use crate::common::CommonParams;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use structopt::StructOpt;
+
+    const BONDS: &str = r#"{"bonds":[{"pubkey":"BondA","vote_account":"jwV7SyvqCSrVcKibYvurCCWr7DUmT7yRYPmY9QwvrGv","authority":"361e3MVchvM8fSjCmeFqkj762JWttTA76tZXc3crLwVp","cpmpe":"0","max_stake_wanted":"0","updated_at":"2024-11-20T12:00:00Z","epoch":700,"funded_amount":"2000000000000","effective_amount":"2000000000000","remaining_witdraw_request_amount":"0","remainining_settlement_claim_amount":"0"}]}"#;
+
+    /// The bonds API is not an RPC call, it is served from a local socket.
+    fn serve_bonds() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/bonds/bidding", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{BONDS}",
+                    BONDS.len()
+                );
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn snapshot_from_recorded_rpc() {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/validators");
+        let common_params = CommonParams::from_iter([
+            "collect",
+            "--url",
+            "http://localhost:8899",
+            "--rpc-replay",
+            fixtures,
+        ]);
+        let bonds_url = serve_bonds();
+        let params = ValidatorsParams::from_iter([
+            "validators",
+            "--bonds-url",
+            &bonds_url,
+            "--rpc-attempts",
+            "1",
+        ]);
+
+        let snapshot = collect_validators_snapshot(&common_params, params).unwrap();
+        assert_eq!(snapshot.epoch, 700);
+        assert_eq!(snapshot.validators.len(), 3);
+        let validator = |vote_account: &str| {
+            snapshot
+                .validators
+                .iter()
+                .find(|v| v.vote_account == vote_account)
+                .unwrap()
+        };
+
+        let jito = validator("jwV7SyvqCSrVcKibYvurCCWr7DUmT7yRYPmY9QwvrGv");
+        assert_eq!(
+            jito.identity,
+            "2QyZaQEmmZPzaXXTgbbNoTeoSCsBpx93GDUea6Wu8ttN"
+        );
+        assert_eq!(jito.info_name.as_deref(), Some("Validator One"));
+        assert_eq!(jito.info_url.as_deref(), Some("https://one.example"));
+        assert_eq!(jito.info_keybase.as_deref(), Some("one"));
+        assert_eq!(jito.activated_stake, 3_000_000_000_000_000);
+        assert_eq!(jito.marinade_stake, 200_000_000_000_000);
+        assert_eq!(jito.institutional_stake, 10_000_000_000_000);
+        assert_eq!(jito.foundation_stake, 0);
+        // 5000 SOL staked by the withdraw authority and 2000 SOL funded into the bond
+        assert_eq!(jito.self_stake, 7_000_000_000_000);
+        assert!(jito.superminority);
+        assert_eq!(jito.stake_to_become_superminority, 0);
+        assert_eq!(jito.performance.credits, 6000);
+        assert_eq!(jito.performance.leader_slots, 12);
+        assert_eq!(jito.performance.blocks_produced, 11);
+
+        let firedancer = validator("oqtkwi1j2wZuJSh74CMk7wk77nFUQDt1Qhf3LiweewG");
+        assert_eq!(firedancer.info_name, None);
+        assert_eq!(firedancer.foundation_stake, 50_000_000_000_000);
+        assert_eq!(firedancer.marinade_native_stake, 30_000_000_000_000);
+        assert_eq!(firedancer.self_stake, 0);
+        assert!(!firedancer.superminority);
+        assert_eq!(
+            firedancer.stake_to_become_superminority,
+            2_000_000_000_000_000
+        );
+        assert_eq!(
+            firedancer.performance.version.as_deref(),
+            Some("0.505.20216")
+        );
+
+        let delinquent = validator("skJQSS6csSHJzZfcZToe3gyN8M2BMKnbH1YYY2wNTbc");
+        assert!(delinquent.performance.delinquent);
+        assert_eq!(delinquent.self_stake, 1_000_000_000_000);
+        assert_eq!(delinquent.performance.skip_rate, 1.0);
+        assert!(snapshot.validators.iter().all(|v| v.data_center.is_none()));
+    }
 }
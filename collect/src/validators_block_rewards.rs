@@ -1,11 +1,10 @@
-use crate::{common::*, solana_service::solana_client_with_timeout};
+use crate::{common::*, rpc_service::*, snapshot::write_snapshot};
 use anyhow::Context;
 use google_cloud_bigquery::client::{Client as BqClient, ClientConfig as BqClientConfig};
 use google_cloud_bigquery::http::job::query::QueryRequest;
 use google_cloud_bigquery::query::row::Row;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use serde_yaml;
 use solana_sdk::clock::Epoch;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -149,10 +148,23 @@ pub fn collect_validator_block_rewards_info(
     common_params: CommonParams,
     rewards_params: BlockRewardsParams,
 ) -> anyhow::Result<()> {
+    if let Some(snapshot) =
+        collect_validator_block_rewards_snapshot(&common_params, rewards_params)?
+    {
+        write_snapshot(std::io::stdout(), common_params.format, &snapshot)?;
+    }
+
+    Ok(())
+}
+
+/// Returns `None` when BigQuery does not have enough block rewards data for the epoch yet.
+pub fn collect_validator_block_rewards_snapshot(
+    common_params: &CommonParams,
+    rewards_params: BlockRewardsParams,
+) -> anyhow::Result<Option<ValidatorsBlockRewardsSnapshot>> {
     info!("Collecting validator block rewards snapshot");
     let timeout = Duration::from_secs(rewards_params.rpc_timeout);
-    let client =
-        solana_client_with_timeout(common_params.rpc_url, timeout, common_params.commitment);
+    let client = solana_rpc(common_params, timeout, rewards_params.epoch)?;
 
     let created_at = chrono::Utc::now();
     let current_epoch_info = client.get_epoch_info()?;
@@ -195,24 +207,20 @@ pub fn collect_validator_block_rewards_info(
                 rewards_params.loading_limit,
             );
         }
+        Ok(None)
     } else {
         info!(
             "Successfully retrieved {} validator block rewards",
             block_rewards.len()
         );
 
-        serde_yaml::to_writer(
-            std::io::stdout(),
-            &ValidatorsBlockRewardsSnapshot {
-                version: DATA_VERSION,
-                epoch: looking_at_epoch,
-                loaded_at_epoch: current_epoch_info.epoch,
-                loaded_at_slot_index: current_epoch_info.slot_index,
-                created_at: created_at.to_string(),
-                block_rewards,
-            },
-        )?;
+        Ok(Some(ValidatorsBlockRewardsSnapshot {
+            version: DATA_VERSION,
+            epoch: looking_at_epoch,
+            loaded_at_epoch: current_epoch_info.epoch,
+            loaded_at_slot_index: current_epoch_info.slot_index,
+            created_at: created_at.to_string(),
+            block_rewards,
+        }))
     }
-
-    Ok(())
 }
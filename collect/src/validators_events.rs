@@ -1,11 +1,10 @@
-use crate::{common::*, solana_service::solana_client_with_timeout};
+use crate::{common::*, rpc_service::*, snapshot::write_snapshot};
 use anyhow::Context;
 use google_cloud_bigquery::client::{Client as BqClient, ClientConfig as BqClientConfig};
 use google_cloud_bigquery::http::job::query::QueryRequest;
 use google_cloud_bigquery::query::row::Row;
 use log::info;
 use serde::{Deserialize, Serialize};
-use serde_yaml;
 use solana_sdk::clock::Epoch;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -137,10 +136,19 @@ pub fn collect_validator_events_info(
     common_params: CommonParams,
     events_params: EventsParams,
 ) -> anyhow::Result<()> {
+    let snapshot = collect_validator_events_snapshot(&common_params, events_params)?;
+    write_snapshot(std::io::stdout(), common_params.format, &snapshot)?;
+
+    Ok(())
+}
+
+pub fn collect_validator_events_snapshot(
+    common_params: &CommonParams,
+    events_params: EventsParams,
+) -> anyhow::Result<ValidatorsEventsSnapshot> {
     info!("Collecting validator events (PSR settlements) snapshot");
     let timeout = Duration::from_secs(events_params.rpc_timeout);
-    let client =
-        solana_client_with_timeout(common_params.rpc_url, timeout, common_params.commitment);
+    let client = solana_rpc(common_params, timeout, None)?;
 
     let created_at = chrono::Utc::now();
     let current_epoch_info = client.get_epoch_info()?;
@@ -162,17 +170,12 @@ pub fn collect_validator_events_info(
     // so we always emit the snapshot even when the set is small or empty.
     info!("Retrieved {} validator settlement records", events.len());
 
-    serde_yaml::to_writer(
-        std::io::stdout(),
-        &ValidatorsEventsSnapshot {
-            version: DATA_VERSION,
-            from_epoch,
-            loaded_at_epoch: current_epoch_info.epoch,
-            loaded_at_slot_index: current_epoch_info.slot_index,
-            created_at: created_at.to_string(),
-            events,
-        },
-    )?;
-
-    Ok(())
+    Ok(ValidatorsEventsSnapshot {
+        version: DATA_VERSION,
+        from_epoch,
+        loaded_at_epoch: current_epoch_info.epoch,
+        loaded_at_slot_index: current_epoch_info.slot_index,
+        created_at: created_at.to_string(),
+        events,
+    })
 }
@@ -1,20 +1,18 @@
-use crate::{common::*, solana_service::solana_client_with_timeout};
+use crate::{common::*, rpc_service::*, snapshot::write_snapshot};
 use anchor_lang::AccountDeserialize;
 use jito_priority_fee_distribution::state::PriorityFeeDistributionAccount;
 use jito_tip_distribution::state::TipDistributionAccount;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use serde_yaml;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::{
-    rpc_client::RpcClient,
     rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
     rpc_filter::{Memcmp, RpcFilterType},
 };
 use solana_program::pubkey::Pubkey;
 use solana_sdk::account::Account;
 use solana_sdk::clock::Epoch;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::time::Duration;
 use structopt::StructOpt;
@@ -183,7 +181,7 @@ impl JitoSnapshot {
 }
 
 fn fetch_program_accounts_with_retry(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     jito_account_type: &JitoAccountType,
     byte_pos: usize,
     epoch: u64,
@@ -232,7 +230,7 @@ fn fetch_program_accounts_with_retry(
 }
 
 pub fn jito_accounts(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     account_type: &JitoAccountType,
     epoch: Epoch,
     rpc_attempts: usize,
@@ -278,6 +276,25 @@ pub fn jito_accounts(
     Ok(loaded_accounts)
 }
 
+/// Vote accounts of the validators that created a MEV tip distribution account for the epoch,
+/// which only the Jito-Solana client (and Firedancer running the Jito bundles) does.
+pub fn tip_distribution_vote_accounts(
+    client: &dyn SolanaRpc,
+    epoch: Epoch,
+    rpc_attempts: usize,
+) -> anyhow::Result<HashSet<String>> {
+    let accounts = jito_accounts(
+        client,
+        &JitoAccountType::MevTipDistribution,
+        epoch,
+        rpc_attempts,
+    )?;
+
+    Ok(deserialize_mev_tip_distribution(&accounts, epoch)?
+        .into_keys()
+        .collect())
+}
+
 fn deserialize_mev_tip_distribution(
     accounts: &[(Pubkey, Account)],
     epoch: Epoch,
@@ -362,10 +379,20 @@ pub fn collect_jito_info(
     jito_params: JitoParams,
     account_type: JitoAccountType,
 ) -> anyhow::Result<()> {
+    let snapshot = collect_jito_snapshot(&common_params, jito_params, account_type)?;
+    write_snapshot(std::io::stdout(), common_params.format, &snapshot)?;
+
+    Ok(())
+}
+
+pub fn collect_jito_snapshot(
+    common_params: &CommonParams,
+    jito_params: JitoParams,
+    account_type: JitoAccountType,
+) -> anyhow::Result<JitoSnapshot> {
     info!("Collecting snapshot of JITO validators accounts");
     let timeout = Duration::from_secs(jito_params.rpc_timeout);
-    let client =
-        solana_client_with_timeout(common_params.rpc_url, timeout, common_params.commitment);
+    let client = solana_rpc(common_params, timeout, jito_params.epoch)?;
 
     let created_at = chrono::Utc::now();
     let current_epoch_info = client.get_epoch_info()?;
@@ -374,7 +401,7 @@ pub fn collect_jito_info(
     info!("Looking at epoch: {looking_at_epoch}");
 
     let raw_accounts = jito_accounts(
-        &client,
+        client.as_ref(),
         &account_type,
         looking_at_epoch,
         jito_params.rpc_attempts,
@@ -391,18 +418,13 @@ pub fn collect_jito_info(
         }
     };
 
-    serde_yaml::to_writer(
-        std::io::stdout(),
-        &JitoSnapshot {
-            version: JITO_SNAPSHOT_VERSION,
-            account_type: account_type.clone(),
-            epoch: looking_at_epoch,
-            loaded_at_epoch: current_epoch_info.epoch,
-            loaded_at_slot_index: current_epoch_info.slot_index,
-            created_at: created_at.to_string(),
-            validators,
-        },
-    )?;
-
-    Ok(())
+    Ok(JitoSnapshot {
+        version: JITO_SNAPSHOT_VERSION,
+        account_type: account_type.clone(),
+        epoch: looking_at_epoch,
+        loaded_at_epoch: current_epoch_info.epoch,
+        loaded_at_slot_index: current_epoch_info.slot_index,
+        created_at: created_at.to_string(),
+        validators,
+    })
 }
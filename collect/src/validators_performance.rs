@@ -1,12 +1,15 @@
 use crate::common::*;
-use crate::solana_service::solana_client_with_timeout;
+use crate::rpc_service::*;
+use crate::snapshot::write_snapshot;
 use crate::solana_service::*;
+use crate::validators_jito::tip_distribution_vote_accounts;
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use serde_yaml;
-use solana_client::{rpc_client::RpcClient, rpc_response::RpcVoteAccountStatus};
+use solana_client::rpc_response::RpcVoteAccountStatus;
 use solana_sdk::clock::Epoch;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 use structopt::StructOpt;
 
@@ -45,10 +48,53 @@ pub struct ClusterInflation {
     pub inflation_taper: f64,
 }
 
+/// Validator client software a node runs, as told by its gossip version or its Jito tip distribution account.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum NodeClient {
+    Agave,
+    JitoSolana,
+    /// Frankendancer and full Firedancer
+    Firedancer,
+    Other,
+}
+
+impl FromStr for NodeClient {
+    type Err = anyhow::Error;
+
+    fn from_str(client: &str) -> Result<Self, Self::Err> {
+        match client {
+            "agave" => Ok(NodeClient::Agave),
+            "jito-solana" => Ok(NodeClient::JitoSolana),
+            "firedancer" => Ok(NodeClient::Firedancer),
+            "other" => Ok(NodeClient::Other),
+            _ => anyhow::bail!(
+                "Unknown client '{client}', expected agave, jito-solana, firedancer or other"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for NodeClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeClient::Agave => write!(f, "agave"),
+            NodeClient::JitoSolana => write!(f, "jito-solana"),
+            NodeClient::Firedancer => write!(f, "firedancer"),
+            NodeClient::Other => write!(f, "other"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ValidatorPerformance {
     pub commission: u8,
     pub version: Option<String>,
+    /// Missing in snapshots collected before the clients were classified
+    #[serde(default)]
+    pub client: Option<NodeClient>,
+    #[serde(default)]
+    pub feature_set: Option<u32>,
     pub credits: u64,
     pub leader_slots: usize,
     pub blocks_produced: usize,
@@ -56,8 +102,23 @@ pub struct ValidatorPerformance {
     pub delinquent: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ValidatorPerformance {
+    /// Snapshots collected before the clients were classified only tell the version numbering.
+    pub fn node_client(&self) -> Option<NodeClient> {
+        self.client.or_else(|| {
+            self.version
+                .as_deref()
+                .and_then(|version| classify_node_client(version, None, false))
+        })
+    }
+}
+
+const DATA_VERSION: u16 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ValidatorsPerformanceSnapshot {
+    #[serde(default)]
+    pub version: u16,
     pub epoch: Epoch,
     pub epoch_slot: u64,
     pub transaction_count: u64,
@@ -68,7 +129,7 @@ pub struct ValidatorsPerformanceSnapshot {
 }
 
 pub fn validators_performance(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     epoch: Epoch,
     vote_accounts: &RpcVoteAccountStatus,
     rpc_attempts: usize,
@@ -90,6 +151,11 @@ pub fn validators_performance(
     )?;
     let node_versions = get_cluster_nodes_versions(client)?;
     let credits = get_credits(client, epoch)?;
+    let tip_distribution_vote_accounts =
+        tip_distribution_vote_accounts(client, epoch, rpc_attempts).unwrap_or_else(|err| {
+            warn!("Failed to load Jito tip distribution accounts, Jito-Solana nodes not reporting their client stay unknown: {err:?}");
+            Default::default()
+        });
 
     for vote_account in vote_accounts
         .current
@@ -102,12 +168,21 @@ pub fn validators_performance(
             .get(&identity)
             .cloned()
             .unwrap_or((0, 0));
+        let node_version = node_versions.get(&identity);
 
         validators.insert(
             vote_pubkey.clone(),
             ValidatorPerformance {
                 commission: vote_account.commission,
-                version: node_versions.get(&identity).cloned(),
+                version: node_version.map(|v| v.version.clone()),
+                client: node_version.and_then(|v| {
+                    classify_node_client(
+                        &v.version,
+                        v.extra.as_deref(),
+                        tip_distribution_vote_accounts.contains(&vote_pubkey),
+                    )
+                }),
+                feature_set: node_version.and_then(|v| v.feature_set),
                 credits: credits.get(&vote_pubkey).cloned().unwrap_or(0),
                 leader_slots,
                 blocks_produced,
@@ -125,12 +200,13 @@ pub fn validators_performance(
 }
 
 pub fn validator_rewards(
-    client: &RpcClient,
+    client: &dyn SolanaRpc,
     epoch: Epoch,
     vote_accounts: &RpcVoteAccountStatus,
+    rpc_concurrency: usize,
 ) -> anyhow::Result<HashMap<String, ValidatorRewards>> {
     let commission_from_rewards =
-        get_commission_from_inflation_rewards(client, vote_accounts, Some(epoch))?;
+        get_commission_from_inflation_rewards(client, vote_accounts, Some(epoch), rpc_concurrency)?;
 
     Ok(vote_accounts
         .current
@@ -153,12 +229,22 @@ pub fn collect_validators_performance_info(
     common_params: CommonParams,
     performance_params: ValidatorsPerformanceParams,
 ) -> anyhow::Result<()> {
+    let snapshot = collect_validators_performance_snapshot(&common_params, performance_params)?;
+    write_snapshot(std::io::stdout(), common_params.format, &snapshot)?;
+
+    Ok(())
+}
+
+pub fn collect_validators_performance_snapshot(
+    common_params: &CommonParams,
+    performance_params: ValidatorsPerformanceParams,
+) -> anyhow::Result<ValidatorsPerformanceSnapshot> {
     info!("Collecting snaphost of validators' performance");
-    let client = solana_client_with_timeout(
-        common_params.rpc_url,
+    let client = solana_rpc(
+        common_params,
         Duration::from_secs(performance_params.rpc_timeout),
-        common_params.commitment,
-    );
+        performance_params.epoch,
+    )?;
 
     let created_at = chrono::Utc::now();
     let current_epoch_info = client.get_epoch_info()?;
@@ -177,14 +263,19 @@ pub fn collect_validators_performance_info(
     );
 
     let validators = validators_performance(
-        &client,
+        client.as_ref(),
         epoch,
         &vote_accounts,
         performance_params.rpc_attempts,
     )?;
 
     let rewards = if performance_params.with_rewards {
-        Some(validator_rewards(&client, epoch, &vote_accounts)?)
+        Some(validator_rewards(
+            client.as_ref(),
+            epoch,
+            &vote_accounts,
+            common_params.rpc_concurrency,
+        )?)
     } else {
         None
     };
@@ -203,18 +294,87 @@ pub fn collect_validators_performance_info(
         None
     };
 
-    serde_yaml::to_writer(
-        std::io::stdout(),
-        &ValidatorsPerformanceSnapshot {
-            epoch,
-            epoch_slot: current_epoch_info.slot_index,
-            transaction_count: current_epoch_info.transaction_count.unwrap(),
-            created_at: created_at.to_string(),
-            cluster_inflation,
-            validators,
-            rewards,
-        },
-    )?;
+    Ok(ValidatorsPerformanceSnapshot {
+        version: DATA_VERSION,
+        epoch,
+        epoch_slot: current_epoch_info.slot_index,
+        transaction_count: current_epoch_info.transaction_count.unwrap(),
+        created_at: created_at.to_string(),
+        cluster_inflation,
+        validators,
+        rewards,
+    })
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::{collect_validators_performance_snapshot, ValidatorsPerformanceParams};
+    use crate::common::CommonParams;
+    use structopt::StructOpt;
+
+    #[test]
+    fn snapshot_from_recorded_rpc() {
+        let fixtures = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/validators-performance"
+        );
+        let common_params = CommonParams::from_iter([
+            "collect",
+            "--url",
+            "http://localhost:8899",
+            "--rpc-replay",
+            fixtures,
+        ]);
+        let params = ValidatorsPerformanceParams::from_iter([
+            "validators-performance",
+            "--with-rewards",
+            "--rpc-attempts",
+            "1",
+        ]);
+
+        let snapshot = collect_validators_performance_snapshot(&common_params, params).unwrap();
+        assert_eq!(snapshot.epoch, 700);
+        assert_eq!(snapshot.epoch_slot, 1000);
+        assert_eq!(snapshot.transaction_count, 400_000_000_000);
+        let inflation = snapshot.cluster_inflation.unwrap();
+        assert_eq!(inflation.sol_total_supply, 600_000_000_000_000_000);
+        assert_eq!(inflation.inflation, 0.045);
+        assert_eq!(inflation.inflation_taper, 0.15);
+        assert_eq!(snapshot.validators.len(), 3);
+
+        let jito = &snapshot.validators["jwV7SyvqCSrVcKibYvurCCWr7DUmT7yRYPmY9QwvrGv"];
+        assert_eq!(jito.commission, 5);
+        assert_eq!(jito.version.as_deref(), Some("2.2.14"));
+        assert_eq!(jito.credits, 6000);
+        assert_eq!(jito.leader_slots, 12);
+        assert_eq!(jito.blocks_produced, 11);
+        assert!((jito.skip_rate - 1.0 / 12.0).abs() < 1e-9);
+        assert!(!jito.delinquent);
+
+        let firedancer = &snapshot.validators["oqtkwi1j2wZuJSh74CMk7wk77nFUQDt1Qhf3LiweewG"];
+        assert_eq!(firedancer.version.as_deref(), Some("0.505.20216"));
+        assert_eq!(firedancer.credits, 5500);
+        assert_eq!(firedancer.skip_rate, 0.0);
+
+        let delinquent = &snapshot.validators["skJQSS6csSHJzZfcZToe3gyN8M2BMKnbH1YYY2wNTbc"];
+        assert!(delinquent.delinquent);
+        assert_eq!(delinquent.credits, 0);
+        assert_eq!(delinquent.leader_slots, 4);
+        assert_eq!(delinquent.blocks_produced, 0);
+
+        let rewards = snapshot.rewards.unwrap();
+        let commission_effective = |vote_account: &str| rewards[vote_account].commission_effective;
+        assert_eq!(
+            commission_effective("jwV7SyvqCSrVcKibYvurCCWr7DUmT7yRYPmY9QwvrGv"),
+            Some(5)
+        );
+        assert_eq!(
+            commission_effective("oqtkwi1j2wZuJSh74CMk7wk77nFUQDt1Qhf3LiweewG"),
+            Some(0)
+        );
+        assert_eq!(
+            commission_effective("skJQSS6csSHJzZfcZToe3gyN8M2BMKnbH1YYY2wNTbc"),
+            None
+        );
+    }
 }
@@ -1,13 +1,13 @@
 use log::{debug, info, warn};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Coordinates {
     pub lat: f64,
     pub lon: f64,
 }
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct IpInfo {
     pub asn: Option<u32>,
     pub aso: Option<String>,
@@ -18,6 +18,10 @@ pub struct IpInfo {
     pub city: Option<String>,
 }
 
+pub trait IpInfoProvider {
+    fn get_ip_info(&self, ip: &str) -> anyhow::Result<IpInfo>;
+}
+
 pub struct WhoisClient {
     host: String,
     bearer_token: Option<String>,
@@ -26,8 +30,10 @@ impl WhoisClient {
     pub fn new(host: String, bearer_token: Option<String>) -> Self {
         Self { host, bearer_token }
     }
+}
 
-    pub fn get_ip_info(&self, ip: &String) -> anyhow::Result<IpInfo> {
+impl IpInfoProvider for WhoisClient {
+    fn get_ip_info(&self, ip: &str) -> anyhow::Result<IpInfo> {
         debug!("Fetching info about data centers: {}", &ip);
         let client = reqwest::blocking::Client::builder().build()?;
         let body = client
@@ -45,7 +51,7 @@ impl WhoisClient {
 }
 
 pub fn get_data_centers(
-    whois_client: WhoisClient,
+    ip_info_provider: &dyn IpInfoProvider,
     node_ips: HashMap<String, String>,
 ) -> anyhow::Result<HashMap<String, (String, IpInfo)>> {
     info!("Fetching info about data centers...");
@@ -55,7 +61,7 @@ pub fn get_data_centers(
         if ip.eq("127.0.0.1") {
             continue;
         }
-        match whois_client.get_ip_info(ip) {
+        match ip_info_provider.get_ip_info(ip) {
             Ok(info) => {
                 data_centers.insert(node.clone(), (ip.clone(), info));
             }
@@ -0,0 +1,16 @@
+CREATE TABLE scheduler_runs (
+  id BIGSERIAL NOT NULL,
+  task TEXT NOT NULL,
+  epoch NUMERIC NOT NULL,
+  slot_index NUMERIC NOT NULL,
+  status TEXT NOT NULL,
+  attempts INTEGER NOT NULL,
+  error TEXT NULL,
+  started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  finished_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  PRIMARY KEY(id)
+);
+
+CREATE INDEX idx_scheduler_runs_task_finished_at
+    ON scheduler_runs(task, finished_at);
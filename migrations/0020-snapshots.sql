@@ -0,0 +1,28 @@
+CREATE TABLE snapshots (
+  id BIGSERIAL NOT NULL,
+  snapshot_type TEXT NOT NULL,
+  format TEXT NOT NULL,
+  epoch NUMERIC NOT NULL,
+  loaded_at_slot_index NUMERIC NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  content_hash TEXT NOT NULL,
+  body BYTEA NOT NULL,
+  stored_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  PRIMARY KEY(id),
+  UNIQUE(snapshot_type, content_hash)
+);
+
+CREATE INDEX idx_snapshots_epoch
+    ON snapshots(epoch);
+
+ALTER TABLE validators ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE uptimes ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE commissions ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE versions ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE cluster_info ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE epochs ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE mev ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE jito_priority_fee ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE validators_block_rewards ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
+ALTER TABLE validators_events ADD COLUMN snapshot_id BIGINT NULL REFERENCES snapshots(id);
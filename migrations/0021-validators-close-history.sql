@@ -0,0 +1,19 @@
+CREATE TABLE validators_close_history (
+  vote_account TEXT NOT NULL,
+  epoch NUMERIC NOT NULL,
+  commission_effective INTEGER NULL,
+  commission_max_observed INTEGER NULL,
+  commission_min_observed INTEGER NULL,
+  credits NUMERIC NOT NULL,
+  leader_slots NUMERIC NOT NULL,
+  blocks_produced NUMERIC NOT NULL,
+  skip_rate DOUBLE PRECISION NOT NULL,
+  uptime_pct DOUBLE PRECISION NULL,
+  uptime NUMERIC NULL,
+  downtime NUMERIC NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  snapshot_id BIGINT NULL REFERENCES snapshots(id),
+  closed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  PRIMARY KEY(vote_account, epoch)
+);
@@ -0,0 +1,24 @@
+ALTER TABLE validators ADD COLUMN client TEXT NULL;
+ALTER TABLE versions ADD COLUMN client TEXT NULL;
+ALTER TABLE versions ADD COLUMN feature_set BIGINT NULL;
+
+-- Records stored before the classification only tell the client by their version numbering and the tip distribution:
+-- Firedancer releases are numbered 0.x, Jito-Solana had a tip distribution account for the epoch.
+-- Agave cannot be told from Jito-Solana by its version, such records stay unknown.
+UPDATE versions SET client = CASE WHEN version LIKE '0.%' THEN 'firedancer' ELSE 'jito-solana' END
+WHERE version IS NOT NULL
+  AND (
+    version LIKE '0.%'
+    OR EXISTS (SELECT 1 FROM mev WHERE mev.vote_account = versions.vote_account AND mev.epoch = versions.epoch)
+  );
+
+-- versions only gets a record when the version changes, validators take the client of the latest one at or before their epoch
+UPDATE validators SET client = (
+  SELECT versions.client FROM versions
+  WHERE versions.vote_account = validators.vote_account
+    AND versions.epoch <= validators.epoch
+    AND versions.version = validators.version
+  ORDER BY versions.epoch DESC, versions.epoch_slot DESC
+  LIMIT 1
+)
+WHERE version IS NOT NULL;
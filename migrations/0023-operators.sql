@@ -0,0 +1,27 @@
+ALTER TABLE validators ADD COLUMN withdraw_authority TEXT NULL;
+
+CREATE TABLE validator_operators (
+  epoch NUMERIC NOT NULL,
+  vote_account TEXT NOT NULL,
+  operator_id TEXT NOT NULL,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+
+  PRIMARY KEY(epoch, vote_account)
+);
+
+CREATE INDEX idx_validator_operators_epoch_operator_id
+    ON validator_operators(epoch, operator_id);
+
+-- weak links are listed with every operator they link to others
+CREATE TABLE operator_links (
+  epoch NUMERIC NOT NULL,
+  operator_id TEXT NOT NULL,
+  evidence TEXT NOT NULL,
+  value TEXT NOT NULL,
+  vote_accounts TEXT[] NOT NULL,
+
+  PRIMARY KEY(epoch, operator_id, evidence, value)
+);
+
+CREATE INDEX idx_operator_links_epoch_operator_id
+    ON operator_links(epoch, operator_id);
@@ -0,0 +1,5 @@
+-- the API polls the last changes of these tables to refresh its cache only when they changed
+CREATE INDEX idx_validators_updated_at ON validators(updated_at);
+CREATE INDEX idx_uptimes_end_at ON uptimes(end_at);
+CREATE INDEX idx_cluster_info_epoch ON cluster_info(epoch);
+CREATE INDEX idx_validator_operators_updated_at ON validator_operators(updated_at);
@@ -0,0 +1,297 @@
+use chrono::Utc;
+use collect::common::{CommonParams as CollectCommonParams, QuadraticBackoffStrategy};
+use collect::rpc_service::{solana_rpc, SolanaRpc};
+use env_logger::Env;
+use log::{error, info, warn};
+use openssl::ssl::{SslConnector, SslMethod};
+use postgres_openssl::MakeTlsConnector;
+use runs::{load_last_runs, record_run, RunRecord, RunStatus};
+use schedule::{is_due, ScheduleParams};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use store::migrations::check_schema_version;
+use structopt::StructOpt;
+use tasks::{Task, TaskArgs, TaskContext, TaskOutcome};
+use tokio_postgres::Client;
+
+pub mod runs;
+pub mod schedule;
+pub mod tasks;
+
+#[derive(Debug, StructOpt)]
+pub struct CommonParams {
+    #[structopt(long = "postgres-url")]
+    postgres_url: String,
+
+    #[structopt(long = "postgres-ssl-root-cert", env = "PG_SSLROOTCERT")]
+    pub postgres_ssl_root_cert: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct Params {
+    #[structopt(flatten)]
+    common: CommonParams,
+
+    #[structopt(flatten)]
+    collect_common: CollectCommonParams,
+
+    #[structopt(subcommand)]
+    command: SchedulerCommand,
+}
+
+#[derive(Debug, StructOpt)]
+enum SchedulerCommand {
+    Run(Box<RunParams>),
+    Status(StatusParams),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RunParams {
+    #[structopt(
+        long = "tasks",
+        help = "Comma-separated tasks to schedule, all by default.",
+        use_delimiter = true
+    )]
+    tasks: Vec<Task>,
+
+    #[structopt(
+        long = "poll-interval",
+        help = "How long to wait between checks whether a task is due (seconds).",
+        default_value = "60"
+    )]
+    poll_interval: u64,
+
+    #[structopt(
+        long = "task-attempts",
+        help = "How many times to attempt a task before recording it as failed.",
+        default_value = "3"
+    )]
+    task_attempts: usize,
+
+    #[structopt(
+        long = "rpc-timeout",
+        help = "How long to wait for RPC response when tracking the epoch (seconds).",
+        default_value = "60"
+    )]
+    rpc_timeout: u64,
+
+    #[structopt(
+        long = "snapshot-dir",
+        help = "Directory to archive every collected snapshot to (in --format)."
+    )]
+    snapshot_dir: Option<PathBuf>,
+
+    #[structopt(long = "once", help = "Run the due tasks once and exit.")]
+    once: bool,
+
+    #[structopt(flatten)]
+    schedule: ScheduleParams,
+
+    #[structopt(flatten)]
+    task_args: TaskArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct StatusParams {}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let params = Params::from_args();
+
+    let mut builder = SslConnector::builder(SslMethod::tls())?;
+    builder.set_ca_file(&params.common.postgres_ssl_root_cert)?;
+    let connector = MakeTlsConnector::new(builder.build());
+
+    let (mut psql_client, psql_conn) =
+        tokio_postgres::connect(&params.common.postgres_url, connector).await?;
+    tokio::spawn(async move {
+        if let Err(err) = psql_conn.await {
+            log::error!("Connection error: {err}");
+            std::process::exit(1);
+        }
+    });
+
+    check_schema_version(&psql_client).await?;
+
+    match params.command {
+        SchedulerCommand::Run(run_params) => {
+            run_scheduler(*run_params, params.collect_common, &mut psql_client).await
+        }
+        SchedulerCommand::Status(_) => print_status(&psql_client).await,
+    }
+}
+
+async fn run_scheduler(
+    run_params: RunParams,
+    collect_common: CollectCommonParams,
+    psql_client: &mut Client,
+) -> anyhow::Result<()> {
+    let tasks = if run_params.tasks.is_empty() {
+        Task::ALL.to_vec()
+    } else {
+        run_params.tasks.clone()
+    };
+    info!("Scheduling tasks: {tasks:?}");
+
+    let rpc_client = solana_rpc(
+        &collect_common,
+        Duration::from_secs(run_params.rpc_timeout),
+        None,
+    )?;
+    let collect_common = Arc::new(collect_common);
+
+    loop {
+        if let Err(err) = run_due_tasks(
+            &run_params,
+            &tasks,
+            collect_common.clone(),
+            rpc_client.as_ref(),
+            psql_client,
+        )
+        .await
+        {
+            error!("Scheduler tick failed: {err:?}");
+        }
+
+        if run_params.once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(run_params.poll_interval)).await;
+    }
+}
+
+async fn run_due_tasks(
+    run_params: &RunParams,
+    tasks: &[Task],
+    collect_common: Arc<CollectCommonParams>,
+    rpc_client: &dyn SolanaRpc,
+    psql_client: &mut Client,
+) -> anyhow::Result<()> {
+    let epoch_info = rpc_client.get_epoch_info()?;
+    info!(
+        "Epoch {} at slot index {}/{}",
+        epoch_info.epoch, epoch_info.slot_index, epoch_info.slots_in_epoch
+    );
+
+    let last_successes: HashMap<_, _> = load_last_runs(psql_client, Some(RunStatus::Success))
+        .await?
+        .into_iter()
+        .map(|run| (run.task, run))
+        .collect();
+    let mut context = TaskContext::new(
+        collect_common,
+        epoch_info.clone(),
+        run_params.snapshot_dir.clone(),
+        run_params.task_args.clone(),
+    );
+
+    for task in tasks.iter().copied() {
+        let due = is_due(
+            task,
+            &run_params.schedule,
+            &epoch_info,
+            last_successes.get(&task),
+            psql_client,
+            rpc_client,
+        )
+        .await;
+        match due {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(err) => {
+                error!("Failed to check whether task {task} is due: {err:?}");
+                continue;
+            }
+        }
+
+        let started_at = Utc::now();
+        let mut backoff = QuadraticBackoffStrategy::iter_durations(run_params.task_attempts);
+        let mut attempts = 0;
+        let outcome = loop {
+            attempts += 1;
+            info!("Running task {task}, attempt {attempts}");
+            match context.run(task, psql_client).await {
+                Ok(outcome) => break Ok(outcome),
+                Err(err) => match backoff.next() {
+                    Some(wait) if attempts < run_params.task_attempts => {
+                        warn!("Task {task} failed, retrying in {wait:?}: {err:?}");
+                        tokio::time::sleep(wait).await;
+                    }
+                    _ => break Err(err),
+                },
+            }
+        };
+
+        let (status, error) = match outcome {
+            Ok(TaskOutcome::Stored) => {
+                info!("Task {task} succeeded after {attempts} attempt(s)");
+                (RunStatus::Success, None)
+            }
+            Ok(TaskOutcome::Skipped(reason)) => {
+                info!("Task {task} skipped: {reason}");
+                (RunStatus::Skipped, Some(reason))
+            }
+            Err(err) => {
+                error!("Task {task} failed after {attempts} attempt(s): {err:?}");
+                (RunStatus::Failed, Some(format!("{err:?}")))
+            }
+        };
+        record_run(
+            psql_client,
+            &RunRecord {
+                task,
+                epoch: epoch_info.epoch,
+                slot_index: epoch_info.slot_index,
+                status,
+                attempts: attempts as i32,
+                error,
+                started_at,
+                finished_at: Utc::now(),
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn print_status(psql_client: &Client) -> anyhow::Result<()> {
+    let last_runs: HashMap<_, _> = load_last_runs(psql_client, None)
+        .await?
+        .into_iter()
+        .map(|run| (run.task, run))
+        .collect();
+    let last_successes: HashMap<_, _> = load_last_runs(psql_client, Some(RunStatus::Success))
+        .await?
+        .into_iter()
+        .map(|run| (run.task, run))
+        .collect();
+
+    for task in Task::ALL {
+        let last_run = match last_runs.get(&task) {
+            Some(run) => format!(
+                "{} at {} (epoch {}, {} attempt(s)){}",
+                run.status,
+                run.finished_at,
+                run.epoch,
+                run.attempts,
+                run.error
+                    .as_ref()
+                    .map(|error| format!(": {}", error.lines().next().unwrap_or_default()))
+                    .unwrap_or_default()
+            ),
+            None => "never run".to_string(),
+        };
+        let last_success = last_successes
+            .get(&task)
+            .map(|run| run.finished_at.to_string())
+            .unwrap_or_else(|| "never".to_string());
+        println!("{task}: {last_run}; last success: {last_success}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use crate::tasks::Task;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Success,
+    Skipped,
+    Failed,
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunStatus::Success => write!(f, "success"),
+            RunStatus::Skipped => write!(f, "skipped"),
+            RunStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl FromStr for RunStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "success" => Ok(RunStatus::Success),
+            "skipped" => Ok(RunStatus::Skipped),
+            "failed" => Ok(RunStatus::Failed),
+            _ => anyhow::bail!("Unknown run status '{status}'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub task: Task,
+    pub epoch: u64,
+    pub slot_index: u64,
+    pub status: RunStatus,
+    pub attempts: i32,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl RunRecord {
+    fn from_row(row: &tokio_postgres::Row) -> anyhow::Result<Self> {
+        Ok(Self {
+            task: row.get::<_, &str>("task").parse()?,
+            epoch: row.get::<_, Decimal>("epoch").try_into()?,
+            slot_index: row.get::<_, Decimal>("slot_index").try_into()?,
+            status: row.get::<_, &str>("status").parse()?,
+            attempts: row.get("attempts"),
+            error: row.get("error"),
+            started_at: row.get("started_at"),
+            finished_at: row.get("finished_at"),
+        })
+    }
+}
+
+pub async fn record_run(psql_client: &Client, run: &RunRecord) -> anyhow::Result<()> {
+    psql_client
+        .execute(
+            "
+        INSERT INTO scheduler_runs (
+            task,
+            epoch,
+            slot_index,
+            status,
+            attempts,
+            error,
+            started_at,
+            finished_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ",
+            &[
+                &run.task.to_string(),
+                &Decimal::from(run.epoch),
+                &Decimal::from(run.slot_index),
+                &run.status.to_string(),
+                &run.attempts,
+                &run.error,
+                &run.started_at,
+                &run.finished_at,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Latest run of every task, optionally only the runs with the given status.
+pub async fn load_last_runs(
+    psql_client: &Client,
+    status: Option<RunStatus>,
+) -> anyhow::Result<Vec<RunRecord>> {
+    let rows = psql_client
+        .query(
+            "
+        SELECT DISTINCT ON (task)
+            task,
+            epoch,
+            slot_index,
+            status,
+            attempts,
+            error,
+            started_at,
+            finished_at
+        FROM scheduler_runs
+        WHERE $1::TEXT IS NULL OR status = $1
+        ORDER BY task, finished_at DESC
+    ",
+            &[&status.map(|status| status.to_string())],
+        )
+        .await?;
+
+    rows.iter().map(RunRecord::from_row).collect()
+}
@@ -0,0 +1,274 @@
+use crate::runs::RunRecord;
+use crate::tasks::{parse_task_args, Task};
+use check::validators_block_rewards::{check_block_rewards, BlockRewardsCheckParams};
+use check::validators_jito::{check_jito, ValidatorsJitoCheckParams};
+use chrono::{DateTime, Utc};
+use collect::rpc_service::SolanaRpc;
+use log::info;
+use rust_decimal::prelude::*;
+use solana_sdk::epoch_info::EpochInfo;
+use std::time::Duration;
+use store::validators_block_rewards::VALIDATORS_BLOCK_REWARDS_TABLE;
+use structopt::StructOpt;
+use tokio_postgres::Client;
+
+#[derive(Debug, StructOpt)]
+pub struct ScheduleParams {
+    #[structopt(
+        long = "validators-interval",
+        help = "How often to collect the validators snapshot (seconds). It is also collected once the epoch changes.",
+        default_value = "3600"
+    )]
+    validators_interval: u64,
+
+    #[structopt(
+        long = "quick-changes-interval",
+        help = "How often to store performance, uptime, commissions and versions (seconds).",
+        default_value = "600"
+    )]
+    quick_changes_interval: u64,
+
+    #[structopt(
+        long = "events-interval",
+        help = "How often to re-query the validator events (seconds).",
+        default_value = "3600"
+    )]
+    events_interval: u64,
+
+    #[structopt(
+        long = "jito-check-args",
+        help = "Arguments of `check jito-mev` / `check jito-priority` deciding when the Jito tasks are due.",
+        default_value = ""
+    )]
+    jito_check_args: String,
+
+    #[structopt(
+        long = "block-rewards-check-args",
+        help = "Arguments of `check block-rewards` deciding when the block-rewards task is due.",
+        default_value = ""
+    )]
+    block_rewards_check_args: String,
+
+    #[structopt(
+        long = "close-epoch-slot-offset",
+        help = "How many slots into an epoch to wait before closing the previous one, so that its inflation rewards are paid out.",
+        default_value = "5000"
+    )]
+    close_epoch_slot_offset: u64,
+}
+
+fn elapsed_since(last_success: Option<&RunRecord>, interval: u64, now: DateTime<Utc>) -> bool {
+    match last_success {
+        Some(run) => {
+            let elapsed = now - run.finished_at;
+            elapsed.to_std().unwrap_or_default() >= Duration::from_secs(interval)
+        }
+        None => true,
+    }
+}
+
+/// Whether a task is due by its interval or the epoch progress, `None` when checks against
+/// the database or the RPC decide it.
+fn is_due_on_schedule(
+    task: Task,
+    params: &ScheduleParams,
+    epoch_info: &EpochInfo,
+    last_success: Option<&RunRecord>,
+    now: DateTime<Utc>,
+) -> Option<bool> {
+    match task {
+        Task::Validators => Some(
+            last_success.is_some_and(|run| run.epoch < epoch_info.epoch)
+                || elapsed_since(last_success, params.validators_interval, now),
+        ),
+        Task::Performance | Task::Uptime | Task::Commissions | Task::Versions => Some(
+            elapsed_since(last_success, params.quick_changes_interval, now),
+        ),
+        Task::Events => Some(elapsed_since(last_success, params.events_interval, now)),
+        Task::CloseEpoch => {
+            if epoch_info.epoch > 0 && epoch_info.slot_index >= params.close_epoch_slot_offset {
+                None
+            } else {
+                Some(false)
+            }
+        }
+        Task::JitoMev | Task::JitoPriority | Task::BlockRewards => None,
+    }
+}
+
+/// The previous epoch is closed once there is a record for it in `epochs`.
+async fn is_epoch_open(psql_client: &Client, epoch: u64) -> anyhow::Result<bool> {
+    let row = psql_client
+        .query_one(
+            "
+        SELECT
+            EXISTS(SELECT 1 FROM validators WHERE epoch = $1)
+            AND NOT EXISTS(SELECT 1 FROM epochs WHERE epoch = $1) AS open
+    ",
+            &[&Decimal::from(epoch)],
+        )
+        .await?;
+
+    Ok(row.get("open"))
+}
+
+/// Decides whether a task should run now, based on its last successful run, the epoch progress
+/// and, for the Jito and block rewards tasks, the same checks as the `check` CLI.
+pub async fn is_due(
+    task: Task,
+    params: &ScheduleParams,
+    epoch_info: &EpochInfo,
+    last_success: Option<&RunRecord>,
+    psql_client: &Client,
+    rpc_client: &dyn SolanaRpc,
+) -> anyhow::Result<bool> {
+    let due = match is_due_on_schedule(task, params, epoch_info, last_success, Utc::now()) {
+        Some(due) => due,
+        None => match task {
+            Task::JitoMev | Task::JitoPriority => {
+                let account_type = task.jito_account_type().unwrap();
+                let check_params: ValidatorsJitoCheckParams =
+                    parse_task_args(&task.to_string(), &params.jito_check_args)?;
+                check_jito(
+                    check_params,
+                    psql_client,
+                    rpc_client,
+                    account_type.db_table_name(),
+                )
+                .await?
+            }
+            Task::BlockRewards => {
+                let check_params: BlockRewardsCheckParams =
+                    parse_task_args(&task.to_string(), &params.block_rewards_check_args)?;
+                check_block_rewards(
+                    check_params,
+                    psql_client,
+                    rpc_client,
+                    VALIDATORS_BLOCK_REWARDS_TABLE,
+                )
+                .await?
+            }
+            Task::CloseEpoch => is_epoch_open(psql_client, epoch_info.epoch - 1).await?,
+            _ => unreachable!("Task {task} is due on schedule"),
+        },
+    };
+
+    if due {
+        info!("Task {task} is due");
+    }
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_due_on_schedule, ScheduleParams};
+    use crate::runs::{RunRecord, RunStatus};
+    use crate::tasks::Task;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use solana_sdk::epoch_info::EpochInfo;
+    use structopt::StructOpt;
+
+    fn params() -> ScheduleParams {
+        ScheduleParams::from_iter_safe(["schedule"]).unwrap()
+    }
+
+    fn epoch_info(epoch: u64, slot_index: u64) -> EpochInfo {
+        EpochInfo {
+            epoch,
+            slot_index,
+            slots_in_epoch: 432_000,
+            absolute_slot: epoch * 432_000 + slot_index,
+            block_height: 0,
+            transaction_count: None,
+        }
+    }
+
+    fn run(task: Task, epoch: u64, finished_at: DateTime<Utc>) -> RunRecord {
+        RunRecord {
+            task,
+            epoch,
+            slot_index: 0,
+            status: RunStatus::Success,
+            attempts: 1,
+            error: None,
+            started_at: finished_at,
+            finished_at,
+        }
+    }
+
+    #[test]
+    fn due_once_the_interval_elapsed() {
+        let params = params();
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let epoch_info = epoch_info(800, 1000);
+        let due = |task, finished_ago: i64| {
+            let last_success = run(task, 800, now - Duration::seconds(finished_ago));
+            is_due_on_schedule(task, &params, &epoch_info, Some(&last_success), now)
+        };
+
+        assert_eq!(due(Task::Performance, 599), Some(false));
+        assert_eq!(due(Task::Performance, 600), Some(true));
+        assert_eq!(due(Task::Versions, 601), Some(true));
+        assert_eq!(due(Task::Events, 3599), Some(false));
+        assert_eq!(due(Task::Events, 3600), Some(true));
+        assert_eq!(due(Task::Validators, 3599), Some(false));
+        assert_eq!(due(Task::Validators, 3600), Some(true));
+        // a run finishing after now, e.g. with a skewed clock, is not due
+        assert_eq!(due(Task::Uptime, -10), Some(false));
+        assert_eq!(
+            is_due_on_schedule(Task::Commissions, &params, &epoch_info, None, now),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn validators_due_once_the_epoch_changed() {
+        let params = params();
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let last_success = run(Task::Validators, 799, now - Duration::seconds(10));
+
+        assert_eq!(
+            is_due_on_schedule(
+                Task::Validators,
+                &params,
+                &epoch_info(800, 0),
+                Some(&last_success),
+                now
+            ),
+            Some(true)
+        );
+        assert_eq!(
+            is_due_on_schedule(
+                Task::Validators,
+                &params,
+                &epoch_info(799, 431_999),
+                Some(&last_success),
+                now
+            ),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn close_epoch_waits_for_the_slot_offset() {
+        let params = params();
+        let now = Utc::now();
+        let close_epoch = |epoch, slot_index| {
+            is_due_on_schedule(
+                Task::CloseEpoch,
+                &params,
+                &epoch_info(epoch, slot_index),
+                None,
+                now,
+            )
+        };
+
+        assert_eq!(close_epoch(800, 4999), Some(false));
+        assert_eq!(close_epoch(800, 5000), None);
+        assert_eq!(close_epoch(0, 5000), Some(false));
+        assert_eq!(
+            is_due_on_schedule(Task::JitoMev, &params, &epoch_info(800, 0), None, now),
+            None
+        );
+    }
+}
@@ -0,0 +1,427 @@
+use collect::common::CommonParams as CollectCommonParams;
+use collect::snapshot::write_snapshot;
+use collect::validators::{collect_validators_snapshot, ValidatorsParams};
+use collect::validators_block_rewards::{
+    collect_validator_block_rewards_snapshot, BlockRewardsParams,
+};
+use collect::validators_events::{collect_validator_events_snapshot, EventsParams};
+use collect::validators_jito::{collect_jito_snapshot, JitoAccountType, JitoParams};
+use collect::validators_performance::{
+    collect_validators_performance_snapshot, ValidatorsPerformanceParams,
+    ValidatorsPerformanceSnapshot,
+};
+use log::info;
+use serde::Serialize;
+use solana_sdk::epoch_info::EpochInfo;
+use std::fmt;
+use std::fs::File;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use store::close_epoch::close_epoch_snapshot;
+use store::cluster_info::store_cluster_info_snapshot;
+use store::commissions::store_commissions_snapshot;
+use store::report::finish_transaction;
+use store::snapshots::RawSnapshot;
+use store::uptime::store_uptime_snapshot;
+use store::validators::store_validators_snapshot;
+use store::validators_block_rewards::store_block_rewards_snapshot;
+use store::validators_events::store_events_snapshot;
+use store::validators_jito::store_jito_snapshot;
+use store::versions::store_versions_snapshot;
+use structopt::StructOpt;
+use tokio_postgres::Client;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Task {
+    Validators,
+    Performance,
+    Uptime,
+    Commissions,
+    Versions,
+    JitoMev,
+    JitoPriority,
+    BlockRewards,
+    Events,
+    CloseEpoch,
+}
+
+impl Task {
+    pub const ALL: [Task; 10] = [
+        Task::Validators,
+        Task::Performance,
+        Task::Uptime,
+        Task::Commissions,
+        Task::Versions,
+        Task::JitoMev,
+        Task::JitoPriority,
+        Task::BlockRewards,
+        Task::Events,
+        Task::CloseEpoch,
+    ];
+
+    pub fn jito_account_type(&self) -> Option<JitoAccountType> {
+        match self {
+            Task::JitoMev => Some(JitoAccountType::MevTipDistribution),
+            Task::JitoPriority => Some(JitoAccountType::PriorityFeeDistribution),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Task::Validators => write!(f, "validators"),
+            Task::Performance => write!(f, "performance"),
+            Task::Uptime => write!(f, "uptime"),
+            Task::Commissions => write!(f, "commissions"),
+            Task::Versions => write!(f, "versions"),
+            Task::JitoMev => write!(f, "jito-mev"),
+            Task::JitoPriority => write!(f, "jito-priority"),
+            Task::BlockRewards => write!(f, "block-rewards"),
+            Task::Events => write!(f, "events"),
+            Task::CloseEpoch => write!(f, "close-epoch"),
+        }
+    }
+}
+
+impl FromStr for Task {
+    type Err = anyhow::Error;
+
+    fn from_str(task: &str) -> Result<Self, Self::Err> {
+        Task::ALL
+            .into_iter()
+            .find(|known_task| known_task.to_string() == task)
+            .ok_or_else(|| anyhow::anyhow!("Unknown task '{task}'"))
+    }
+}
+
+pub enum TaskOutcome {
+    Stored,
+    Skipped(String),
+}
+
+/// Parses the extra arguments configured for a task the same way the `collect` CLI would.
+pub fn parse_task_args<P: StructOpt>(command: &str, args: &str) -> anyhow::Result<P> {
+    Ok(P::from_iter_safe(
+        std::iter::once(command).chain(args.split_whitespace()),
+    )?)
+}
+
+/// Extra `collect` arguments of the tasks, e.g. `--validators-args "--geoip-db /var/lib/geoip"`.
+#[derive(Debug, Clone, StructOpt)]
+pub struct TaskArgs {
+    #[structopt(
+        long = "validators-args",
+        help = "Arguments of `collect validators` used by the validators task.",
+        default_value = ""
+    )]
+    pub validators: String,
+
+    #[structopt(
+        long = "jito-args",
+        help = "Arguments of `collect jito-mev` / `collect jito-priority` used by the Jito tasks.",
+        default_value = ""
+    )]
+    pub jito: String,
+
+    #[structopt(
+        long = "block-rewards-args",
+        help = "Arguments of `collect validators-block-rewards` used by the block-rewards task.",
+        default_value = ""
+    )]
+    pub block_rewards: String,
+
+    #[structopt(
+        long = "events-args",
+        help = "Arguments of `collect validators-events` used by the events task.",
+        default_value = ""
+    )]
+    pub events: String,
+}
+
+/// State shared by the tasks run within one scheduler tick.
+pub struct TaskContext {
+    collect_common: Arc<CollectCommonParams>,
+    epoch_info: EpochInfo,
+    snapshot_dir: Option<PathBuf>,
+    task_args: TaskArgs,
+    performance_snapshot: Option<(ValidatorsPerformanceSnapshot, Arc<RawSnapshot>)>,
+}
+
+impl TaskContext {
+    pub fn new(
+        collect_common: Arc<CollectCommonParams>,
+        epoch_info: EpochInfo,
+        snapshot_dir: Option<PathBuf>,
+        task_args: TaskArgs,
+    ) -> Self {
+        Self {
+            collect_common,
+            epoch_info,
+            snapshot_dir,
+            task_args,
+            performance_snapshot: None,
+        }
+    }
+
+    /// Collecting uses blocking RPC and HTTP clients, so it runs outside of the async runtime.
+    async fn collect<T: Send + 'static>(
+        &self,
+        collect_snapshot: impl FnOnce(&CollectCommonParams) -> anyhow::Result<T> + Send + 'static,
+    ) -> anyhow::Result<T> {
+        let collect_common = self.collect_common.clone();
+        tokio::task::spawn_blocking(move || collect_snapshot(&collect_common)).await?
+    }
+
+    fn archive<T: Serialize>(&self, task: Task, snapshot: &T) -> anyhow::Result<()> {
+        if let Some(snapshot_dir) = &self.snapshot_dir {
+            let path = snapshot_dir.join(format!(
+                "{task}-{}-{}.{}",
+                self.epoch_info.epoch,
+                chrono::Utc::now().format("%Y%m%dT%H%M%S"),
+                self.collect_common.format
+            ));
+            info!("Archiving {task} snapshot to {}", path.display());
+            write_snapshot(File::create(path)?, self.collect_common.format, snapshot)?;
+        }
+
+        Ok(())
+    }
+
+    /// The current performance snapshot is collected once per tick and shared
+    /// by the performance, uptime, commissions and versions tasks,
+    /// so their records are tagged with the same archived snapshot.
+    async fn performance_snapshot(
+        &mut self,
+    ) -> anyhow::Result<(ValidatorsPerformanceSnapshot, Arc<RawSnapshot>)> {
+        if self.performance_snapshot.is_none() {
+            let performance_params: ValidatorsPerformanceParams =
+                parse_task_args("validators-performance", "")?;
+            let snapshot = self
+                .collect(move |common| {
+                    collect_validators_performance_snapshot(common, performance_params)
+                })
+                .await?;
+            self.archive(Task::Performance, &snapshot)?;
+            let raw = Arc::new(RawSnapshot::from_snapshot(&snapshot)?);
+            self.performance_snapshot = Some((snapshot, raw));
+        }
+
+        Ok(self.performance_snapshot.clone().unwrap())
+    }
+
+    /// Every task stores its snapshot in a single transaction, the changed tables are announced with the commit.
+    pub async fn run(
+        &mut self,
+        task: Task,
+        psql_client: &mut Client,
+    ) -> anyhow::Result<TaskOutcome> {
+        match task {
+            Task::Validators => {
+                let validators_params: ValidatorsParams =
+                    parse_task_args("validators", &self.task_args.validators)?;
+                let snapshot = self
+                    .collect(move |common| collect_validators_snapshot(common, validators_params))
+                    .await?;
+                self.archive(task, &snapshot)?;
+
+                let raw = RawSnapshot::from_snapshot(&snapshot)?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_validators_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::Performance => {
+                let (snapshot, raw) = self.performance_snapshot().await?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_cluster_info_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::Uptime => {
+                let (snapshot, raw) = self.performance_snapshot().await?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_uptime_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::Commissions => {
+                let (snapshot, raw) = self.performance_snapshot().await?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_commissions_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::Versions => {
+                let (snapshot, raw) = self.performance_snapshot().await?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_versions_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::JitoMev | Task::JitoPriority => {
+                let account_type = task.jito_account_type().unwrap();
+                let jito_params: JitoParams =
+                    parse_task_args(&task.to_string(), &self.task_args.jito)?;
+                let collected_account_type = account_type.clone();
+                let snapshot = self
+                    .collect(move |common| {
+                        collect_jito_snapshot(common, jito_params, collected_account_type)
+                    })
+                    .await?;
+                self.archive(task, &snapshot)?;
+                let raw = RawSnapshot::from_snapshot(&snapshot)?;
+                let transaction = psql_client.transaction().await?;
+                let report =
+                    store_jito_snapshot(snapshot, &raw, &transaction, account_type).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::BlockRewards => {
+                let rewards_params: BlockRewardsParams =
+                    parse_task_args("validators-block-rewards", &self.task_args.block_rewards)?;
+                let snapshot = self
+                    .collect(move |common| {
+                        collect_validator_block_rewards_snapshot(common, rewards_params)
+                    })
+                    .await?;
+                let Some(snapshot) = snapshot else {
+                    return Ok(TaskOutcome::Skipped(
+                        "No block rewards data in BigQuery for the epoch yet".to_string(),
+                    ));
+                };
+                self.archive(task, &snapshot)?;
+                let raw = RawSnapshot::from_snapshot(&snapshot)?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_block_rewards_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::Events => {
+                let events_params: EventsParams =
+                    parse_task_args("validators-events", &self.task_args.events)?;
+                let snapshot = self
+                    .collect(move |common| collect_validator_events_snapshot(common, events_params))
+                    .await?;
+                self.archive(task, &snapshot)?;
+                let raw = RawSnapshot::from_snapshot(&snapshot)?;
+                let transaction = psql_client.transaction().await?;
+                let report = store_events_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+            Task::CloseEpoch => {
+                let closed_epoch = self.epoch_info.epoch - 1;
+                let performance_params: ValidatorsPerformanceParams = parse_task_args(
+                    "validators-performance",
+                    &format!("--with-rewards --epoch {closed_epoch}"),
+                )?;
+                let snapshot = self
+                    .collect(move |common| {
+                        collect_validators_performance_snapshot(common, performance_params)
+                    })
+                    .await?;
+                self.archive(task, &snapshot)?;
+                let raw = RawSnapshot::from_snapshot(&snapshot)?;
+                let transaction = psql_client.transaction().await?;
+                let report = close_epoch_snapshot(snapshot, &raw, &transaction).await?;
+                finish_transaction(transaction, &report, false).await?;
+            }
+        }
+
+        Ok(TaskOutcome::Stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Task, TaskArgs, TaskContext};
+    use collect::common::CommonParams as CollectCommonParams;
+    use collect::validators_performance::{ValidatorPerformance, ValidatorsPerformanceSnapshot};
+    use solana_sdk::epoch_info::EpochInfo;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use store::notifications::{StoreNotification, STORE_CHANNEL};
+    use store::snapshots::RawSnapshot;
+    use store::testing::TestDatabase;
+    use structopt::StructOpt;
+    use tokio::time::timeout;
+
+    #[test]
+    fn task_names_round_trip() {
+        for task in Task::ALL {
+            assert_eq!(task.to_string().parse::<Task>().unwrap(), task);
+        }
+        assert_eq!("jito-mev".parse::<Task>().unwrap(), Task::JitoMev);
+        assert_eq!("close-epoch".parse::<Task>().unwrap(), Task::CloseEpoch);
+        assert!("jito".parse::<Task>().is_err());
+    }
+
+    #[tokio::test]
+    async fn scheduled_stores_are_announced() {
+        let Some(database) = TestDatabase::create("scheduled_stores_are_announced")
+            .await
+            .unwrap()
+        else {
+            return;
+        };
+        let (_listener, mut notifications) = database.listen(STORE_CHANNEL).await.unwrap();
+
+        let snapshot = ValidatorsPerformanceSnapshot {
+            version: 1,
+            epoch: 700,
+            epoch_slot: 1000,
+            transaction_count: 0,
+            created_at: "2024-11-20T10:00:00Z".to_string(),
+            cluster_inflation: None,
+            validators: HashMap::from([(
+                "vote-account".to_string(),
+                ValidatorPerformance {
+                    commission: 5,
+                    version: Some("2.1.5".to_string()),
+                    client: None,
+                    feature_set: None,
+                    credits: 0,
+                    leader_slots: 0,
+                    blocks_produced: 0,
+                    skip_rate: 0.0,
+                    delinquent: false,
+                },
+            )]),
+            rewards: None,
+        };
+        let raw = Arc::new(RawSnapshot::from_snapshot(&snapshot).unwrap());
+        let mut context = TaskContext::new(
+            Arc::new(CollectCommonParams::from_iter(["collect"])),
+            EpochInfo {
+                epoch: 700,
+                slot_index: 1000,
+                slots_in_epoch: 432000,
+                absolute_slot: 700 * 432000 + 1000,
+                block_height: 0,
+                transaction_count: None,
+            },
+            None,
+            TaskArgs::from_iter(["scheduler"]),
+        );
+        // the collected snapshot is shared by the tasks, no RPC is needed with it in place
+        context.performance_snapshot = Some((snapshot, raw));
+
+        let mut psql_client = database.client().await.unwrap();
+        context.run(Task::Versions, &mut psql_client).await.unwrap();
+
+        let mut announced = vec![];
+        while announced.len() < 2 {
+            let notification = timeout(Duration::from_secs(5), notifications.recv())
+                .await
+                .expect("The stored tables were not announced")
+                .unwrap();
+            announced
+                .push(serde_json::from_str::<StoreNotification>(notification.payload()).unwrap());
+        }
+        for dataset in ["snapshots", "versions"] {
+            assert!(
+                announced.contains(&StoreNotification {
+                    dataset: dataset.to_string(),
+                    epoch: Some(700),
+                }),
+                "{dataset} not announced in {announced:?}"
+            );
+        }
+    }
+}
@@ -5,9 +5,9 @@ set -e
 SCRIPT_DIR=$(dirname "$0")
 BIN_DIR="${BIN_DIR:-"$SCRIPT_DIR/../target/debug"}"
 
-if [[ -z $WHOIS_BEARER_TOKEN ]]
+if [[ -z $WHOIS_BEARER_TOKEN ]] && [[ -z $GEOIP_DB ]]
 then
-  echo "Env variable WHOIS_BEARER_TOKEN is missing!" >&2
+  echo "Env variable WHOIS_BEARER_TOKEN or GEOIP_DB is missing!" >&2
   exit 1
 fi
 
@@ -17,9 +17,20 @@ then
   exit 1
 fi
 
+if [[ -n $GEOIP_DB ]]
+then
+  DATA_CENTER_ARGS=(--geoip-db "$GEOIP_DB")
+else
+  DATA_CENTER_ARGS=(--whois "https://whois.marinade.finance" --whois-bearer-token "$WHOIS_BEARER_TOKEN")
+fi
+
+if [[ -n $IP_INFO_CACHE ]]
+then
+  DATA_CENTER_ARGS+=(--ip-info-cache "$IP_INFO_CACHE")
+fi
+
 "$BIN_DIR/collect" \
   --url "$RPC_URL" \
   validators \
-    --whois "https://whois.marinade.finance" \
-    --whois-bearer-token "$WHOIS_BEARER_TOKEN" \
+    "${DATA_CENTER_ARGS[@]}" \
     --bonds-url "$BONDS_URL"
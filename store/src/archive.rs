@@ -0,0 +1,1066 @@
+use crate::bulk::{copy_in, BulkColumn};
+use crate::migrations::latest_schema_version;
+use crate::report::{finish_transaction, StoreReport, TableStats};
+use crate::utils::DEFAULT_CACHE_EPOCHS;
+use arrow::array::{
+    ArrayRef, AsArray, BooleanArray, Float64Array, Int32Array, Int64Array, StringArray,
+    TimestampMicrosecondArray,
+};
+use arrow::datatypes::{
+    DataType, Field, Float64Type, Int32Type, Int64Type, Schema, SchemaRef, TimeUnit,
+    TimestampMicrosecondType,
+};
+use arrow::record_batch::RecordBatch;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, TryStreamExt};
+use log::{info, warn};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use structopt::StructOpt;
+use tokio_postgres::types::{FromSql, ToSql, Type};
+use tokio_postgres::{Client, GenericClient, IsolationLevel, Row, Transaction};
+
+/// Tables with records per epoch growing forever, while the API only reads the last `DEFAULT_CACHE_EPOCHS` of them.
+pub const ARCHIVABLE_TABLES: &[&str] = &["validators", "uptimes", "versions", "commissions"];
+
+const MANIFEST_FILE: &str = "manifest.json";
+const BATCH_SIZE: usize = 10_000;
+const CSV_CHUNK_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Parquet,
+    Csv,
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "parquet" => Ok(ArchiveFormat::Parquet),
+            "csv" => Ok(ArchiveFormat::Csv),
+            _ => anyhow::bail!("Unknown archive format '{format}', expected parquet or csv"),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveFormat::Parquet => write!(f, "parquet"),
+            ArchiveFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ArchiveParams {
+    #[structopt(
+        long = "before-epoch",
+        help = "Export the records of the epochs before this one."
+    )]
+    before_epoch: u64,
+
+    #[structopt(
+        long = "out",
+        help = "Directory to write the exported files and their manifest to."
+    )]
+    out: PathBuf,
+
+    #[structopt(
+        long = "tables",
+        help = "Comma-separated tables to export, all archivable tables by default.",
+        use_delimiter = true
+    )]
+    tables: Vec<String>,
+
+    #[structopt(
+        long = "format",
+        help = "Format of the exported files: parquet or csv.",
+        default_value = "parquet"
+    )]
+    format: ArchiveFormat,
+
+    #[structopt(
+        long = "delete",
+        help = "Delete the exported records once the written files were verified."
+    )]
+    delete: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RestoreParams {
+    #[structopt(
+        long = "from",
+        help = "Directory with the manifest written by `store archive`."
+    )]
+    from: PathBuf,
+
+    #[structopt(
+        long = "tables",
+        help = "Comma-separated tables to restore, all tables of the manifest by default.",
+        use_delimiter = true
+    )]
+    tables: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub before_epoch: u64,
+    pub format: ArchiveFormat,
+    pub schema_version: i32,
+    pub created_at: String,
+    pub tables: Vec<ArchivedTable>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivedTable {
+    pub table: String,
+    pub file: String,
+    pub rows: u64,
+    pub min_epoch: Option<u64>,
+    pub max_epoch: Option<u64>,
+    pub columns: Vec<ArchivedColumn>,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivedColumn {
+    pub name: String,
+    pub sql_type: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ColumnType {
+    Text,
+    Int4,
+    Int8,
+    Float8,
+    Bool,
+    Numeric,
+    Timestamptz,
+}
+
+impl ColumnType {
+    fn from_sql_type(sql_type: &str) -> anyhow::Result<Self> {
+        match sql_type {
+            "text" => Ok(ColumnType::Text),
+            "int4" => Ok(ColumnType::Int4),
+            "int8" => Ok(ColumnType::Int8),
+            "float8" => Ok(ColumnType::Float8),
+            "bool" => Ok(ColumnType::Bool),
+            "numeric" => Ok(ColumnType::Numeric),
+            "timestamptz" => Ok(ColumnType::Timestamptz),
+            _ => anyhow::bail!("Columns of type {sql_type} cannot be archived"),
+        }
+    }
+
+    fn sql_type(self) -> Type {
+        match self {
+            ColumnType::Text => Type::TEXT,
+            ColumnType::Int4 => Type::INT4,
+            ColumnType::Int8 => Type::INT8,
+            ColumnType::Float8 => Type::FLOAT8,
+            ColumnType::Bool => Type::BOOL,
+            ColumnType::Numeric => Type::NUMERIC,
+            ColumnType::Timestamptz => Type::TIMESTAMPTZ,
+        }
+    }
+
+    /// NUMERIC values exceed the Parquet integers, they are kept as decimal strings.
+    fn arrow_type(self) -> DataType {
+        match self {
+            ColumnType::Text | ColumnType::Numeric => DataType::Utf8,
+            ColumnType::Int4 => DataType::Int32,
+            ColumnType::Int8 => DataType::Int64,
+            ColumnType::Float8 => DataType::Float64,
+            ColumnType::Bool => DataType::Boolean,
+            ColumnType::Timestamptz => {
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+            }
+        }
+    }
+}
+
+fn column_types(columns: &[ArchivedColumn]) -> anyhow::Result<Vec<ColumnType>> {
+    columns
+        .iter()
+        .map(|column| ColumnType::from_sql_type(&column.sql_type))
+        .collect()
+}
+
+fn arrow_schema(columns: &[ArchivedColumn], column_types: &[ColumnType]) -> SchemaRef {
+    Arc::new(Schema::new(
+        columns
+            .iter()
+            .zip(column_types)
+            .map(|(column, column_type)| Field::new(&column.name, column_type.arrow_type(), true))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+fn column_list(columns: &[ArchivedColumn]) -> String {
+    columns
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn selected_tables(tables: &[String]) -> anyhow::Result<Vec<&'static str>> {
+    if tables.is_empty() {
+        return Ok(ARCHIVABLE_TABLES.to_vec());
+    }
+
+    tables
+        .iter()
+        .map(|table| {
+            ARCHIVABLE_TABLES
+                .iter()
+                .copied()
+                .find(|archivable| *archivable == table.as_str())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Table {table} cannot be archived, expected one of: {}",
+                        ARCHIVABLE_TABLES.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+async fn load_table_columns(
+    psql_client: &impl GenericClient,
+    table: &str,
+) -> anyhow::Result<Vec<ArchivedColumn>> {
+    let columns: Vec<_> = psql_client
+        .query(
+            "
+        SELECT column_name::TEXT AS name, udt_name::TEXT AS sql_type
+        FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = $1
+        ORDER BY ordinal_position
+    ",
+            &[&table],
+        )
+        .await?
+        .iter()
+        .map(|row| ArchivedColumn {
+            name: row.get("name"),
+            sql_type: row.get("sql_type"),
+        })
+        .collect();
+    if columns.is_empty() {
+        anyhow::bail!("Table {table} does not exist");
+    }
+
+    Ok(columns)
+}
+
+/// The API serves the last `DEFAULT_CACHE_EPOCHS` epochs, so their records must stay in the database.
+async fn check_epochs_not_served(psql_client: &Client, before_epoch: u64) -> anyhow::Result<()> {
+    let last_epoch: Option<Decimal> = psql_client
+        .query_one("SELECT MAX(epoch) AS last_epoch FROM validators", &[])
+        .await?
+        .get("last_epoch");
+    let last_epoch: u64 = last_epoch.unwrap_or_default().try_into()?;
+    let first_served_epoch = last_epoch.saturating_sub(DEFAULT_CACHE_EPOCHS);
+    if before_epoch > first_served_epoch {
+        anyhow::bail!(
+            "Refusing to delete records of epochs served by the API, --before-epoch must not exceed {first_served_epoch}"
+        );
+    }
+
+    Ok(())
+}
+
+fn file_sha256(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Reads the whole file back, returning the number of records and the checksum of the file.
+fn verify_file(path: &Path, format: ArchiveFormat) -> anyhow::Result<(u64, String)> {
+    let mut rows = 0;
+    match format {
+        ArchiveFormat::Csv => {
+            for record in csv::Reader::from_path(path)?.records() {
+                record?;
+                rows += 1;
+            }
+        }
+        ArchiveFormat::Parquet => {
+            for batch in ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()? {
+                rows += batch?.num_rows() as u64;
+            }
+        }
+    }
+
+    Ok((rows, file_sha256(path)?))
+}
+
+/// A record read from the database, by the column index.
+trait ArchiveRow {
+    fn value<'a, T: FromSql<'a>>(&'a self, index: usize) -> T;
+}
+
+impl ArchiveRow for Row {
+    fn value<'a, T: FromSql<'a>>(&'a self, index: usize) -> T {
+        self.get(index)
+    }
+}
+
+fn record_batch(
+    schema: &SchemaRef,
+    column_types: &[ColumnType],
+    rows: &[impl ArchiveRow],
+) -> anyhow::Result<RecordBatch> {
+    let columns: Vec<ArrayRef> = column_types
+        .iter()
+        .enumerate()
+        .map(|(index, column_type)| -> ArrayRef {
+            match column_type {
+                ColumnType::Text => Arc::new(
+                    rows.iter()
+                        .map(|row| row.value::<Option<String>>(index))
+                        .collect::<StringArray>(),
+                ),
+                ColumnType::Int4 => Arc::new(
+                    rows.iter()
+                        .map(|row| row.value::<Option<i32>>(index))
+                        .collect::<Int32Array>(),
+                ),
+                ColumnType::Int8 => Arc::new(
+                    rows.iter()
+                        .map(|row| row.value::<Option<i64>>(index))
+                        .collect::<Int64Array>(),
+                ),
+                ColumnType::Float8 => Arc::new(
+                    rows.iter()
+                        .map(|row| row.value::<Option<f64>>(index))
+                        .collect::<Float64Array>(),
+                ),
+                ColumnType::Bool => Arc::new(
+                    rows.iter()
+                        .map(|row| row.value::<Option<bool>>(index))
+                        .collect::<BooleanArray>(),
+                ),
+                ColumnType::Numeric => Arc::new(
+                    rows.iter()
+                        .map(|row| {
+                            row.value::<Option<Decimal>>(index)
+                                .map(|value| value.to_string())
+                        })
+                        .collect::<StringArray>(),
+                ),
+                ColumnType::Timestamptz => Arc::new(
+                    rows.iter()
+                        .map(|row| {
+                            row.value::<Option<DateTime<Utc>>>(index)
+                                .map(|value| value.timestamp_micros())
+                        })
+                        .collect::<TimestampMicrosecondArray>()
+                        .with_timezone("UTC"),
+                ),
+            }
+        })
+        .collect();
+
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+fn boxed<T: ToSql + Send + Sync + 'static>(value: T) -> Box<dyn ToSql + Send + Sync> {
+    Box::new(value)
+}
+
+/// Values of the batch by column.
+fn column_values(
+    batch: &RecordBatch,
+    column_types: &[ColumnType],
+) -> anyhow::Result<Vec<Vec<Box<dyn ToSql + Send + Sync>>>> {
+    column_types
+        .iter()
+        .enumerate()
+        .map(|(index, column_type)| {
+            let array = batch.column(index);
+            if array.data_type() != &column_type.arrow_type() {
+                anyhow::bail!(
+                    "Column {} is stored as {}, expected {}",
+                    batch.schema().field(index).name(),
+                    array.data_type(),
+                    column_type.arrow_type()
+                );
+            }
+
+            Ok(match column_type {
+                ColumnType::Text => array
+                    .as_string::<i32>()
+                    .iter()
+                    .map(|value| boxed(value.map(str::to_string)))
+                    .collect(),
+                ColumnType::Int4 => array
+                    .as_primitive::<Int32Type>()
+                    .iter()
+                    .map(boxed)
+                    .collect(),
+                ColumnType::Int8 => array
+                    .as_primitive::<Int64Type>()
+                    .iter()
+                    .map(boxed)
+                    .collect(),
+                ColumnType::Float8 => array
+                    .as_primitive::<Float64Type>()
+                    .iter()
+                    .map(boxed)
+                    .collect(),
+                ColumnType::Bool => array.as_boolean().iter().map(boxed).collect(),
+                ColumnType::Numeric => array
+                    .as_string::<i32>()
+                    .iter()
+                    .map(|value| Ok(boxed(value.map(Decimal::from_str).transpose()?)))
+                    .collect::<anyhow::Result<_>>()?,
+                ColumnType::Timestamptz => array
+                    .as_primitive::<TimestampMicrosecondType>()
+                    .iter()
+                    .map(|value| {
+                        let timestamp = value
+                            .map(|micros| {
+                                DateTime::from_timestamp_micros(micros).ok_or_else(|| {
+                                    anyhow::anyhow!("Timestamp {micros} is out of range")
+                                })
+                            })
+                            .transpose()?;
+                        Ok(boxed(timestamp))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            })
+        })
+        .collect()
+}
+
+async fn export_csv(
+    transaction: &Transaction<'_>,
+    select: &str,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let stream = transaction
+        .copy_out(&format!("COPY ({select}) TO STDOUT (FORMAT csv, HEADER)"))
+        .await?;
+    tokio::pin!(stream);
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    while let Some(chunk) = stream.try_next().await? {
+        writer.write_all(&chunk)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+async fn export_parquet(
+    transaction: &Transaction<'_>,
+    select: &str,
+    columns: &[ArchivedColumn],
+    path: &Path,
+) -> anyhow::Result<()> {
+    let column_types = column_types(columns)?;
+    let schema = arrow_schema(columns, &column_types);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))?;
+
+    let rows = transaction
+        .query_raw(select, std::iter::empty::<&(dyn ToSql + Sync)>())
+        .await?;
+    tokio::pin!(rows);
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while let Some(row) = rows.try_next().await? {
+        batch.push(row);
+        if batch.len() == BATCH_SIZE {
+            writer.write(&record_batch(&schema, &column_types, &batch)?)?;
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        writer.write(&record_batch(&schema, &column_types, &batch)?)?;
+    }
+    writer.close()?;
+
+    Ok(())
+}
+
+async fn export_table(
+    transaction: &Transaction<'_>,
+    table: &str,
+    before_epoch: u64,
+    format: ArchiveFormat,
+    out: &Path,
+) -> anyhow::Result<ArchivedTable> {
+    let columns = load_table_columns(transaction, table).await?;
+    let stats = transaction
+        .query_one(
+            &format!(
+                "SELECT COUNT(*) AS records, MIN(epoch) AS min_epoch, MAX(epoch) AS max_epoch FROM {table} WHERE epoch < $1"
+            ),
+            &[&Decimal::from(before_epoch)],
+        )
+        .await?;
+    let rows = stats.get::<_, i64>("records") as u64;
+    let min_epoch: Option<Decimal> = stats.get("min_epoch");
+    let max_epoch: Option<Decimal> = stats.get("max_epoch");
+
+    let file = format!("{table}-before-{before_epoch}.{format}");
+    let path = out.join(&file);
+    info!("Exporting {rows} records of {table} to {}", path.display());
+
+    // COPY does not take parameters, the epoch is a plain number
+    let select = format!(
+        "SELECT {} FROM {table} WHERE epoch < {before_epoch} ORDER BY epoch",
+        column_list(&columns)
+    );
+    match format {
+        ArchiveFormat::Csv => export_csv(transaction, &select, &path).await?,
+        ArchiveFormat::Parquet => export_parquet(transaction, &select, &columns, &path).await?,
+    }
+
+    let (written_rows, sha256) = verify_file(&path, format)?;
+    if written_rows != rows {
+        anyhow::bail!(
+            "{} contains {written_rows} records, expected {rows} records of {table}",
+            path.display()
+        );
+    }
+
+    Ok(ArchivedTable {
+        table: table.to_string(),
+        file,
+        rows,
+        min_epoch: min_epoch.map(u64::try_from).transpose()?,
+        max_epoch: max_epoch.map(u64::try_from).transpose()?,
+        columns,
+        sha256,
+    })
+}
+
+/// Exports and deletes in a single repeatable read transaction, so exactly the exported records are deleted.
+/// The tables with deleted records are announced with the commit.
+pub async fn archive(
+    params: ArchiveParams,
+    psql_client: &mut Client,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let tables = selected_tables(&params.tables)?;
+    if params.delete {
+        check_epochs_not_served(psql_client, params.before_epoch).await?;
+    }
+
+    let transaction = psql_client
+        .build_transaction()
+        .isolation_level(IsolationLevel::RepeatableRead)
+        .start()
+        .await?;
+
+    if dry_run {
+        for table in tables {
+            let rows: i64 = transaction
+                .query_one(
+                    &format!("SELECT COUNT(*) AS records FROM {table} WHERE epoch < $1"),
+                    &[&Decimal::from(params.before_epoch)],
+                )
+                .await?
+                .get("records");
+            info!(
+                "{table}: {rows} records would be archived{}",
+                if params.delete { " and deleted" } else { "" }
+            );
+        }
+        transaction.rollback().await?;
+        info!("Dry run, nothing was exported");
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&params.out)?;
+    let manifest_path = params.out.join(MANIFEST_FILE);
+    if manifest_path.exists() {
+        anyhow::bail!(
+            "{} already exists, archive to another directory",
+            manifest_path.display()
+        );
+    }
+
+    let mut manifest = ArchiveManifest {
+        before_epoch: params.before_epoch,
+        format: params.format,
+        schema_version: latest_schema_version(),
+        created_at: Utc::now().to_string(),
+        tables: vec![],
+    };
+    for table in tables {
+        manifest.tables.push(
+            export_table(
+                &transaction,
+                table,
+                params.before_epoch,
+                params.format,
+                &params.out,
+            )
+            .await?,
+        );
+    }
+    serde_json::to_writer_pretty(File::create(&manifest_path)?, &manifest)?;
+    info!("Wrote the manifest to {}", manifest_path.display());
+
+    let mut report = StoreReport::default();
+    if params.delete {
+        for archived in &manifest.tables {
+            let deleted = transaction
+                .execute(
+                    &format!("DELETE FROM {} WHERE epoch < $1", archived.table),
+                    &[&Decimal::from(params.before_epoch)],
+                )
+                .await?;
+            if deleted != archived.rows {
+                anyhow::bail!(
+                    "Deleted {deleted} records of {}, but {} were archived",
+                    archived.table,
+                    archived.rows
+                );
+            }
+            info!("Deleted {deleted} archived records of {}", archived.table);
+            report.table(&archived.table).deleted = deleted;
+            if let Some(max_epoch) = archived.max_epoch {
+                report.epoch(max_epoch);
+            }
+        }
+    }
+
+    finish_transaction(transaction, &report, false).await
+}
+
+async fn restore_csv(
+    transaction: &Transaction<'_>,
+    temp_table: &str,
+    columns: &[ArchivedColumn],
+    path: &Path,
+) -> anyhow::Result<u64> {
+    let sink = transaction
+        .copy_in(&format!(
+            "COPY {temp_table} ({}) FROM STDIN (FORMAT csv, HEADER)",
+            column_list(columns)
+        ))
+        .await?;
+    tokio::pin!(sink);
+
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; CSV_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sink.send(Bytes::copy_from_slice(&buffer[..read])).await?;
+    }
+
+    Ok(sink.finish().await?)
+}
+
+async fn restore_parquet(
+    transaction: &Transaction<'_>,
+    temp_table: &str,
+    columns: &[ArchivedColumn],
+    path: &Path,
+) -> anyhow::Result<u64> {
+    let column_types = column_types(columns)?;
+    let bulk_columns: Vec<_> = columns
+        .iter()
+        .zip(&column_types)
+        .map(|(column, column_type)| BulkColumn::new(column.name.clone(), column_type.sql_type()))
+        .collect();
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+        .with_batch_size(BATCH_SIZE)
+        .build()?;
+    let mut loaded = 0;
+    for batch in reader {
+        let batch = batch?;
+        let values = column_values(&batch, &column_types)?;
+        let rows = (0..batch.num_rows()).map(|index| {
+            let row: Vec<&(dyn ToSql + Sync)> = values
+                .iter()
+                .map(|column| column[index].as_ref() as &(dyn ToSql + Sync))
+                .collect();
+            row
+        });
+        loaded += copy_in(transaction, temp_table, &bulk_columns, rows).await?;
+    }
+
+    Ok(loaded)
+}
+
+/// Records that are in the table already are skipped, so a restore can be repeated.
+async fn restore_table(
+    transaction: &Transaction<'_>,
+    archived: &ArchivedTable,
+    format: ArchiveFormat,
+    path: &Path,
+) -> anyhow::Result<TableStats> {
+    let table = selected_tables(&[archived.table.clone()])?[0];
+    let table_columns = load_table_columns(transaction, table).await?;
+    for column in &archived.columns {
+        match table_columns.iter().find(|c| c.name == column.name) {
+            Some(table_column) if table_column.sql_type == column.sql_type => {}
+            Some(table_column) => anyhow::bail!(
+                "Column {table}.{} is {} in the archive but {} in the database",
+                column.name,
+                column.sql_type,
+                table_column.sql_type
+            ),
+            None => anyhow::bail!(
+                "Column {table}.{} of the archive does not exist in the database",
+                column.name
+            ),
+        }
+    }
+
+    let temp_table = format!("{table}_restore");
+    transaction
+        .batch_execute(&format!(
+            "CREATE TEMPORARY TABLE {temp_table} (LIKE {table} INCLUDING DEFAULTS) ON COMMIT DROP"
+        ))
+        .await?;
+    let loaded = match format {
+        ArchiveFormat::Csv => {
+            restore_csv(transaction, &temp_table, &archived.columns, path).await?
+        }
+        ArchiveFormat::Parquet => {
+            restore_parquet(transaction, &temp_table, &archived.columns, path).await?
+        }
+    };
+
+    let columns = column_list(&archived.columns);
+    let inserted = transaction
+        .execute(
+            &format!(
+                "INSERT INTO {table} ({columns}) SELECT {columns} FROM {temp_table} ON CONFLICT DO NOTHING"
+            ),
+            &[],
+        )
+        .await?;
+    info!("Restored {inserted} of {loaded} archived records of {table}");
+
+    Ok(TableStats {
+        inserted,
+        updated: 0,
+        skipped: loaded - inserted,
+        deleted: 0,
+    })
+}
+
+/// All files are verified against the manifest before the database is touched.
+pub async fn restore(
+    params: RestoreParams,
+    psql_client: &mut Client,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let manifest_path = params.from.join(MANIFEST_FILE);
+    let manifest: ArchiveManifest =
+        serde_json::from_reader(File::open(&manifest_path)?).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read the manifest '{}': {e}",
+                manifest_path.display()
+            )
+        })?;
+    if manifest.schema_version != latest_schema_version() {
+        warn!(
+            "The archive was written at schema version {}, the database is at version {}",
+            manifest.schema_version,
+            latest_schema_version()
+        );
+    }
+
+    for table in &params.tables {
+        if !manifest
+            .tables
+            .iter()
+            .any(|archived| &archived.table == table)
+        {
+            anyhow::bail!("Table {table} is not in the manifest");
+        }
+    }
+    let tables: Vec<_> = manifest
+        .tables
+        .iter()
+        .filter(|archived| params.tables.is_empty() || params.tables.contains(&archived.table))
+        .collect();
+
+    for archived in &tables {
+        let path = params.from.join(&archived.file);
+        let (rows, sha256) = verify_file(&path, manifest.format)?;
+        if sha256 != archived.sha256 {
+            anyhow::bail!(
+                "{} does not match the checksum of the manifest",
+                path.display()
+            );
+        }
+        if rows != archived.rows {
+            anyhow::bail!(
+                "{} contains {rows} records, the manifest lists {}",
+                path.display(),
+                archived.rows
+            );
+        }
+    }
+
+    let transaction = psql_client.transaction().await?;
+    let mut report = StoreReport::default();
+    for archived in tables {
+        let path = params.from.join(&archived.file);
+        *report.table(&archived.table) =
+            restore_table(&transaction, archived, manifest.format, &path).await?;
+    }
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        archive, arrow_schema, column_types, column_values, record_batch, ArchiveParams,
+        ArchiveRow, ArchivedColumn,
+    };
+    use crate::notifications::{StoreNotification, STORE_CHANNEL};
+    use crate::testing::TestDatabase;
+    use bytes::{Bytes, BytesMut};
+    use chrono::{DateTime, Utc};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::arrow::ArrowWriter;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use structopt::StructOpt;
+    use tokio::time::timeout;
+    use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+    /// Values encoded the way the database sends them, `None` for NULL.
+    struct EncodedRow(Vec<(Type, Option<BytesMut>)>);
+
+    impl ArchiveRow for EncodedRow {
+        fn value<'a, T: FromSql<'a>>(&'a self, index: usize) -> T {
+            let (ty, raw) = &self.0[index];
+            match raw {
+                Some(raw) => T::from_sql(ty, raw),
+                None => T::from_sql_null(ty),
+            }
+            .unwrap()
+        }
+    }
+
+    fn encode(value: &dyn ToSql, ty: &Type) -> Option<BytesMut> {
+        let mut raw = BytesMut::new();
+        match value.to_sql_checked(ty, &mut raw).unwrap() {
+            IsNull::Yes => None,
+            IsNull::No => Some(raw),
+        }
+    }
+
+    #[test]
+    fn records_round_trip_through_parquet() {
+        let columns: Vec<_> = [
+            ("info_name", "text"),
+            ("commission", "int4"),
+            ("credits", "int8"),
+            ("skip_rate", "float8"),
+            ("delinquent", "bool"),
+            ("activated_stake", "numeric"),
+            ("created_at", "timestamptz"),
+        ]
+        .iter()
+        .map(|(name, sql_type)| ArchivedColumn {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+        })
+        .collect();
+        let column_types = column_types(&columns).unwrap();
+
+        // beyond any Parquet integer, and with a scale to keep
+        let large_stake = Decimal::from_str("79228162514264337593543950335").unwrap();
+        let apy = Decimal::from_str("0.068100").unwrap();
+        let created_at = DateTime::<Utc>::from_timestamp_micros(1_748_781_015_123_456).unwrap();
+        let values: [[&(dyn ToSql + Sync); 7]; 3] = [
+            [
+                &"Validator",
+                &7i32,
+                &i64::MAX,
+                &0.0375f64,
+                &true,
+                &large_stake,
+                &created_at,
+            ],
+            [
+                &None::<String>,
+                &None::<i32>,
+                &None::<i64>,
+                &None::<f64>,
+                &None::<bool>,
+                &None::<Decimal>,
+                &None::<DateTime<Utc>>,
+            ],
+            [
+                &"",
+                &-1i32,
+                &0i64,
+                &-1.5f64,
+                &false,
+                &apy,
+                &None::<DateTime<Utc>>,
+            ],
+        ];
+        let rows: Vec<_> = values
+            .iter()
+            .map(|row| {
+                EncodedRow(
+                    row.iter()
+                        .zip(&column_types)
+                        .map(|(value, column_type)| {
+                            let ty = column_type.sql_type();
+                            let raw = encode(*value, &ty);
+                            (ty, raw)
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let schema = arrow_schema(&columns, &column_types);
+        let mut file = vec![];
+        let mut writer = ArrowWriter::try_new(&mut file, schema.clone(), None).unwrap();
+        writer
+            .write(&record_batch(&schema, &column_types, &rows).unwrap())
+            .unwrap();
+        writer.close().unwrap();
+
+        let batches: Vec<_> = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file))
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), rows.len());
+
+        let restored = column_values(&batches[0], &column_types).unwrap();
+        for (row_index, row) in rows.iter().enumerate() {
+            for (column_index, (ty, raw)) in row.0.iter().enumerate() {
+                assert_eq!(
+                    &encode(restored[column_index][row_index].as_ref(), ty),
+                    raw,
+                    "{} of record {row_index}",
+                    columns[column_index].name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mismatched_column_types() {
+        let columns = [ArchivedColumn {
+            name: "epoch".to_string(),
+            sql_type: "numeric".to_string(),
+        }];
+        let numeric = column_types(&columns).unwrap();
+        let rows = [EncodedRow(vec![(
+            Type::NUMERIC,
+            encode(&Decimal::from(800), &Type::NUMERIC),
+        )])];
+        let batch = record_batch(&arrow_schema(&columns, &numeric), &numeric, &rows).unwrap();
+
+        let int8 = column_types(&[ArchivedColumn {
+            name: "epoch".to_string(),
+            sql_type: "int8".to_string(),
+        }])
+        .unwrap();
+        assert!(column_values(&batch, &int8).is_err());
+        assert!(column_types(&[ArchivedColumn {
+            name: "ip".to_string(),
+            sql_type: "inet".to_string(),
+        }])
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn deleted_records_are_announced() {
+        let Some(database) = TestDatabase::create("deleted_records_are_announced")
+            .await
+            .unwrap()
+        else {
+            return;
+        };
+        let mut psql_client = database.client().await.unwrap();
+        psql_client
+            .batch_execute(
+                "
+            INSERT INTO validators (identity, vote_account, epoch, activated_stake, marinade_stake, marinade_native_stake,
+                superminority, stake_to_become_superminority, credits, leader_slots, blocks_produced, skip_rate, updated_at)
+            VALUES ('node', 'vote', 100, 0, 0, 0, FALSE, 0, 0, 0, 0, 0, NOW());
+            INSERT INTO versions (vote_account, version, epoch, epoch_slot, created_at)
+            VALUES ('vote', '2.1.5', 10, 0, NOW()), ('vote', '2.1.6', 11, 0, NOW()), ('vote', '2.2.0', 100, 0, NOW());
+        ",
+            )
+            .await
+            .unwrap();
+        let (_listener, mut notifications) = database.listen(STORE_CHANNEL).await.unwrap();
+
+        let out = std::env::temp_dir().join(format!("archive-delete-{}", std::process::id()));
+        let params = ArchiveParams::from_iter([
+            "archive",
+            "--before-epoch",
+            "12",
+            "--tables",
+            "versions",
+            "--delete",
+            "--out",
+            out.to_str().unwrap(),
+        ]);
+        archive(params, &mut psql_client, false).await.unwrap();
+        std::fs::remove_dir_all(out).unwrap();
+
+        let notification = timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .expect("The deletion was not announced")
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<StoreNotification>(notification.payload()).unwrap(),
+            StoreNotification {
+                dataset: "versions".to_string(),
+                epoch: Some(11),
+            }
+        );
+        let remaining: i64 = psql_client
+            .query_one("SELECT COUNT(*) FROM versions", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(remaining, 1);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::report::TableStats;
+use log::debug;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::Transaction;
+
+/// Column of a bulk-loaded table, the values must serialize to exactly this type.
+pub struct BulkColumn {
+    pub name: String,
+    pub sql_type: Type,
+}
+
+impl BulkColumn {
+    pub fn new(name: impl Into<String>, sql_type: Type) -> Self {
+        Self {
+            name: name.into(),
+            sql_type,
+        }
+    }
+}
+
+fn column_names(columns: &[BulkColumn]) -> String {
+    columns
+        .iter()
+        .map(|column| column.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Streams the rows into the table with `COPY ... FROM STDIN BINARY`, so that the size of the
+/// snapshot is not limited by the number of query parameters.
+pub async fn copy_in<'a>(
+    transaction: &Transaction<'_>,
+    table: &str,
+    columns: &[BulkColumn],
+    rows: impl IntoIterator<Item = Vec<&'a (dyn ToSql + Sync)>>,
+) -> anyhow::Result<u64> {
+    let sink = transaction
+        .copy_in(&format!(
+            "COPY {table} ({}) FROM STDIN BINARY",
+            column_names(columns)
+        ))
+        .await?;
+    let types: Vec<_> = columns
+        .iter()
+        .map(|column| column.sql_type.clone())
+        .collect();
+    let writer = BinaryCopyInWriter::new(sink, &types);
+    tokio::pin!(writer);
+
+    for row in rows {
+        writer.as_mut().write(&row).await?;
+    }
+    let copied = writer.finish().await?;
+    debug!("Copied {copied} rows into {table}");
+
+    Ok(copied)
+}
+
+/// Creates the temporary table unless an earlier call of the transaction did, and empties it.
+fn temp_table_sql(temp_table: &str, columns: &[BulkColumn]) -> String {
+    let definitions = columns
+        .iter()
+        .map(|column| format!("{} {}", column.name, column.sql_type.name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "CREATE TEMPORARY TABLE IF NOT EXISTS {temp_table} ({definitions}) ON COMMIT DROP;
+        TRUNCATE {temp_table}"
+    )
+}
+
+/// Loads the rows into a temporary table dropped at the end of the transaction,
+/// to be merged into the target table with plain SQL. The table may be loaded again
+/// within the transaction, replacing the rows loaded before.
+pub async fn copy_into_temp_table<'a>(
+    transaction: &Transaction<'_>,
+    temp_table: &str,
+    columns: &[BulkColumn],
+    rows: impl IntoIterator<Item = Vec<&'a (dyn ToSql + Sync)>>,
+) -> anyhow::Result<u64> {
+    transaction
+        .batch_execute(&temp_table_sql(temp_table, columns))
+        .await?;
+
+    copy_in(transaction, temp_table, columns, rows).await
+}
+
+struct MergeStatements {
+    count_matched: String,
+    update: String,
+    insert: String,
+}
+
+fn merge_statements(
+    temp_table: &str,
+    table: &str,
+    key: &[&str],
+    insert_columns: &[&str],
+    update_columns: &[(&str, &str)],
+    changed: &str,
+) -> MergeStatements {
+    let key_condition = key
+        .iter()
+        .map(|column| format!("{table}.{column} = s.{column}"))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let updates = update_columns
+        .iter()
+        .map(|(column, value)| format!("{column} = {value}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let select_columns = insert_columns
+        .iter()
+        .map(|column| format!("s.{column}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let insert_columns = insert_columns.join(", ");
+
+    MergeStatements {
+        count_matched: format!(
+            "SELECT COUNT(*) AS matched FROM {temp_table} s INNER JOIN {table} ON {key_condition}"
+        ),
+        update: format!(
+            "UPDATE {table} SET {updates} FROM {temp_table} s WHERE {key_condition} AND ({changed})"
+        ),
+        insert: format!(
+            "INSERT INTO {table} ({insert_columns})
+                SELECT {select_columns} FROM {temp_table} s
+                WHERE NOT EXISTS (SELECT 1 FROM {table} WHERE {key_condition})"
+        ),
+    }
+}
+
+/// Rows with a record of the same key that were not updated did not change.
+fn merge_stats(matched: u64, updated: u64, inserted: u64) -> TableStats {
+    TableStats {
+        inserted,
+        updated,
+        skipped: matched - updated,
+        deleted: 0,
+    }
+}
+
+/// Inserts the rows of the temporary table that have no record with the same key in the target table
+/// and updates the others where `changed` holds.
+pub async fn merge_from_temp_table(
+    transaction: &Transaction<'_>,
+    temp_table: &str,
+    table: &str,
+    key: &[&str],
+    insert_columns: &[&str],
+    update_columns: &[(&str, &str)],
+    changed: &str,
+) -> anyhow::Result<TableStats> {
+    let statements = merge_statements(
+        temp_table,
+        table,
+        key,
+        insert_columns,
+        update_columns,
+        changed,
+    );
+
+    let matched: i64 = transaction
+        .query_one(&statements.count_matched, &[])
+        .await?
+        .get("matched");
+    let updated = transaction.execute(&statements.update, &[]).await?;
+    let inserted = transaction.execute(&statements.insert, &[]).await?;
+
+    Ok(merge_stats(matched as u64, updated, inserted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        copy_into_temp_table, merge_from_temp_table, merge_statements, merge_stats, temp_table_sql,
+        BulkColumn,
+    };
+    use crate::report::TableStats;
+    use crate::testing::TestDatabase;
+    use chrono::{DateTime, Utc};
+    use rust_decimal::Decimal;
+    use tokio_postgres::types::{ToSql, Type};
+
+    #[test]
+    fn temp_table_can_be_loaded_twice() {
+        let columns = [
+            BulkColumn::new("vote_account", Type::TEXT),
+            BulkColumn::new("epoch", Type::NUMERIC),
+        ];
+        assert_eq!(
+            temp_table_sql("mev_snapshot", &columns),
+            "CREATE TEMPORARY TABLE IF NOT EXISTS mev_snapshot (vote_account text, epoch numeric) ON COMMIT DROP;
+        TRUNCATE mev_snapshot"
+        );
+    }
+
+    #[test]
+    fn merge_of_temp_table() {
+        let statements = merge_statements(
+            "mev_snapshot",
+            "mev",
+            &["vote_account", "epoch"],
+            &["vote_account", "epoch", "mev_commission"],
+            &[("mev_commission", "s.mev_commission")],
+            "mev.mev_commission <> s.mev_commission",
+        );
+        assert_eq!(
+            statements.count_matched,
+            "SELECT COUNT(*) AS matched FROM mev_snapshot s INNER JOIN mev ON mev.vote_account = s.vote_account AND mev.epoch = s.epoch"
+        );
+        assert_eq!(
+            statements.update,
+            "UPDATE mev SET mev_commission = s.mev_commission FROM mev_snapshot s WHERE mev.vote_account = s.vote_account AND mev.epoch = s.epoch AND (mev.mev_commission <> s.mev_commission)"
+        );
+        assert_eq!(
+            statements.insert,
+            "INSERT INTO mev (vote_account, epoch, mev_commission)
+                SELECT s.vote_account, s.epoch, s.mev_commission FROM mev_snapshot s
+                WHERE NOT EXISTS (SELECT 1 FROM mev WHERE mev.vote_account = s.vote_account AND mev.epoch = s.epoch)"
+        );
+
+        // of 10 rows with a stored record 3 changed, 5 rows were new
+        assert_eq!(
+            merge_stats(10, 3, 5),
+            TableStats {
+                inserted: 5,
+                updated: 3,
+                skipped: 7,
+                deleted: 0,
+            }
+        );
+        assert_eq!(
+            merge_stats(0, 0, 8),
+            TableStats {
+                inserted: 8,
+                updated: 0,
+                skipped: 0,
+                deleted: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn merge_of_copied_rows() {
+        let Some(database) = TestDatabase::create("merge_of_copied_rows").await.unwrap() else {
+            return;
+        };
+        let mut psql_client = database.client().await.unwrap();
+        psql_client
+            .batch_execute(
+                "
+            CREATE TABLE bulk_test (
+                vote_account TEXT NOT NULL,
+                epoch NUMERIC NOT NULL,
+                commission INTEGER NOT NULL,
+                name TEXT NULL,
+                updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                PRIMARY KEY(vote_account, epoch)
+            );
+            INSERT INTO bulk_test VALUES
+                ('same', 700, 5, 'Same', '2024-11-20T00:00:00Z'),
+                ('named', 700, 7, NULL, '2024-11-20T00:00:00Z'),
+                ('missing', 700, 10, 'Missing', '2024-11-20T00:00:00Z');
+        ",
+            )
+            .await
+            .unwrap();
+
+        let columns = [
+            BulkColumn::new("vote_account", Type::TEXT),
+            BulkColumn::new("epoch", Type::NUMERIC),
+            BulkColumn::new("commission", Type::INT4),
+            BulkColumn::new("name", Type::TEXT),
+            BulkColumn::new("updated_at", Type::TIMESTAMPTZ),
+        ];
+        let epoch = Decimal::from(700);
+        let updated_at: DateTime<Utc> = "2024-11-21T00:00:00Z".parse().unwrap();
+        let snapshot: Vec<(&str, i32, Option<&str>)> = vec![
+            ("same", 5, Some("Same")),
+            ("named", 7, Some("Named")),
+            ("new", 0, None),
+        ];
+
+        let transaction = psql_client.transaction().await.unwrap();
+        // a second load replaces the rows of the first one
+        for rows in [&snapshot[..1], &snapshot[..]] {
+            copy_into_temp_table(
+                &transaction,
+                "bulk_test_snapshot",
+                &columns,
+                rows.iter().map(|(vote_account, commission, name)| {
+                    let row: Vec<&(dyn ToSql + Sync)> =
+                        vec![vote_account, &epoch, commission, name, &updated_at];
+                    row
+                }),
+            )
+            .await
+            .unwrap();
+        }
+        let stats = merge_from_temp_table(
+            &transaction,
+            "bulk_test_snapshot",
+            "bulk_test",
+            &["vote_account", "epoch"],
+            &["vote_account", "epoch", "commission", "name", "updated_at"],
+            &[
+                ("commission", "s.commission"),
+                ("name", "s.name"),
+                ("updated_at", "s.updated_at"),
+            ],
+            "bulk_test.commission IS DISTINCT FROM s.commission OR bulk_test.name IS DISTINCT FROM s.name",
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        assert_eq!(
+            stats,
+            TableStats {
+                inserted: 1,
+                updated: 1,
+                skipped: 1,
+                deleted: 0,
+            }
+        );
+        let rows: Vec<(String, i32, Option<String>, DateTime<Utc>)> = psql_client
+            .query(
+                "SELECT vote_account, commission, name, updated_at FROM bulk_test ORDER BY vote_account",
+                &[],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+            .collect();
+        let stored_at: DateTime<Utc> = "2024-11-20T00:00:00Z".parse().unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                // not in the snapshot
+                (
+                    "missing".to_string(),
+                    10,
+                    Some("Missing".to_string()),
+                    stored_at
+                ),
+                // a name where there was none is a change
+                (
+                    "named".to_string(),
+                    7,
+                    Some("Named".to_string()),
+                    updated_at
+                ),
+                ("new".to_string(), 0, None, updated_at),
+                // unchanged, not even its time of update
+                ("same".to_string(), 5, Some("Same".to_string()), stored_at),
+            ]
+        );
+    }
+}
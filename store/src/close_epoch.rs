@@ -1,12 +1,13 @@
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use crate::utils::UpdateQueryCombiner;
 use chrono::{DateTime, Utc};
 use collect::validators_performance::{ClusterInflation, ValidatorsPerformanceSnapshot};
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
 use std::collections::{HashMap, HashSet};
 use structopt::StructOpt;
-use tokio_postgres::{types::ToSql, Client};
+use tokio_postgres::{types::ToSql, Client, GenericClient};
 
 #[derive(Debug, StructOpt)]
 pub struct CloseEpochParams {
@@ -16,12 +17,55 @@ pub struct CloseEpochParams {
 
 const DEFAULT_CHUNK_SIZE: usize = 500;
 
+pub const VALIDATORS_CLOSE_HISTORY_TABLE: &str = "validators_close_history";
+
+/// Columns of `validators` overwritten when an epoch is closed, kept in the history to reopen the epoch.
+pub const CLOSED_VALIDATOR_COLUMNS: &[&str] = &[
+    "commission_effective",
+    "commission_max_observed",
+    "commission_min_observed",
+    "credits",
+    "leader_slots",
+    "blocks_produced",
+    "skip_rate",
+    "uptime_pct",
+    "uptime",
+    "downtime",
+    "updated_at",
+    "snapshot_id",
+];
+
+/// Keeps the state of the validator records before the epoch is closed for the first time,
+/// closing it again leaves the history as it is.
+pub async fn record_close_history(
+    psql_client: &impl GenericClient,
+    epoch: u64,
+) -> anyhow::Result<u64> {
+    let columns = CLOSED_VALIDATOR_COLUMNS.join(", ");
+    Ok(psql_client
+        .execute(
+            &format!(
+                "
+        INSERT INTO {VALIDATORS_CLOSE_HISTORY_TABLE} (vote_account, epoch, {columns}, closed_at)
+        SELECT vote_account, epoch, {columns}, NOW()
+        FROM validators
+        WHERE epoch = $1
+        ON CONFLICT (vote_account, epoch) DO NOTHING
+    "
+            ),
+            &[&Decimal::from(epoch)],
+        )
+        .await?)
+}
+
+/// Returns the number of inserted records, 0 when the epoch was already closed.
 pub async fn create_epoch_record(
-    psql_client: &Client,
+    psql_client: &impl GenericClient,
     epoch: u64,
     cluster_inflation: ClusterInflation,
-) -> anyhow::Result<()> {
-    psql_client
+    snapshot_id: i64,
+) -> anyhow::Result<u64> {
+    Ok(psql_client
         .execute(
             "
         WITH
@@ -46,7 +90,8 @@ pub async fn create_epoch_record(
             transaction_count,
             supply,
             inflation,
-            inflation_taper
+            inflation_taper,
+            snapshot_id
         ) SELECT
             $1,
             COALESCE(previous_epoch.end_at, epoch_cluster_info.start_at) start_at,
@@ -54,23 +99,27 @@ pub async fn create_epoch_record(
             transaction_count,
             $2,
             $3,
-            $4
+            $4,
+            $5
         FROM epoch_cluster_info, previous_epoch
+        ON CONFLICT (epoch) DO NOTHING
     ",
             &[
                 &Decimal::from(epoch),
                 &Decimal::from(cluster_inflation.sol_total_supply),
                 &cluster_inflation.inflation,
                 &cluster_inflation.inflation_taper,
+                &snapshot_id,
             ],
         )
-        .await?;
-
-    Ok(())
+        .await?)
 }
 
-pub async fn update_observed_commission(psql_client: &Client, epoch: u64) -> anyhow::Result<()> {
-    psql_client
+pub async fn update_observed_commission(
+    psql_client: &impl GenericClient,
+    epoch: u64,
+) -> anyhow::Result<u64> {
+    Ok(psql_client
             .execute("
                 WITH grouped_commissions AS (
                     WITH
@@ -90,19 +139,21 @@ pub async fn update_observed_commission(psql_client: &Client, epoch: u64) -> any
                     commission_min_observed = LEAST(commission_min, commission_advertised, commission_effective)
                 FROM grouped_commissions
                 WHERE grouped_commissions.vote_account = validators.vote_account AND validators.epoch = $1
+                    AND (commission_max_observed, commission_min_observed) IS DISTINCT FROM (
+                        GREATEST(commission_max, commission_advertised, commission_effective),
+                        LEAST(commission_min, commission_advertised, commission_effective)
+                    )
                 "
 ,
         &[
             &Decimal::from(epoch),
         ],
     )
-    .await?;
-
-    Ok(())
+    .await?)
 }
 
-pub async fn update_uptimes(psql_client: &Client, epoch: u64) -> anyhow::Result<()> {
-    psql_client
+pub async fn update_uptimes(psql_client: &impl GenericClient, epoch: u64) -> anyhow::Result<u64> {
+    Ok(psql_client
             .execute("
                 WITH uptimes AS (
                     WITH
@@ -124,15 +175,14 @@ pub async fn update_uptimes(psql_client: &Client, epoch: u64) -> anyhow::Result<
                 SET uptime_pct = uptimes.uptime_pct, uptime = uptimes.uptime, downtime = uptimes.downtime
                 FROM uptimes
                 WHERE uptimes.vote_account = validators.vote_account AND uptimes.epoch = validators.epoch
+                    AND (validators.uptime_pct, validators.uptime, validators.downtime) IS DISTINCT FROM (uptimes.uptime_pct, uptimes.uptime, uptimes.downtime)
                 "
 ,
         &[
             &Decimal::from(epoch),
         ],
     )
-    .await?;
-
-    Ok(())
+    .await?)
 }
 
 struct ValidatorUpdateRecord {
@@ -144,26 +194,49 @@ struct ValidatorUpdateRecord {
     blocks_produced: Decimal,
     skip_rate: f64,
     updated_at: DateTime<Utc>,
+    snapshot_id: i64,
 }
 
 pub async fn close_epoch(
     epoch_params: CloseEpochParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let (snapshot, raw): (ValidatorsPerformanceSnapshot, _) =
+        read_snapshot_file(epoch_params.snapshot_path)?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = close_epoch_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+pub async fn close_epoch_snapshot(
+    snapshot: ValidatorsPerformanceSnapshot,
+    raw: &RawSnapshot,
+    psql_client: &impl GenericClient,
+) -> anyhow::Result<StoreReport> {
     info!("Finalizing validators snapshot...");
 
-    let snapshot_file = std::fs::File::open(epoch_params.snapshot_path)?;
-    let snapshot: ValidatorsPerformanceSnapshot = serde_yaml::from_reader(snapshot_file)?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(psql_client, &snapshot, raw, &mut report).await?;
+
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse().unwrap();
     let snapshot_epoch: Decimal = snapshot.epoch.into();
     let rewards = snapshot.rewards.unwrap();
 
-    create_epoch_record(
+    let epoch_insertions = create_epoch_record(
         psql_client,
         snapshot.epoch,
         snapshot.cluster_inflation.unwrap(),
+        snapshot_id,
     )
     .await?;
+    let stats = report.table("epochs");
+    stats.inserted = epoch_insertions;
+    stats.skipped = 1 - epoch_insertions;
+
+    report.table(VALIDATORS_CLOSE_HISTORY_TABLE).inserted +=
+        record_close_history(psql_client, snapshot.epoch).await?;
 
     let mut updated_identities: HashSet<_> = Default::default();
 
@@ -183,6 +256,7 @@ pub async fn close_epoch(
             blocks_produced: v.blocks_produced.into(),
             skip_rate: v.skip_rate,
             updated_at: snapshot_created_at,
+            snapshot_id,
         })
         .collect();
 
@@ -195,7 +269,8 @@ pub async fn close_epoch(
             leader_slots = u.leader_slots,
             blocks_produced = u.blocks_produced,
             skip_rate = u.skip_rate,
-            updated_at = u.updated_at
+            updated_at = u.updated_at,
+            snapshot_id = u.snapshot_id
             "
             .to_string(),
             "u(
@@ -206,10 +281,13 @@ pub async fn close_epoch(
                 leader_slots,
                 blocks_produced,
                 skip_rate,
-                updated_at
+                updated_at,
+                snapshot_id
             )"
             .to_string(),
-            "validators.vote_account = u.vote_account AND validators.epoch = u.epoch".to_string(),
+            "validators.vote_account = u.vote_account AND validators.epoch = u.epoch
+            AND validators.updated_at IS DISTINCT FROM u.updated_at"
+                .to_string(),
         );
         for v in chunk {
             let mut params: Vec<&(dyn ToSql + Sync)> = vec![
@@ -221,6 +299,7 @@ pub async fn close_epoch(
                 &v.blocks_produced,
                 &v.skip_rate,
                 &v.updated_at,
+                &v.snapshot_id,
             ];
             query.add(
                 &mut params,
@@ -232,19 +311,26 @@ pub async fn close_epoch(
                     (5, "NUMERIC".into()),                  // blocks_produced
                     (6, "DOUBLE PRECISION".into()),         // skip_rate
                     (7, "TIMESTAMP WITH TIME ZONE".into()), // updated_at
+                    (8, "BIGINT".into()),                   // snapshot_id
                 ]),
             );
             updated_identities.insert(v.vote_account.clone());
         }
-        query.execute(psql_client).await?;
+        let updates = query.execute(psql_client).await?.unwrap_or(0);
+        let stats = report.table("validators");
+        stats.updated += updates;
+        stats.skipped += query.updates - updates;
         info!(
             "Updated previously existing validator records: {}",
             updated_identities.len()
         );
     }
 
-    update_uptimes(psql_client, snapshot.epoch).await?;
-    update_observed_commission(psql_client, snapshot.epoch).await?;
+    let uptime_updates = update_uptimes(psql_client, snapshot.epoch).await?;
+    let commission_updates = update_observed_commission(psql_client, snapshot.epoch).await?;
+    info!(
+        "Updated uptimes of {uptime_updates} and observed commissions of {commission_updates} validator records"
+    );
 
-    Ok(())
+    Ok(report)
 }
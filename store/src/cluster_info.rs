@@ -1,10 +1,11 @@
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Utc};
 use collect::validators_performance::ValidatorsPerformanceSnapshot;
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
 use structopt::StructOpt;
-use tokio_postgres::Client;
+use tokio_postgres::{Client, GenericClient};
 
 #[derive(Debug, StructOpt)]
 pub struct StoreClusterInfoParams {
@@ -15,31 +16,53 @@ pub struct StoreClusterInfoParams {
 pub async fn store_cluster_info(
     params: StoreClusterInfoParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let (snapshot, raw): (ValidatorsPerformanceSnapshot, _) =
+        read_snapshot_file(params.snapshot_path)?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_cluster_info_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+pub async fn store_cluster_info_snapshot(
+    snapshot: ValidatorsPerformanceSnapshot,
+    raw: &RawSnapshot,
+    psql_client: &impl GenericClient,
+) -> anyhow::Result<StoreReport> {
     info!("Storing cluster info...");
 
-    let snapshot_file = std::fs::File::open(params.snapshot_path)?;
-    let snapshot: ValidatorsPerformanceSnapshot = serde_yaml::from_reader(snapshot_file)?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(psql_client, &snapshot, raw, &mut report).await?;
 
     info!("Loaded the cluster info");
 
-    psql_client
+    let insertions = psql_client
         .execute(
             // todo add supply, inflation and active stake
             "
-        INSERT INTO cluster_info (epoch, epoch_slot, transaction_count, created_at)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO cluster_info (epoch, epoch_slot, transaction_count, created_at, snapshot_id)
+        SELECT $1::NUMERIC, $2::NUMERIC, $3::NUMERIC, $4::TIMESTAMP WITH TIME ZONE, $5::BIGINT
+        WHERE NOT EXISTS (
+            SELECT 1 FROM cluster_info WHERE epoch = $1 AND epoch_slot = $2 AND created_at = $4
+        )
     ",
             &[
                 &(Decimal::from(snapshot.epoch)),
                 &(Decimal::from(snapshot.epoch_slot)),
                 &(Decimal::from(snapshot.transaction_count)),
                 &snapshot.created_at.parse::<DateTime<Utc>>().unwrap(),
+                &snapshot_id,
             ],
         )
         .await?;
 
+    let stats = report.table("cluster_info");
+    stats.inserted = insertions;
+    stats.skipped = 1 - insertions;
+
     info!("Stored cluster info");
 
-    Ok(())
+    Ok(report)
 }
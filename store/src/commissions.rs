@@ -1,13 +1,13 @@
-use crate::utils::*;
+use crate::bulk::{copy_into_temp_table, BulkColumn};
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Utc};
 use collect::validators_performance::ValidatorsPerformanceSnapshot;
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
-use std::collections::{HashMap, HashSet};
 use structopt::StructOpt;
-use tokio_postgres::types::ToSql;
-use tokio_postgres::Client;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Transaction};
 
 #[derive(Debug, StructOpt)]
 pub struct StoreCommissionsParams {
@@ -18,70 +18,91 @@ pub struct StoreCommissionsParams {
 pub async fn store_commissions(
     params: StoreCommissionsParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let (snapshot, raw): (ValidatorsPerformanceSnapshot, _) =
+        read_snapshot_file(params.snapshot_path)?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_commissions_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+/// A record is stored only when the commission differs from the latest stored one or the epoch changed.
+pub async fn store_commissions_snapshot(
+    snapshot: ValidatorsPerformanceSnapshot,
+    raw: &RawSnapshot,
+    transaction: &Transaction<'_>,
+) -> anyhow::Result<StoreReport> {
     info!("Storing commission...");
 
-    let snapshot_file = std::fs::File::open(params.snapshot_path)?;
-    let snapshot: ValidatorsPerformanceSnapshot = serde_yaml::from_reader(snapshot_file)?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(transaction, &snapshot, raw, &mut report).await?;
+
     let snapshot_epoch_slot: Decimal = snapshot.epoch_slot.into();
     let snapshot_epoch: Decimal = snapshot.epoch.into();
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse().unwrap();
 
     info!("Loaded the snapshot");
 
-    let mut skipped_vote_accounts: HashSet<String> = Default::default();
-
-    for row in psql_client
-        .query(
-            "
-        SELECT DISTINCT ON (vote_account)
-            vote_account,
-            commission,
-            epoch
-        FROM commissions
-        ORDER BY vote_account, created_at DESC
-    ",
-            &[],
-        )
-        .await?
-    {
-        let vote_account: &str = row.get("vote_account");
-        let commission: i32 = row.get("commission");
-        let epoch: Decimal = row.get("epoch");
-
-        if let Some(validator_snapshot) = snapshot.validators.get(vote_account) {
-            if epoch == snapshot_epoch && commission == validator_snapshot.commission as i32 {
-                skipped_vote_accounts.insert(vote_account.to_string());
-            }
-        }
-    }
-
-    let mut query = InsertQueryCombiner::new(
-        "commissions".to_string(),
-        "vote_account, commission, epoch_slot, epoch, created_at".to_string(),
-    );
-
-    let commissions: HashMap<_, _> = snapshot
+    let commissions: Vec<_> = snapshot
         .validators
         .iter()
-        .map(|(i, v)| (i.clone(), v.commission as i32))
+        .map(|(vote_account, v)| (vote_account, v.commission as i32))
         .collect();
+    let rows = commissions.iter().map(|(vote_account, commission)| {
+        let row: Vec<&(dyn ToSql + Sync)> = vec![
+            vote_account,
+            commission,
+            &snapshot_epoch_slot,
+            &snapshot_epoch,
+            &snapshot_created_at,
+            &snapshot_id,
+        ];
+        row
+    });
+    let loaded = copy_into_temp_table(
+        transaction,
+        "commissions_snapshot",
+        &[
+            BulkColumn::new("vote_account", Type::TEXT),
+            BulkColumn::new("commission", Type::INT4),
+            BulkColumn::new("epoch_slot", Type::NUMERIC),
+            BulkColumn::new("epoch", Type::NUMERIC),
+            BulkColumn::new("created_at", Type::TIMESTAMPTZ),
+            BulkColumn::new("snapshot_id", Type::INT8),
+        ],
+        rows,
+    )
+    .await?;
 
-    for (vote_account, commission) in commissions.iter() {
-        if !skipped_vote_accounts.contains(vote_account) {
-            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+    let insertions = transaction
+        .execute(
+            "
+        WITH latest AS (
+            SELECT DISTINCT ON (vote_account)
                 vote_account,
                 commission,
-                &snapshot_epoch_slot,
-                &snapshot_epoch,
-                &snapshot_created_at,
-            ];
-            query.add(&mut params);
-        }
-    }
-    let insertions = query.execute(psql_client).await?;
+                epoch
+            FROM commissions
+            WHERE vote_account IN (SELECT vote_account FROM commissions_snapshot)
+            ORDER BY vote_account, created_at DESC
+        )
+        INSERT INTO commissions (vote_account, commission, epoch_slot, epoch, created_at, snapshot_id)
+        SELECT s.vote_account, s.commission, s.epoch_slot, s.epoch, s.created_at, s.snapshot_id
+        FROM commissions_snapshot s
+        LEFT JOIN latest ON latest.vote_account = s.vote_account
+        WHERE latest.vote_account IS NULL OR latest.epoch <> s.epoch OR latest.commission <> s.commission
+    ",
+            &[],
+        )
+        .await?;
+
+    let stats = report.table("commissions");
+    stats.inserted = insertions;
+    stats.skipped = loaded - insertions;
 
-    info!("Stored {} commission changes", insertions.unwrap_or(0));
+    info!("Stored {insertions} commission changes");
 
-    Ok(())
+    Ok(report)
 }
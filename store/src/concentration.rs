@@ -0,0 +1,102 @@
+use crate::dto::ConcentrationMetrics;
+use std::collections::HashMap;
+
+/// Entities nobody can be told apart by, e.g. validators with no known location or client.
+const UNKNOWN_ENTITY: &str = "unknown";
+
+/// Concentration of the stake over the entities of one dimension (ASO, client, operator, ...).
+/// The unknown entity stands for many unrelated validators, so it is left out instead of being
+/// counted as one large entity.
+pub fn concentration_metrics(stake_by_entity: &HashMap<String, u64>) -> ConcentrationMetrics {
+    let mut stakes: Vec<u128> = stake_by_entity
+        .iter()
+        .filter(|(entity, _)| !entity.eq_ignore_ascii_case(UNKNOWN_ENTITY))
+        .map(|(_, stake)| *stake as u128)
+        .collect();
+    stakes.sort_unstable_by(|a, b| b.cmp(a));
+    let total_stake: u128 = stakes.iter().sum();
+
+    // the fewest entities together holding more than the given share of the stake
+    let nakamoto_coefficient = |numerator: u128, denominator: u128| -> u64 {
+        if total_stake == 0 {
+            return 0;
+        }
+        let mut cumulative_stake = 0;
+        for (count, stake) in stakes.iter().enumerate() {
+            cumulative_stake += stake;
+            if cumulative_stake * denominator > total_stake * numerator {
+                return count as u64 + 1;
+            }
+        }
+        stakes.len() as u64
+    };
+
+    let (hhi, gini) = if total_stake == 0 {
+        (0.0, 0.0)
+    } else {
+        let hhi = stakes
+            .iter()
+            .map(|stake| (*stake as f64 / total_stake as f64).powi(2))
+            .sum();
+        // over the stakes in ascending order: G = 2 * sum(i * x_i) / (n * sum(x)) - (n + 1) / n
+        let entities = stakes.len() as f64;
+        let weighted_stake: f64 = stakes
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(index, stake)| (index + 1) as f64 * *stake as f64)
+            .sum();
+        let gini =
+            2.0 * weighted_stake / (entities * total_stake as f64) - (entities + 1.0) / entities;
+        (hhi, gini)
+    };
+
+    ConcentrationMetrics {
+        entities: stakes.len() as u64,
+        nakamoto_coefficient_33: nakamoto_coefficient(1, 3),
+        nakamoto_coefficient_66: nakamoto_coefficient(2, 3),
+        hhi,
+        gini,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::concentration_metrics;
+    use std::collections::HashMap;
+
+    fn stakes(stakes: &[(&str, u64)]) -> HashMap<String, u64> {
+        stakes
+            .iter()
+            .map(|(entity, stake)| (entity.to_string(), *stake))
+            .collect()
+    }
+
+    #[test]
+    fn concentration_of_stake() {
+        let equal = concentration_metrics(&stakes(&[("a", 10), ("b", 10), ("c", 10), ("d", 10)]));
+        assert_eq!(equal.entities, 4);
+        assert_eq!(equal.nakamoto_coefficient_33, 2);
+        assert_eq!(equal.nakamoto_coefficient_66, 3);
+        assert!((equal.hhi - 0.25).abs() < 1e-9);
+        assert!(equal.gini.abs() < 1e-9);
+
+        let skewed = concentration_metrics(&stakes(&[
+            ("a", 70),
+            ("b", 10),
+            ("c", 10),
+            ("d", 10),
+            ("Unknown", 1000),
+        ]));
+        assert_eq!(skewed.entities, 4);
+        assert_eq!(skewed.nakamoto_coefficient_33, 1);
+        assert_eq!(skewed.nakamoto_coefficient_66, 1);
+        assert!((skewed.hhi - 0.52).abs() < 1e-9);
+        assert!((skewed.gini - 0.45).abs() < 1e-9);
+
+        let empty = concentration_metrics(&stakes(&[]));
+        assert_eq!(empty.entities, 0);
+        assert_eq!(empty.nakamoto_coefficient_33, 0);
+        assert_eq!(empty.hhi, 0.0);
+    }
+}
@@ -87,6 +87,7 @@ pub struct Validator {
     pub info_url: Option<String>,
     pub info_keybase: Option<String>,
     pub info_icon_url: Option<String>,
+    pub withdraw_authority: Option<String>,
     pub node_ip: Option<String>,
     pub dc_coordinates_lat: Option<f64>,
     pub dc_coordinates_lon: Option<f64>,
@@ -101,6 +102,7 @@ pub struct Validator {
     pub commission_advertised: Option<i32>,
     pub commission_effective: Option<i32>,
     pub version: Option<String>,
+    pub client: Option<String>,
     pub activated_stake: Decimal,
     pub marinade_stake: Decimal,
     pub foundation_stake: Decimal,
@@ -139,6 +141,7 @@ impl Validator {
             info_url: v.info_url.clone(),
             info_keybase: v.info_keybase.clone(),
             info_icon_url: v.info_icon_url.clone(),
+            withdraw_authority: v.withdraw_authority.clone(),
 
             node_ip: v.node_ip.clone(),
             dc_coordinates_lon: coordinates.map(|(_, lon)| lon),
@@ -155,6 +158,7 @@ impl Validator {
             commission_advertised: Some(v.performance.commission as i32),
             commission_effective: None,
             version: v.performance.version.clone(),
+            client: v.performance.node_client().map(|client| client.to_string()),
             activated_stake: v.activated_stake.into(),
             marinade_stake: v.marinade_stake.into(),
             foundation_stake: v.foundation_stake.into(),
@@ -186,6 +190,7 @@ pub struct ValidatorEpochStats {
     pub commission_advertised: Option<u8>,
     pub commission_effective: Option<u8>,
     pub version: Option<String>,
+    pub client: Option<String>,
     pub mev_commission_bps: Option<i32>,
     pub priority_commission_bps: Option<i32>,
     pub dc_asn: Option<i32>,
@@ -248,6 +253,7 @@ pub struct ValidatorRecord {
     pub rugged_commission: bool,
     pub rugged_commission_info: Vec<RugInfo>,
     pub version: Option<String>,
+    pub client: Option<String>,
     pub activated_stake: Decimal,
     pub marinade_stake: Decimal,
     pub foundation_stake: Decimal,
@@ -324,6 +330,7 @@ pub struct ValidatorBlockRewardsRecord {
 pub struct VersionRecord {
     pub epoch: u64,
     pub version: Option<String>,
+    pub client: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -410,10 +417,48 @@ pub struct BlockProductionStats {
     pub avg_skip_rate: f64,
 }
 
+/// Share of the activated stake by the client software the validators run,
+/// validators whose client can't be told are counted under `unknown`.
+#[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
+pub struct ClientDiversityStats {
+    pub epoch: u64,
+    pub total_activated_stake: u64,
+    pub concentration_by_client: HashMap<String, f64>,
+    pub stake_by_client: HashMap<String, u64>,
+    pub validators_by_client: HashMap<String, u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
+pub struct ConcentrationMetrics {
+    /// Entities holding stake, the unknown ones left out
+    pub entities: u64,
+    /// Fewest entities holding more than a third of the stake
+    pub nakamoto_coefficient_33: u64,
+    /// Fewest entities holding more than two thirds of the stake
+    pub nakamoto_coefficient_66: u64,
+    /// Herfindahl-Hirschman index over the stake shares, from 1 / entities (even) up to 1 (one entity)
+    pub hhi: f64,
+    /// Gini coefficient of the stake, from 0 (even) up to 1
+    pub gini: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
+pub struct DecentralizationStats {
+    pub epoch: u64,
+    pub dc_aso: ConcentrationMetrics,
+    pub dc_asn: ConcentrationMetrics,
+    pub dc_city: ConcentrationMetrics,
+    pub dc_country: ConcentrationMetrics,
+    pub client: ConcentrationMetrics,
+    pub operator: ConcentrationMetrics,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
 pub struct ClusterStats {
     pub block_production_stats: Vec<BlockProductionStats>,
     pub dc_concentration_stats: Vec<DCConcentrationStats>,
+    pub client_diversity_stats: Vec<ClientDiversityStats>,
+    pub decentralization_stats: Vec<DecentralizationStats>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
@@ -424,6 +469,25 @@ pub struct ValidatorsAggregated {
     pub avg_apy: Option<f64>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
+pub struct OperatorLinkRecord {
+    /// `withdraw-authority`, `keybase`, `info-url` or `node-subnet`
+    pub evidence: String,
+    /// The withdraw authority, Keybase username, website or subnet the vote accounts share
+    pub value: String,
+    pub vote_accounts: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, utoipa::ToSchema)]
+pub struct OperatorRecord {
+    pub operator_id: String,
+    pub epoch: u64,
+    pub vote_accounts: Vec<String>,
+    pub activated_stake: Decimal,
+    pub marinade_stake: Decimal,
+    pub links: Vec<OperatorLinkRecord>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ValidatorAggregatedFlat {
     pub vote_account: String,
@@ -437,6 +501,7 @@ pub struct ValidatorAggregatedFlat {
     pub dc_aso: String,
     pub marinade_stake: f64,
     pub version: String,
+    pub operator_id: String,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -1,7 +1,27 @@
+pub mod archive;
+pub mod bulk;
+pub mod close_epoch;
+pub mod cluster_info;
+pub mod commissions;
+pub mod concentration;
 pub mod dto;
+pub mod ls_open_epochs;
+pub mod migrations;
+pub mod notifications;
+pub mod operators;
+pub mod recompute;
+pub mod reopen_epoch;
+pub mod report;
 pub mod rewards;
 pub mod scoring;
+pub mod snapshots;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod uptime;
 pub mod utils;
+pub mod validators;
 pub mod validators_block_rewards;
 pub mod validators_events;
 pub mod validators_jito;
+pub mod versions;
+pub mod watermarks;
@@ -1,18 +1,23 @@
-use close_epoch::{close_epoch, CloseEpochParams};
-use cluster_info::{store_cluster_info, StoreClusterInfoParams};
 use collect::validators_jito::JitoAccountType;
-use commissions::{store_commissions, StoreCommissionsParams};
 use env_logger::Env;
-use ls_open_epochs::{list_open_epochs, LsOpenEpochsParams};
 use openssl::ssl::{SslConnector, SslMethod};
 use postgres_openssl::MakeTlsConnector;
+use store::archive::{archive, restore, ArchiveParams, RestoreParams};
+use store::close_epoch::{close_epoch, CloseEpochParams};
+use store::cluster_info::{store_cluster_info, StoreClusterInfoParams};
+use store::commissions::{store_commissions, StoreCommissionsParams};
+use store::ls_open_epochs::{list_open_epochs, LsOpenEpochsParams};
+use store::migrations::{check_schema_version, migrate, MigrateParams};
+use store::operators::{store_operators, OperatorsParams};
+use store::recompute::{recompute, RecomputeParams};
+use store::reopen_epoch::{reopen_epoch, ReopenEpochParams};
+use store::uptime::{store_uptime, StoreUptimeParams};
+use store::validators::{store_validators, StoreValidatorsParams};
 use store::validators_block_rewards::{store_block_rewards, StoreBlockRewardsParams};
 use store::validators_events::{store_events, StoreEventsParams};
+use store::validators_jito::{store_jito, StoreJitoParams};
+use store::versions::{store_versions, StoreVersionsParams};
 use structopt::StructOpt;
-use uptime::{store_uptime, StoreUptimeParams};
-use validators::{store_validators, StoreValidatorsParams};
-use validators_jito::{store_jito, StoreJitoParams};
-use versions::{store_versions, StoreVersionsParams};
 
 #[derive(Debug, StructOpt)]
 pub struct CommonParams {
@@ -21,6 +26,13 @@ pub struct CommonParams {
 
     #[structopt(long = "postgres-ssl-root-cert", env = "PG_SSLROOTCERT")]
     pub postgres_ssl_root_cert: String,
+
+    #[structopt(
+        long = "dry-run",
+        help = "Report how many rows would be inserted, updated or skipped per table without committing.",
+        global = true
+    )]
+    dry_run: bool,
 }
 
 #[derive(Debug, StructOpt)]
@@ -38,26 +50,21 @@ enum StoreCommand {
     Commissions(StoreCommissionsParams),
     Versions(StoreVersionsParams),
     ClusterInfo(StoreClusterInfoParams),
-    Validators(StoreValidatorsParams),
+    Validators(Box<StoreValidatorsParams>),
     ValidatorsBlockRewards(StoreBlockRewardsParams),
     ValidatorsEvents(StoreEventsParams),
     JitoMev(StoreJitoParams),
     JitoPriority(StoreJitoParams),
     CloseEpoch(CloseEpochParams),
+    ReopenEpoch(ReopenEpochParams),
     LsOpenEpochs(LsOpenEpochsParams),
+    Migrate(MigrateParams),
+    Archive(ArchiveParams),
+    Restore(RestoreParams),
+    Recompute(RecomputeParams),
+    Operators(OperatorsParams),
 }
 
-pub mod close_epoch;
-pub mod cluster_info;
-pub mod commissions;
-pub mod dto;
-pub mod ls_open_epochs;
-pub mod uptime;
-pub mod utils;
-pub mod validators;
-pub mod validators_jito;
-pub mod versions;
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
@@ -77,25 +84,33 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    let dry_run = params.common.dry_run;
+    if !matches!(params.command, StoreCommand::Migrate(_)) {
+        check_schema_version(&psql_client).await?;
+    }
+
     match params.command {
-        StoreCommand::Uptime(store_params) => store_uptime(store_params, &mut psql_client).await,
+        StoreCommand::Uptime(store_params) => {
+            store_uptime(store_params, &mut psql_client, dry_run).await
+        }
         StoreCommand::Commissions(store_params) => {
-            store_commissions(store_params, &mut psql_client).await
+            store_commissions(store_params, &mut psql_client, dry_run).await
         }
         StoreCommand::Versions(store_params) => {
-            store_versions(store_params, &mut psql_client).await
+            store_versions(store_params, &mut psql_client, dry_run).await
         }
         StoreCommand::ClusterInfo(store_params) => {
-            store_cluster_info(store_params, &mut psql_client).await
+            store_cluster_info(store_params, &mut psql_client, dry_run).await
         }
         StoreCommand::Validators(store_params) => {
-            store_validators(store_params, &mut psql_client).await
+            store_validators(*store_params, &mut psql_client, dry_run).await
         }
         StoreCommand::JitoMev(store_params) => {
             store_jito(
                 store_params,
                 &mut psql_client,
                 JitoAccountType::MevTipDistribution,
+                dry_run,
             )
             .await
         }
@@ -104,16 +119,37 @@ async fn main() -> anyhow::Result<()> {
                 store_params,
                 &mut psql_client,
                 JitoAccountType::PriorityFeeDistribution,
+                dry_run,
             )
             .await
         }
         StoreCommand::ValidatorsBlockRewards(store_params) => {
-            store_block_rewards(store_params, &mut psql_client).await
+            store_block_rewards(store_params, &mut psql_client, dry_run).await
         }
         StoreCommand::ValidatorsEvents(store_params) => {
-            store_events(store_params, &mut psql_client).await
+            store_events(store_params, &mut psql_client, dry_run).await
+        }
+        StoreCommand::CloseEpoch(close_params) => {
+            close_epoch(close_params, &mut psql_client, dry_run).await
+        }
+        StoreCommand::ReopenEpoch(reopen_params) => {
+            reopen_epoch(reopen_params, &mut psql_client, dry_run).await
         }
-        StoreCommand::CloseEpoch(close_params) => close_epoch(close_params, &mut psql_client).await,
         StoreCommand::LsOpenEpochs(_ls_params) => list_open_epochs(&psql_client).await,
+        StoreCommand::Migrate(migrate_params) => {
+            migrate(migrate_params, &mut psql_client, dry_run).await
+        }
+        StoreCommand::Archive(archive_params) => {
+            archive(archive_params, &mut psql_client, dry_run).await
+        }
+        StoreCommand::Restore(restore_params) => {
+            restore(restore_params, &mut psql_client, dry_run).await
+        }
+        StoreCommand::Recompute(recompute_params) => {
+            recompute(recompute_params, &mut psql_client, dry_run).await
+        }
+        StoreCommand::Operators(operators_params) => {
+            store_operators(operators_params, &mut psql_client, dry_run).await
+        }
     }
 }
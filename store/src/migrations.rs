@@ -0,0 +1,271 @@
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use structopt::StructOpt;
+use tokio_postgres::Client;
+
+pub struct Migration {
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    /// Migrations are named `<version>-<description>.sql`.
+    pub fn version(&self) -> i32 {
+        self.name
+            .split('-')
+            .next()
+            .and_then(|version| version.parse().ok())
+            .expect("Migration name starts with its version")
+    }
+
+    pub fn checksum(&self) -> String {
+        Sha256::digest(self.sql.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+macro_rules! migration {
+    ($name:literal) => {
+        Migration {
+            name: $name,
+            sql: include_str!(concat!("../../migrations/", $name)),
+        }
+    };
+}
+
+/// All files of the `migrations` directory, in the order they are applied.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!("0000-init.sql"),
+    migration!("0002-scores.sql"),
+    migration!("0003-scores-with-ranks.sql"),
+    migration!("0004-vote-account-data.sql"),
+    migration!("0005-scores-component-values.sql"),
+    migration!("0006-scores-mnde-votes.sql"),
+    migration!("0007-scores-msol-votes.sql"),
+    migration!("0008-foundation-stake.sql"),
+    migration!("0009-self-stake.sql"),
+    migration!("0010-native-stake.sql"),
+    migration!("0011-indexes.sql"),
+    migration!("0012-institutional-stake.sql"),
+    migration!("0013-migrations-fix.sql"),
+    migration!("0014-jito-priority-fee.sql"),
+    migration!("0015-validator-info-icon-url.sql"),
+    migration!("0016-block-rewards.sql"),
+    migration!("0018-events.sql"),
+    migration!("0019-scheduler-runs.sql"),
+    migration!("0020-snapshots.sql"),
+    migration!("0021-validators-close-history.sql"),
+    migration!("0022-node-clients.sql"),
+    migration!("0023-operators.sql"),
+    migration!("0024-cache-watermarks.sql"),
+];
+
+pub fn latest_schema_version() -> i32 {
+    MIGRATIONS
+        .last()
+        .map(Migration::version)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, StructOpt)]
+pub struct MigrateParams {
+    #[structopt(
+        long = "baseline",
+        help = "Record the migrations up to this version as applied without running them, for databases migrated by hand."
+    )]
+    baseline: Option<i32>,
+}
+
+struct AppliedMigration {
+    name: String,
+    checksum: String,
+}
+
+async fn load_applied_migrations(
+    psql_client: &Client,
+) -> anyhow::Result<HashMap<i32, AppliedMigration>> {
+    Ok(psql_client
+        .query("SELECT version, name, checksum FROM schema_migrations", &[])
+        .await?
+        .iter()
+        .map(|row| {
+            (
+                row.get("version"),
+                AppliedMigration {
+                    name: row.get("name"),
+                    checksum: row.get("checksum"),
+                },
+            )
+        })
+        .collect())
+}
+
+/// Fails when an applied migration was changed or is unknown to this binary.
+fn verify_applied_migrations(applied: &HashMap<i32, AppliedMigration>) -> anyhow::Result<()> {
+    for (version, applied_migration) in applied {
+        let Some(migration) = MIGRATIONS.iter().find(|m| m.version() == *version) else {
+            anyhow::bail!(
+                "Migration {} was applied to the database but is unknown to this binary, the binary is outdated",
+                applied_migration.name
+            );
+        };
+        if migration.checksum() != applied_migration.checksum {
+            anyhow::bail!(
+                "Migration {} was changed after it had been applied (checksum {} in the database, {} embedded)",
+                migration.name,
+                applied_migration.checksum,
+                migration.checksum()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// With `dry_run` only the pending migrations are listed.
+pub async fn migrate(
+    params: MigrateParams,
+    psql_client: &mut Client,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    psql_client
+        .batch_execute(
+            "
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMP WITH TIME ZONE NOT NULL,
+            baseline BOOLEAN NOT NULL DEFAULT FALSE,
+
+            PRIMARY KEY(version)
+        )
+    ",
+        )
+        .await?;
+
+    let applied = load_applied_migrations(psql_client).await?;
+    verify_applied_migrations(&applied)?;
+
+    let pending: Vec<_> = MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains_key(&migration.version()))
+        .collect();
+    if pending.is_empty() {
+        info!(
+            "Schema is up to date at version {}",
+            latest_schema_version()
+        );
+        return Ok(());
+    }
+    if let Some(applied_version) = applied.keys().max() {
+        if let Some(skipped) = pending.iter().find(|m| m.version() < *applied_version) {
+            warn!(
+                "Migration {} is older than the applied version {applied_version}",
+                skipped.name
+            );
+        }
+    }
+
+    if dry_run {
+        for migration in pending {
+            let baseline = params
+                .baseline
+                .is_some_and(|baseline| migration.version() <= baseline);
+            info!(
+                "Pending migration: {}{}",
+                migration.name,
+                if baseline { " (baseline)" } else { "" }
+            );
+        }
+        return Ok(());
+    }
+
+    let transaction = psql_client.transaction().await?;
+    for migration in pending {
+        let baseline = params
+            .baseline
+            .is_some_and(|baseline| migration.version() <= baseline);
+        if baseline {
+            info!("Recording migration {} as baseline", migration.name);
+        } else {
+            info!("Applying migration {}", migration.name);
+            transaction.batch_execute(migration.sql).await?;
+        }
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version, name, checksum, applied_at, baseline) VALUES ($1, $2, $3, NOW(), $4)",
+                &[
+                    &migration.version(),
+                    &migration.name,
+                    &migration.checksum(),
+                    &baseline,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+    info!("Schema migrated to version {}", latest_schema_version());
+
+    Ok(())
+}
+
+/// Refuses to work with a database whose schema does not match the embedded migrations.
+pub async fn check_schema_version(psql_client: &Client) -> anyhow::Result<()> {
+    let has_migrations_table: bool = psql_client
+        .query_one(
+            "SELECT to_regclass('schema_migrations') IS NOT NULL AS exists",
+            &[],
+        )
+        .await?
+        .get("exists");
+    if !has_migrations_table {
+        anyhow::bail!(
+            "Database schema is not versioned, run `store migrate` (with `--baseline` for a database migrated by hand)"
+        );
+    }
+
+    let applied = load_applied_migrations(psql_client).await?;
+    verify_applied_migrations(&applied)?;
+
+    let pending: Vec<_> = MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains_key(&migration.version()))
+        .map(|migration| migration.name)
+        .collect();
+    if !pending.is_empty() {
+        anyhow::bail!(
+            "Database schema is behind version {}, run `store migrate` to apply: {}",
+            latest_schema_version(),
+            pending.join(", ")
+        );
+    }
+
+    info!("Database schema is at version {}", latest_schema_version());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MIGRATIONS;
+
+    #[test]
+    fn all_migration_files_are_embedded_in_order() {
+        let mut files: Vec<_> =
+            std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../migrations"))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .filter(|name| name.ends_with(".sql"))
+                .collect();
+        files.sort();
+
+        let embedded: Vec<_> = MIGRATIONS.iter().map(|migration| migration.name).collect();
+        assert_eq!(embedded, files);
+        assert!(MIGRATIONS
+            .windows(2)
+            .all(|pair| pair[0].version() < pair[1].version()));
+    }
+}
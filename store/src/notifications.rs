@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use tokio_postgres::GenericClient;
+
+/// Channel the tables changed by store commands and scoring uploads are announced on.
+pub const STORE_CHANNEL: &str = "store_changes";
+
+/// Payload of a notification on [`STORE_CHANNEL`], one per changed table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreNotification {
+    /// The changed table
+    pub dataset: String,
+    /// The last epoch the changed records belong to, if the command stored any epoch in particular
+    pub epoch: Option<u64>,
+}
+
+/// Announces the change of the dataset, delivered to the listeners once the transaction commits.
+pub async fn notify(
+    psql_client: &impl GenericClient,
+    dataset: &str,
+    epoch: Option<u64>,
+) -> anyhow::Result<()> {
+    let payload = serde_json::to_string(&StoreNotification {
+        dataset: dataset.to_string(),
+        epoch,
+    })?;
+    psql_client
+        .execute("SELECT pg_notify($1, $2)", &[&STORE_CHANNEL, &payload])
+        .await?;
+
+    Ok(())
+}
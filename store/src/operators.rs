@@ -0,0 +1,629 @@
+use crate::report::{finish_transaction, StoreReport};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rust_decimal::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::net::IpAddr;
+use structopt::StructOpt;
+use tokio_postgres::{Client, Transaction};
+
+/// More validators in one subnet are rather neighbours at a hosting provider than a single operator.
+const MAX_SUBNET_VALIDATORS: usize = 4;
+
+/// What linked vote accounts have in common.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OperatorEvidence {
+    /// The same authorized withdrawer of the vote accounts, which also withdraws their self stake
+    WithdrawAuthority,
+    /// The same Keybase username in the validator info
+    Keybase,
+    /// The same website in the validator info
+    InfoUrl,
+    /// Nodes in the same /24 (IPv4) or /64 (IPv6) subnet
+    NodeSubnet,
+}
+
+impl fmt::Display for OperatorEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OperatorEvidence::WithdrawAuthority => write!(f, "withdraw-authority"),
+            OperatorEvidence::Keybase => write!(f, "keybase"),
+            OperatorEvidence::InfoUrl => write!(f, "info-url"),
+            OperatorEvidence::NodeSubnet => write!(f, "node-subnet"),
+        }
+    }
+}
+
+impl OperatorEvidence {
+    /// Only keys and accounts controlled by the operator merge vote accounts into one operator.
+    /// A website or subnet may be shared by unrelated validators, e.g. of a hosting provider or
+    /// a staking service, so these links are only reported.
+    pub fn is_strong(self) -> bool {
+        match self {
+            OperatorEvidence::WithdrawAuthority | OperatorEvidence::Keybase => true,
+            OperatorEvidence::InfoUrl | OperatorEvidence::NodeSubnet => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OperatorValidator {
+    pub vote_account: String,
+    pub withdraw_authority: Option<String>,
+    pub info_keybase: Option<String>,
+    pub info_url: Option<String>,
+    pub node_ip: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorLink {
+    pub evidence: OperatorEvidence,
+    pub value: String,
+    pub vote_accounts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorCluster {
+    /// Carried over from the previous epoch's operator sharing the most vote accounts,
+    /// otherwise the first of the vote accounts in lexicographic order not taken by another operator
+    pub operator_id: String,
+    pub vote_accounts: Vec<String>,
+    /// The links merging its vote accounts and the weak links to vote accounts of other operators
+    pub links: Vec<OperatorLink>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct OperatorsParams {
+    #[structopt(
+        long = "epoch",
+        help = "Epoch to cluster the operators of, the last stored epoch by default."
+    )]
+    epoch: Option<u64>,
+}
+
+fn normalize_keybase(keybase: &str) -> Option<String> {
+    let keybase = keybase.trim().trim_start_matches('@').to_lowercase();
+    (!keybase.is_empty()).then_some(keybase)
+}
+
+fn normalize_url(url: &str) -> Option<String> {
+    let url = url.trim().to_lowercase();
+    let url = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(&url);
+    let url = url
+        .strip_prefix("www.")
+        .unwrap_or(url)
+        .trim_end_matches('/');
+    (!url.is_empty()).then(|| url.to_string())
+}
+
+fn node_subnet(node_ip: &str) -> Option<String> {
+    match node_ip.parse().ok()? {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            Some(format!("{a}.{b}.{c}.0/24"))
+        }
+        IpAddr::V6(ip) => {
+            let [a, b, c, d, ..] = ip.segments();
+            Some(format!("{a:x}:{b:x}:{c:x}:{d:x}::/64"))
+        }
+    }
+}
+
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// Keeps the operator ids stable over the epochs: the operators take over the ids of the previous
+/// epoch's operators they share the most vote accounts with, each id going to one operator only.
+fn assign_operator_ids(
+    clusters: &[Vec<String>],
+    previous_operators: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut candidates: Vec<(usize, &str, usize)> = Vec::new();
+    for (cluster_index, vote_accounts) in clusters.iter().enumerate() {
+        let mut overlaps: HashMap<&str, usize> = HashMap::new();
+        for vote_account in vote_accounts {
+            if let Some(previous_id) = previous_operators.get(vote_account) {
+                *overlaps.entry(previous_id.as_str()).or_default() += 1;
+            }
+        }
+        candidates.extend(
+            overlaps
+                .into_iter()
+                .map(|(previous_id, overlap)| (overlap, previous_id, cluster_index)),
+        );
+    }
+    // the largest overlaps first, ties broken by the ids to stay deterministic
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)).then(a.2.cmp(&b.2)));
+
+    let mut operator_ids: Vec<Option<String>> = vec![None; clusters.len()];
+    let mut taken_ids: HashSet<String> = HashSet::new();
+    for (_, previous_id, cluster_index) in candidates {
+        if operator_ids[cluster_index].is_none() && !taken_ids.contains(previous_id) {
+            taken_ids.insert(previous_id.to_string());
+            operator_ids[cluster_index] = Some(previous_id.to_string());
+        }
+    }
+
+    operator_ids
+        .into_iter()
+        .zip(clusters)
+        .map(|(operator_id, vote_accounts)| {
+            operator_id.unwrap_or_else(|| {
+                let operator_id = vote_accounts
+                    .iter()
+                    .find(|vote_account| !taken_ids.contains(*vote_account))
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        (2..)
+                            .map(|suffix| format!("{}-{suffix}", vote_accounts[0]))
+                            .find(|operator_id| !taken_ids.contains(operator_id))
+                            .unwrap()
+                    });
+                taken_ids.insert(operator_id.clone());
+                operator_id
+            })
+        })
+        .collect()
+}
+
+/// Vote accounts sharing strong evidence end up in one operator, also transitively.
+/// Every vote account belongs to exactly one operator, most of them to an operator of their own.
+/// Weak evidence is listed with every operator it links to others.
+pub fn cluster_operators(
+    validators: &[OperatorValidator],
+    previous_operators: &HashMap<String, String>,
+) -> Vec<OperatorCluster> {
+    let mut evidence_groups: BTreeMap<(OperatorEvidence, String), Vec<usize>> = BTreeMap::new();
+    for (index, validator) in validators.iter().enumerate() {
+        let evidence = [
+            (
+                OperatorEvidence::WithdrawAuthority,
+                validator.withdraw_authority.clone(),
+            ),
+            (
+                OperatorEvidence::Keybase,
+                validator
+                    .info_keybase
+                    .as_deref()
+                    .and_then(normalize_keybase),
+            ),
+            (
+                OperatorEvidence::InfoUrl,
+                validator.info_url.as_deref().and_then(normalize_url),
+            ),
+            (
+                OperatorEvidence::NodeSubnet,
+                validator.node_ip.as_deref().and_then(node_subnet),
+            ),
+        ];
+        for (kind, value) in evidence {
+            if let Some(value) = value {
+                evidence_groups
+                    .entry((kind, value))
+                    .or_default()
+                    .push(index);
+            }
+        }
+    }
+    evidence_groups.retain(|(kind, _), members| {
+        members.len() > 1
+            && (*kind != OperatorEvidence::NodeSubnet || members.len() <= MAX_SUBNET_VALIDATORS)
+    });
+
+    let mut parents: Vec<usize> = (0..validators.len()).collect();
+    for ((evidence, _), members) in &evidence_groups {
+        if !evidence.is_strong() {
+            continue;
+        }
+        for member in &members[1..] {
+            let root = find_root(&mut parents, members[0]);
+            let member_root = find_root(&mut parents, *member);
+            if root != member_root {
+                parents[member_root] = root;
+            }
+        }
+    }
+
+    let mut members_by_root: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for index in 0..validators.len() {
+        let root = find_root(&mut parents, index);
+        members_by_root.entry(root).or_default().push(index);
+    }
+    let mut links_by_root: HashMap<usize, Vec<OperatorLink>> = HashMap::new();
+    for ((evidence, value), members) in evidence_groups {
+        let mut roots: Vec<_> = members
+            .iter()
+            .map(|member| find_root(&mut parents, *member))
+            .collect();
+        roots.sort_unstable();
+        roots.dedup();
+        // weak evidence within a single operator adds nothing
+        if !evidence.is_strong() && roots.len() == 1 {
+            continue;
+        }
+        let mut vote_accounts: Vec<_> = members
+            .iter()
+            .map(|member| validators[*member].vote_account.clone())
+            .collect();
+        vote_accounts.sort();
+        for root in roots {
+            links_by_root.entry(root).or_default().push(OperatorLink {
+                evidence,
+                value: value.clone(),
+                vote_accounts: vote_accounts.clone(),
+            });
+        }
+    }
+
+    let clusters: Vec<(usize, Vec<String>)> = members_by_root
+        .into_iter()
+        .map(|(root, members)| {
+            let mut vote_accounts: Vec<_> = members
+                .iter()
+                .map(|member| validators[*member].vote_account.clone())
+                .collect();
+            vote_accounts.sort();
+            (root, vote_accounts)
+        })
+        .collect();
+    let operator_ids = assign_operator_ids(
+        &clusters
+            .iter()
+            .map(|(_, vote_accounts)| vote_accounts.clone())
+            .collect::<Vec<_>>(),
+        previous_operators,
+    );
+
+    let mut operators: Vec<_> = clusters
+        .into_iter()
+        .zip(operator_ids)
+        .map(|((root, vote_accounts), operator_id)| OperatorCluster {
+            operator_id,
+            vote_accounts,
+            links: links_by_root.remove(&root).unwrap_or_default(),
+        })
+        .collect();
+    operators.sort_by(|a, b| a.operator_id.cmp(&b.operator_id));
+
+    operators
+}
+
+/// Replaces the operators of the epoch with ones clustered from its stored validator records.
+pub async fn update_operators(
+    transaction: &Transaction<'_>,
+    epoch: u64,
+    report: &mut StoreReport,
+) -> anyhow::Result<()> {
+    let epoch = Decimal::from(epoch);
+    let validators: Vec<_> = transaction
+        .query(
+            "SELECT vote_account, withdraw_authority, info_keybase, info_url, node_ip
+            FROM validators WHERE epoch = $1",
+            &[&epoch],
+        )
+        .await?
+        .iter()
+        .map(|row| OperatorValidator {
+            vote_account: row.get("vote_account"),
+            withdraw_authority: row.get("withdraw_authority"),
+            info_keybase: row.get("info_keybase"),
+            info_url: row.get("info_url"),
+            node_ip: row.get("node_ip"),
+        })
+        .collect();
+    if validators.iter().all(|v| v.withdraw_authority.is_none()) {
+        warn!("No withdraw authorities are stored for epoch {epoch}, the operators are linked by the validator info and subnets only");
+    }
+
+    let replaced_operators: HashMap<String, String> = transaction
+        .query(
+            "DELETE FROM validator_operators WHERE epoch = $1 RETURNING vote_account, operator_id",
+            &[&epoch],
+        )
+        .await?
+        .iter()
+        .map(|row| (row.get("vote_account"), row.get("operator_id")))
+        .collect();
+    transaction
+        .execute("DELETE FROM operator_links WHERE epoch = $1", &[&epoch])
+        .await?;
+    let previous_operators: HashMap<String, String> = transaction
+        .query(
+            "SELECT vote_account, operator_id FROM validator_operators
+            WHERE epoch = (SELECT MAX(epoch) FROM validator_operators WHERE epoch < $1)",
+            &[&epoch],
+        )
+        .await?
+        .iter()
+        .map(|row| (row.get("vote_account"), row.get("operator_id")))
+        .collect();
+
+    let operators = cluster_operators(&validators, &previous_operators);
+    let updated_at: DateTime<Utc> = Utc::now();
+
+    let mut vote_accounts: Vec<&str> = Vec::new();
+    let mut operator_ids: Vec<&str> = Vec::new();
+    let mut link_operator_ids: Vec<&str> = Vec::new();
+    let mut link_evidence: Vec<String> = Vec::new();
+    let mut link_values: Vec<&str> = Vec::new();
+    let mut link_vote_accounts: Vec<String> = Vec::new();
+    for operator in &operators {
+        for vote_account in &operator.vote_accounts {
+            let stats = report.table("validator_operators");
+            match replaced_operators.get(vote_account) {
+                None => stats.inserted += 1,
+                Some(previous) if *previous == operator.operator_id => stats.skipped += 1,
+                Some(_) => stats.updated += 1,
+            }
+            vote_accounts.push(vote_account);
+            operator_ids.push(&operator.operator_id);
+        }
+        for link in &operator.links {
+            link_operator_ids.push(&operator.operator_id);
+            link_evidence.push(link.evidence.to_string());
+            link_values.push(&link.value);
+            link_vote_accounts.push(link.vote_accounts.join(","));
+        }
+    }
+
+    transaction
+        .execute(
+            "INSERT INTO validator_operators (epoch, vote_account, operator_id, updated_at)
+            SELECT $1, vote_account, operator_id, $4 FROM UNNEST($2::TEXT[], $3::TEXT[]) AS o(vote_account, operator_id)",
+            &[&epoch, &vote_accounts, &operator_ids, &updated_at],
+        )
+        .await?;
+    // a vote account list per link, as arrays of arrays cannot be unnested row by row
+    report.table("operator_links").inserted += transaction
+        .execute(
+            "INSERT INTO operator_links (epoch, operator_id, evidence, value, vote_accounts)
+            SELECT $1, operator_id, evidence, value, string_to_array(vote_accounts, ',')
+            FROM UNNEST($2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[]) AS l(operator_id, evidence, value, vote_accounts)",
+            &[
+                &epoch,
+                &link_operator_ids,
+                &link_evidence,
+                &link_values,
+                &link_vote_accounts,
+            ],
+        )
+        .await?;
+
+    info!(
+        "Clustered {} vote accounts of epoch {epoch} into {} operators, {} of them running more than one",
+        validators.len(),
+        operators.len(),
+        operators
+            .iter()
+            .filter(|operator| operator.vote_accounts.len() > 1)
+            .count()
+    );
+
+    Ok(())
+}
+
+pub async fn store_operators(
+    params: OperatorsParams,
+    psql_client: &mut Client,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let transaction = psql_client.transaction().await?;
+
+    let epoch = match params.epoch {
+        Some(epoch) => epoch,
+        None => transaction
+            .query_one("SELECT MAX(epoch) AS last_epoch FROM validators", &[])
+            .await?
+            .get::<_, Option<Decimal>>("last_epoch")
+            .ok_or_else(|| anyhow::anyhow!("No validators are stored"))?
+            .try_into()?,
+    };
+
+    let mut report = StoreReport::default();
+    report.epoch(epoch);
+    update_operators(&transaction, epoch, &mut report).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cluster_operators, OperatorEvidence, OperatorValidator};
+    use std::collections::HashMap;
+
+    fn validator(vote_account: &str) -> OperatorValidator {
+        OperatorValidator {
+            vote_account: vote_account.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn members(
+        validators: &[OperatorValidator],
+        previous: &[(&str, &str)],
+    ) -> Vec<(String, Vec<String>)> {
+        let previous_operators: HashMap<_, _> = previous
+            .iter()
+            .map(|(vote_account, operator_id)| (vote_account.to_string(), operator_id.to_string()))
+            .collect();
+        cluster_operators(validators, &previous_operators)
+            .into_iter()
+            .map(|operator| (operator.operator_id, operator.vote_accounts))
+            .collect()
+    }
+
+    fn owned(members: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        members
+            .iter()
+            .map(|(operator_id, vote_accounts)| {
+                (
+                    operator_id.to_string(),
+                    vote_accounts.iter().map(|v| v.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn operators_are_linked_transitively() {
+        let validators = vec![
+            OperatorValidator {
+                withdraw_authority: Some("w1".into()),
+                ..validator("c")
+            },
+            OperatorValidator {
+                withdraw_authority: Some("w1".into()),
+                info_keybase: Some("@Operator".into()),
+                ..validator("b")
+            },
+            OperatorValidator {
+                info_keybase: Some("operator".into()),
+                ..validator("a")
+            },
+            OperatorValidator {
+                info_url: Some("https://www.other.com/".into()),
+                node_ip: Some("10.0.0.1".into()),
+                ..validator("d")
+            },
+            OperatorValidator {
+                info_url: Some("http://other.com".into()),
+                ..validator("e")
+            },
+            validator("f"),
+        ];
+
+        let operators = cluster_operators(&validators, &HashMap::new());
+        let members: Vec<_> = operators
+            .iter()
+            .map(|operator| (operator.operator_id.as_str(), operator.vote_accounts.len()))
+            .collect();
+        assert_eq!(members, vec![("a", 3), ("d", 1), ("e", 1), ("f", 1)]);
+
+        let evidence: Vec<_> = operators[0]
+            .links
+            .iter()
+            .map(|link| (link.evidence, link.value.as_str()))
+            .collect();
+        assert_eq!(
+            evidence,
+            vec![
+                (OperatorEvidence::WithdrawAuthority, "w1"),
+                (OperatorEvidence::Keybase, "operator"),
+            ]
+        );
+        // the shared website is reported with both operators without merging them
+        for operator in &operators[1..3] {
+            assert_eq!(operator.links.len(), 1);
+            assert_eq!(operator.links[0].evidence, OperatorEvidence::InfoUrl);
+            assert_eq!(operator.links[0].value, "other.com");
+            assert_eq!(operator.links[0].vote_accounts, vec!["d", "e"]);
+        }
+        assert!(operators[3].links.is_empty());
+    }
+
+    #[test]
+    fn weak_evidence_is_not_linked_transitively() {
+        // a and c share nothing, b shares a website with a and a subnet with c
+        let validators = vec![
+            OperatorValidator {
+                info_url: Some("staking.example".into()),
+                ..validator("a")
+            },
+            OperatorValidator {
+                info_url: Some("staking.example".into()),
+                node_ip: Some("10.0.0.1".into()),
+                ..validator("b")
+            },
+            OperatorValidator {
+                node_ip: Some("10.0.0.2".into()),
+                ..validator("c")
+            },
+        ];
+
+        let operators = cluster_operators(&validators, &HashMap::new());
+        assert_eq!(operators.len(), 3);
+        let evidence: Vec<_> = operators[1]
+            .links
+            .iter()
+            .map(|link| link.evidence)
+            .collect();
+        assert_eq!(
+            evidence,
+            vec![OperatorEvidence::InfoUrl, OperatorEvidence::NodeSubnet]
+        );
+    }
+
+    #[test]
+    fn crowded_subnets_do_not_link_operators() {
+        let validators: Vec<_> = (1..=5)
+            .map(|host| OperatorValidator {
+                node_ip: Some(format!("10.0.0.{host}")),
+                ..validator(&host.to_string())
+            })
+            .collect();
+        let operators = cluster_operators(&validators, &HashMap::new());
+        assert_eq!(operators.len(), 5);
+        assert!(operators.iter().all(|operator| operator.links.is_empty()));
+
+        let operators = cluster_operators(&validators[..2], &HashMap::new());
+        assert_eq!(operators.len(), 2);
+        assert!(operators
+            .iter()
+            .all(|operator| operator.links[0].evidence == OperatorEvidence::NodeSubnet));
+    }
+
+    #[test]
+    fn operator_ids_are_kept_over_epochs() {
+        let authority = |vote_account: &str, authority: &str| OperatorValidator {
+            withdraw_authority: Some(authority.into()),
+            ..validator(vote_account)
+        };
+
+        // a vote account sorting first joins, the operator keeps its id
+        let validators = vec![
+            authority("a", "w1"),
+            authority("m", "w1"),
+            authority("n", "w1"),
+        ];
+        assert_eq!(
+            members(&validators, &[("m", "m"), ("n", "m")]),
+            owned(&[("m", &["a", "m", "n"])])
+        );
+
+        // a split operator keeps its id with the larger part, the rest gets another one
+        let validators = vec![
+            authority("a", "w1"),
+            authority("m", "w2"),
+            authority("n", "w2"),
+        ];
+        assert_eq!(
+            members(&validators, &[("a", "a"), ("m", "a"), ("n", "a")]),
+            owned(&[("a", &["m", "n"]), ("a-2", &["a"])])
+        );
+
+        // merged operators take the id of the larger one
+        let validators = vec![
+            authority("x", "w1"),
+            authority("y", "w1"),
+            authority("z", "w1"),
+        ];
+        assert_eq!(
+            members(&validators, &[("x", "x"), ("y", "y"), ("z", "y")]),
+            owned(&[("y", &["x", "y", "z"])])
+        );
+
+        // new operators are named after their first vote account
+        assert_eq!(
+            members(&[validator("q")], &[("x", "x")]),
+            owned(&[("q", &["q"])])
+        );
+    }
+}
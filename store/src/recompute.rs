@@ -0,0 +1,121 @@
+use crate::close_epoch::{update_observed_commission, update_uptimes};
+use crate::report::{finish_transaction, StoreReport};
+use log::{info, warn};
+use rust_decimal::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use structopt::StructOpt;
+use tokio_postgres::Client;
+
+/// Columns of `validators` derived from the other stored records when an epoch is closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derivation {
+    /// `commission_max_observed` and `commission_min_observed` from the commissions of the epoch
+    Commissions,
+    /// `uptime_pct`, `uptime` and `downtime` from the uptimes and the duration of the epoch
+    Uptimes,
+}
+
+impl Derivation {
+    pub const ALL: [Derivation; 2] = [Derivation::Commissions, Derivation::Uptimes];
+}
+
+impl FromStr for Derivation {
+    type Err = anyhow::Error;
+
+    fn from_str(derivation: &str) -> Result<Self, Self::Err> {
+        match derivation {
+            "commissions" => Ok(Derivation::Commissions),
+            "uptimes" => Ok(Derivation::Uptimes),
+            _ => {
+                anyhow::bail!("Unknown derivation '{derivation}', expected commissions or uptimes")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Derivation::Commissions => write!(f, "commissions"),
+            Derivation::Uptimes => write!(f, "uptimes"),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RecomputeParams {
+    #[structopt(long = "from-epoch", help = "First epoch to recompute.")]
+    from_epoch: u64,
+
+    #[structopt(long = "to-epoch", help = "Last epoch to recompute (inclusive).")]
+    to_epoch: u64,
+
+    #[structopt(
+        long = "what",
+        help = "Comma-separated derivations to recompute (commissions, uptimes), all by default.",
+        use_delimiter = true
+    )]
+    what: Vec<Derivation>,
+}
+
+/// Re-runs the derivations of `close_epoch` over the stored records in a single transaction.
+/// Only rows whose derived values change are updated, so a rerun changes nothing.
+pub async fn recompute(
+    params: RecomputeParams,
+    psql_client: &mut Client,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if params.from_epoch > params.to_epoch {
+        anyhow::bail!(
+            "--from-epoch {} is after --to-epoch {}",
+            params.from_epoch,
+            params.to_epoch
+        );
+    }
+    let derivations = if params.what.is_empty() {
+        Derivation::ALL.to_vec()
+    } else {
+        params.what
+    };
+
+    let transaction = psql_client.transaction().await?;
+
+    let closed_epochs: HashSet<u64> = transaction
+        .query(
+            "SELECT epoch FROM epochs WHERE epoch BETWEEN $1 AND $2",
+            &[
+                &Decimal::from(params.from_epoch),
+                &Decimal::from(params.to_epoch),
+            ],
+        )
+        .await?
+        .iter()
+        .map(|row| row.get::<_, Decimal>("epoch").try_into())
+        .collect::<Result<_, _>>()?;
+
+    let mut report = StoreReport::default();
+    for epoch in params.from_epoch..=params.to_epoch {
+        for derivation in &derivations {
+            let changed = match derivation {
+                Derivation::Commissions => update_observed_commission(&transaction, epoch).await?,
+                Derivation::Uptimes => {
+                    // the uptime is relative to the duration of the epoch, known once it is closed
+                    if !closed_epochs.contains(&epoch) {
+                        warn!("Epoch {epoch} is not closed, its uptimes cannot be recomputed");
+                        continue;
+                    }
+                    update_uptimes(&transaction, epoch).await?
+                }
+            };
+            info!("Epoch {epoch}: {derivation} changed {changed} validator records");
+            report.table("validators").updated += changed;
+            if changed > 0 {
+                report.epoch(epoch);
+            }
+        }
+    }
+
+    finish_transaction(transaction, &report, dry_run).await
+}
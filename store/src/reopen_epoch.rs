@@ -0,0 +1,94 @@
+use crate::close_epoch::{CLOSED_VALIDATOR_COLUMNS, VALIDATORS_CLOSE_HISTORY_TABLE};
+use crate::report::{finish_transaction, StoreReport};
+use log::{info, warn};
+use rust_decimal::prelude::*;
+use structopt::StructOpt;
+use tokio_postgres::Client;
+
+#[derive(Debug, StructOpt)]
+pub struct ReopenEpochParams {
+    #[structopt(help = "Closed epoch to reopen.")]
+    epoch: u64,
+}
+
+/// Undoes `close-epoch`: the validator records get their state from before the close back
+/// and the epoch record is removed, so the epoch is listed by `ls-open-epochs` and can be closed again.
+pub async fn reopen_epoch(
+    params: ReopenEpochParams,
+    psql_client: &mut Client,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let epoch = Decimal::from(params.epoch);
+    let transaction = psql_client.transaction().await?;
+
+    let closed: bool = transaction
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM epochs WHERE epoch = $1) AS closed",
+            &[&epoch],
+        )
+        .await?
+        .get("closed");
+    if !closed {
+        anyhow::bail!("Epoch {epoch} is not closed");
+    }
+    // the start of the next epoch record is taken from the end of this one
+    let last_closed_epoch: Decimal = transaction
+        .query_one("SELECT MAX(epoch) AS last_epoch FROM epochs", &[])
+        .await?
+        .get("last_epoch");
+    if last_closed_epoch > epoch {
+        anyhow::bail!(
+            "Epoch {last_closed_epoch} was closed after epoch {epoch}, reopen the later epochs first"
+        );
+    }
+
+    let mut report = StoreReport::default();
+    report.epoch(params.epoch);
+
+    let history_records: i64 = transaction
+        .query_one(
+            &format!(
+                "SELECT COUNT(*) AS records FROM {VALIDATORS_CLOSE_HISTORY_TABLE} WHERE epoch = $1"
+            ),
+            &[&epoch],
+        )
+        .await?
+        .get("records");
+    if history_records == 0 {
+        warn!("No state from before the close of epoch {epoch} is kept, the validator records are left as they are");
+    }
+
+    let updates = CLOSED_VALIDATOR_COLUMNS
+        .iter()
+        .map(|column| format!("{column} = h.{column}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let restored = transaction
+        .execute(
+            &format!(
+                "
+        UPDATE validators
+        SET {updates}
+        FROM {VALIDATORS_CLOSE_HISTORY_TABLE} h
+        WHERE validators.vote_account = h.vote_account AND validators.epoch = h.epoch AND h.epoch = $1
+    "
+            ),
+            &[&epoch],
+        )
+        .await?;
+    report.table("validators").updated += restored;
+    info!("Restored {restored} validator records of epoch {epoch} to their state before the close");
+
+    transaction
+        .execute(
+            &format!("DELETE FROM {VALIDATORS_CLOSE_HISTORY_TABLE} WHERE epoch = $1"),
+            &[&epoch],
+        )
+        .await?;
+    transaction
+        .execute("DELETE FROM epochs WHERE epoch = $1", &[&epoch])
+        .await?;
+    info!("Removed the record of epoch {epoch}, it is open again");
+
+    finish_transaction(transaction, &report, dry_run).await
+}
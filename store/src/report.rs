@@ -0,0 +1,80 @@
+use crate::notifications::notify;
+use log::info;
+use std::collections::BTreeMap;
+use tokio_postgres::Transaction;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TableStats {
+    pub inserted: u64,
+    pub updated: u64,
+    /// Snapshot records already stored with the same values.
+    pub skipped: u64,
+    pub deleted: u64,
+}
+
+/// Rows written by a store command, per table.
+#[derive(Debug, Default)]
+pub struct StoreReport {
+    tables: BTreeMap<String, TableStats>,
+    epoch: Option<u64>,
+}
+
+impl StoreReport {
+    pub fn table(&mut self, table: &str) -> &mut TableStats {
+        self.tables.entry(table.to_string()).or_default()
+    }
+
+    pub fn tables(&self) -> &BTreeMap<String, TableStats> {
+        &self.tables
+    }
+
+    /// Records that the command stored data of the epoch, the last such epoch is announced with the changes.
+    pub fn epoch(&mut self, epoch: u64) {
+        self.epoch = self.epoch.max(Some(epoch));
+    }
+
+    pub fn merge(&mut self, other: StoreReport) {
+        for (table, stats) in other.tables {
+            let merged = self.table(&table);
+            merged.inserted += stats.inserted;
+            merged.updated += stats.updated;
+            merged.skipped += stats.skipped;
+            merged.deleted += stats.deleted;
+        }
+        if let Some(epoch) = other.epoch {
+            self.epoch(epoch);
+        }
+    }
+
+    pub fn log(&self) {
+        for (table, stats) in &self.tables {
+            info!(
+                "{table}: {} inserted, {} updated, {} skipped, {} deleted",
+                stats.inserted, stats.updated, stats.skipped, stats.deleted
+            );
+        }
+    }
+}
+
+/// Commits the transaction of a store command, or rolls it back with `--dry-run`.
+/// Every table with inserted, updated or deleted rows is announced on the store channel with the commit.
+pub async fn finish_transaction(
+    transaction: Transaction<'_>,
+    report: &StoreReport,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    report.log();
+    if dry_run {
+        transaction.rollback().await?;
+        info!("Dry run, nothing was committed");
+    } else {
+        for (table, stats) in &report.tables {
+            if stats.inserted + stats.updated + stats.deleted > 0 {
+                notify(&transaction, table, report.epoch).await?;
+            }
+        }
+        transaction.commit().await?;
+    }
+
+    Ok(())
+}
@@ -203,8 +203,10 @@ pub async fn load_global_unstake_hint_records(
         .collect())
 }
 
+/// Scores of all scoring runs, only of the runs after `after_scoring_run_id` when given.
 pub async fn load_all_scores(
     psql_client: &Client,
+    after_scoring_run_id: Option<i64>,
 ) -> anyhow::Result<HashMap<Decimal, Vec<ValidatorScoreRecord>>> {
     log::info!("Querying all scores...");
     let rows = psql_client
@@ -229,8 +231,9 @@ pub async fn load_all_scores(
                 scoring_runs.created_at AS created_at
             FROM scores
             LEFT JOIN scoring_runs ON scoring_runs.scoring_run_id = scores.scoring_run_id
+            WHERE $1::BIGINT IS NULL OR scores.scoring_run_id > $1
             ORDER BY rank",
-            &[],
+            &[&after_scoring_run_id],
         )
         .await?;
 
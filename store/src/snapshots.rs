@@ -0,0 +1,268 @@
+use crate::report::StoreReport;
+use chrono::{DateTime, Utc};
+use collect::snapshot::{parse_snapshot, write_snapshot, SnapshotFormat};
+use collect::validators::Snapshot as ValidatorsSnapshot;
+use collect::validators_block_rewards::ValidatorsBlockRewardsSnapshot;
+use collect::validators_events::ValidatorsEventsSnapshot;
+use collect::validators_jito::{JitoAccountType, JitoSnapshot};
+use collect::validators_performance::ValidatorsPerformanceSnapshot;
+use log::info;
+use rust_decimal::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio_postgres::{Client, GenericClient};
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Where a snapshot came from, recorded in the `snapshots` table.
+pub trait SnapshotProvenance {
+    fn snapshot_type(&self) -> &'static str;
+    fn epoch(&self) -> u64;
+    fn loaded_at_slot_index(&self) -> Option<u64>;
+    fn created_at(&self) -> &str;
+}
+
+impl SnapshotProvenance for ValidatorsSnapshot {
+    fn snapshot_type(&self) -> &'static str {
+        "validators"
+    }
+    fn epoch(&self) -> u64 {
+        self.epoch
+    }
+    fn loaded_at_slot_index(&self) -> Option<u64> {
+        None
+    }
+    fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+impl SnapshotProvenance for ValidatorsPerformanceSnapshot {
+    fn snapshot_type(&self) -> &'static str {
+        "validators-performance"
+    }
+    fn epoch(&self) -> u64 {
+        self.epoch
+    }
+    fn loaded_at_slot_index(&self) -> Option<u64> {
+        Some(self.epoch_slot)
+    }
+    fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+impl SnapshotProvenance for JitoSnapshot {
+    fn snapshot_type(&self) -> &'static str {
+        match self.account_type {
+            JitoAccountType::MevTipDistribution => "jito-mev",
+            JitoAccountType::PriorityFeeDistribution => "jito-priority",
+        }
+    }
+    fn epoch(&self) -> u64 {
+        self.epoch
+    }
+    fn loaded_at_slot_index(&self) -> Option<u64> {
+        Some(self.loaded_at_slot_index)
+    }
+    fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+impl SnapshotProvenance for ValidatorsBlockRewardsSnapshot {
+    fn snapshot_type(&self) -> &'static str {
+        "validators-block-rewards"
+    }
+    fn epoch(&self) -> u64 {
+        self.epoch
+    }
+    fn loaded_at_slot_index(&self) -> Option<u64> {
+        Some(self.loaded_at_slot_index)
+    }
+    fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+/// Events span many epochs, the snapshot is recorded at the epoch it was loaded at.
+impl SnapshotProvenance for ValidatorsEventsSnapshot {
+    fn snapshot_type(&self) -> &'static str {
+        "validators-events"
+    }
+    fn epoch(&self) -> u64 {
+        self.loaded_at_epoch
+    }
+    fn loaded_at_slot_index(&self) -> Option<u64> {
+        Some(self.loaded_at_slot_index)
+    }
+    fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+/// The snapshot as it was read from its file, or serialized when it was collected in-process.
+pub struct RawSnapshot {
+    pub format: SnapshotFormat,
+    pub data: Vec<u8>,
+}
+
+impl RawSnapshot {
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let data = std::fs::read(path)?;
+        Ok(Self {
+            format: SnapshotFormat::detect(&data),
+            data,
+        })
+    }
+
+    pub fn from_snapshot<T: Serialize>(snapshot: &T) -> anyhow::Result<Self> {
+        let mut data = vec![];
+        write_snapshot(&mut data, SnapshotFormat::JsonZst, snapshot)?;
+        Ok(Self {
+            format: SnapshotFormat::JsonZst,
+            data,
+        })
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        parse_snapshot(&self.data)
+    }
+
+    pub fn content_hash(&self) -> String {
+        Sha256::digest(&self.data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Bodies are stored compressed, `json.zst` snapshots as they are.
+    fn body(&self) -> anyhow::Result<Vec<u8>> {
+        match self.format {
+            SnapshotFormat::JsonZst => Ok(self.data.clone()),
+            _ => Ok(zstd::encode_all(&self.data[..], ZSTD_LEVEL)?),
+        }
+    }
+}
+
+/// Reads a snapshot file, keeping its original content for the archive.
+pub fn read_snapshot_file<T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<(T, RawSnapshot)> {
+    let raw = RawSnapshot::read(path)?;
+    Ok((raw.parse()?, raw))
+}
+
+/// Records the snapshot in the `snapshots` table unless the same content was stored before.
+/// Returns the id the stored rows are tagged with.
+pub async fn archive_snapshot(
+    psql_client: &impl GenericClient,
+    snapshot: &impl SnapshotProvenance,
+    raw: &RawSnapshot,
+    report: &mut StoreReport,
+) -> anyhow::Result<i64> {
+    let snapshot_type = snapshot.snapshot_type();
+    let content_hash = raw.content_hash();
+    report.epoch(snapshot.epoch());
+
+    if let Some(row) = psql_client
+        .query_opt(
+            "SELECT id FROM snapshots WHERE snapshot_type = $1 AND content_hash = $2",
+            &[&snapshot_type, &content_hash],
+        )
+        .await?
+    {
+        let id: i64 = row.get("id");
+        info!("The {snapshot_type} snapshot was already archived as {id}");
+        report.table("snapshots").skipped += 1;
+        return Ok(id);
+    }
+
+    let created_at: DateTime<Utc> = snapshot.created_at().parse()?;
+    let id: i64 = psql_client
+        .query_one(
+            "
+        INSERT INTO snapshots (
+            snapshot_type,
+            format,
+            epoch,
+            loaded_at_slot_index,
+            created_at,
+            content_hash,
+            body,
+            stored_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+        RETURNING id
+    ",
+            &[
+                &snapshot_type,
+                &raw.format.to_string(),
+                &Decimal::from(snapshot.epoch()),
+                &snapshot.loaded_at_slot_index().map(Decimal::from),
+                &created_at,
+                &content_hash,
+                &raw.body()?,
+            ],
+        )
+        .await?
+        .get("id");
+    info!("Archived the {snapshot_type} snapshot as {id}");
+    report.table("snapshots").inserted += 1;
+
+    Ok(id)
+}
+
+pub struct ArchivedSnapshot {
+    pub id: i64,
+    pub snapshot_type: String,
+    pub format: SnapshotFormat,
+    pub epoch: u64,
+    pub created_at: DateTime<Utc>,
+    pub content_hash: String,
+    /// The original content of the snapshot.
+    pub data: Vec<u8>,
+}
+
+impl ArchivedSnapshot {
+    pub fn content_type(&self) -> &'static str {
+        match self.format {
+            SnapshotFormat::Yaml => "application/yaml",
+            SnapshotFormat::Json => "application/json",
+            SnapshotFormat::JsonZst => "application/zstd",
+        }
+    }
+}
+
+pub async fn load_archived_snapshot(
+    psql_client: &Client,
+    id: i64,
+) -> anyhow::Result<Option<ArchivedSnapshot>> {
+    let Some(row) = psql_client
+        .query_opt(
+            "SELECT id, snapshot_type, format, epoch, created_at, content_hash, body FROM snapshots WHERE id = $1",
+            &[&id],
+        )
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let format: SnapshotFormat = row.get::<_, &str>("format").parse()?;
+    let body: Vec<u8> = row.get("body");
+    let data = match format {
+        SnapshotFormat::JsonZst => body,
+        _ => zstd::decode_all(&body[..])?,
+    };
+
+    Ok(Some(ArchivedSnapshot {
+        id: row.get("id"),
+        snapshot_type: row.get("snapshot_type"),
+        format,
+        epoch: row.get::<_, Decimal>("epoch").try_into()?,
+        created_at: row.get("created_at"),
+        content_hash: row.get("content_hash"),
+        data,
+    }))
+}
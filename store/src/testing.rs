@@ -0,0 +1,81 @@
+use crate::migrations::{migrate, MigrateParams};
+use log::error;
+use std::str::FromStr;
+use structopt::StructOpt;
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, Client, Config, NoTls, Notification};
+
+/// Names the PostgreSQL server the database tests run against, they are skipped without it.
+pub const TEST_POSTGRES_URL: &str = "TEST_POSTGRES_URL";
+
+/// A migrated database of its own for one test, created anew on every run.
+pub struct TestDatabase {
+    config: Config,
+}
+
+impl TestDatabase {
+    /// Returns `None` when no test server is configured.
+    pub async fn create(name: &str) -> anyhow::Result<Option<Self>> {
+        let Ok(url) = std::env::var(TEST_POSTGRES_URL) else {
+            eprintln!("{TEST_POSTGRES_URL} is not set, skipping the database test {name}");
+            return Ok(None);
+        };
+        let mut config = Config::from_str(&url)?;
+        let server = connect(&config).await?;
+        let dbname = format!("test_{name}");
+        // each statement on its own, databases are not created within a transaction block
+        server
+            .batch_execute(&format!("DROP DATABASE IF EXISTS {dbname} WITH (FORCE)"))
+            .await?;
+        server
+            .batch_execute(&format!("CREATE DATABASE {dbname}"))
+            .await?;
+
+        config.dbname(&dbname);
+        let database = Self { config };
+        migrate(
+            MigrateParams::from_iter(["migrate"]),
+            &mut database.client().await?,
+            false,
+        )
+        .await?;
+
+        Ok(Some(database))
+    }
+
+    pub async fn client(&self) -> anyhow::Result<Client> {
+        connect(&self.config).await
+    }
+
+    /// Listens on the channel with a connection of its own, the returned client keeps it open.
+    pub async fn listen(
+        &self,
+        channel: &str,
+    ) -> anyhow::Result<(Client, mpsc::UnboundedReceiver<Notification>)> {
+        let (client, mut connection) = self.config.connect(NoTls).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(message) = std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+                if let Ok(AsyncMessage::Notification(notification)) = message {
+                    if sender.send(notification).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        client.batch_execute(&format!("LISTEN {channel}")).await?;
+
+        Ok((client, receiver))
+    }
+}
+
+async fn connect(config: &Config) -> anyhow::Result<Client> {
+    let (client, connection) = config.connect(NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            error!("Test database connection error: {err}");
+        }
+    });
+
+    Ok(client)
+}
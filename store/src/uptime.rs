@@ -1,13 +1,14 @@
-use crate::utils::*;
+use crate::bulk::{copy_in, copy_into_temp_table, BulkColumn};
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Duration, Utc};
 use collect::validators_performance::ValidatorsPerformanceSnapshot;
 use log::{debug, info, warn};
 use rust_decimal::prelude::*;
-use serde_yaml;
 use std::collections::{HashMap, HashSet};
 use structopt::StructOpt;
-use tokio_postgres::types::ToSql;
-use tokio_postgres::Client;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Transaction};
 
 #[derive(Debug, StructOpt)]
 pub struct StoreUptimeParams {
@@ -29,11 +30,26 @@ fn status_from_delinquency(delinquent: bool) -> &'static str {
 pub async fn store_uptime(
     params: StoreUptimeParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let (snapshot, raw): (ValidatorsPerformanceSnapshot, _) =
+        read_snapshot_file(params.snapshot_path)?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_uptime_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+pub async fn store_uptime_snapshot(
+    snapshot: ValidatorsPerformanceSnapshot,
+    raw: &RawSnapshot,
+    transaction: &Transaction<'_>,
+) -> anyhow::Result<StoreReport> {
     info!("Storing uptime...");
 
-    let snapshot_file = std::fs::File::open(params.snapshot_path)?;
-    let snapshot: ValidatorsPerformanceSnapshot = serde_yaml::from_reader(snapshot_file)?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(transaction, &snapshot, raw, &mut report).await?;
+
     let mut validators_with_extended_status: HashSet<String> = HashSet::new();
     let snapshot_epoch: Decimal = snapshot.epoch.into();
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse().unwrap();
@@ -45,7 +61,7 @@ pub async fn store_uptime(
 
     info!("Loaded the snapshot");
 
-    for row in psql_client
+    for row in transaction
         .query(
             "
         SELECT DISTINCT ON (vote_account)
@@ -75,11 +91,17 @@ pub async fn store_uptime(
         if let Some(validator_snapshot) = snapshot.validators.get(vote_account) {
             let status_from_snapshot = status_from_delinquency(validator_snapshot.delinquent);
             if latest_end_extension_at > snapshot_created_at {
-                if status == status_from_snapshot && epoch == snapshot_epoch {
+                let status_end_at = if status == status_from_snapshot && epoch == snapshot_epoch {
                     validators_with_extended_status.insert(vote_account.to_string());
-                    records_extensions.insert(id, default_status_end_at);
+                    default_status_end_at
+                } else {
+                    snapshot_created_at
+                };
+                // the record was already extended by this snapshot
+                if end_at == status_end_at {
+                    report.table("uptimes").skipped += 1;
                 } else {
-                    records_extensions.insert(id, snapshot_created_at);
+                    records_extensions.insert(id, status_end_at);
                 }
             }
         }
@@ -87,55 +109,69 @@ pub async fn store_uptime(
         debug!("found uptime record: {id} {vote_account} {status} {start_at} {end_at}");
     }
 
-    let mut query = UpdateQueryCombiner::new(
-        "uptimes".to_string(),
-        "end_at = u.end_at".to_string(),
-        "u(id, end_at)".to_string(),
-        "uptimes.id = u.id".to_string(),
-    );
-
-    for (id, status_end_at) in records_extensions.iter() {
-        let mut params: Vec<&(dyn ToSql + Sync)> = vec![id, status_end_at];
-        query.add(
-            &mut params,
-            HashMap::from_iter([(0, "BIGINT".into()), (1, "TIMESTAMP WITH TIME ZONE".into())]),
-        );
-    }
-    query.execute(psql_client).await?;
+    copy_into_temp_table(
+        transaction,
+        "uptime_extensions",
+        &[
+            BulkColumn::new("id", Type::INT8),
+            BulkColumn::new("end_at", Type::TIMESTAMPTZ),
+        ],
+        records_extensions.iter().map(|(id, status_end_at)| {
+            let row: Vec<&(dyn ToSql + Sync)> = vec![id, status_end_at];
+            row
+        }),
+    )
+    .await?;
+    report.table("uptimes").updated += transaction
+        .execute(
+            "UPDATE uptimes SET end_at = s.end_at, snapshot_id = $1 FROM uptime_extensions s WHERE uptimes.id = s.id",
+            &[&snapshot_id],
+        )
+        .await?;
     info!("Extended previous {} uptimes", records_extensions.len());
 
-    let mut query = InsertQueryCombiner::new(
-        "uptimes".to_string(),
-        "vote_account, status, epoch, start_at, end_at".to_string(),
-    );
-
+    let mut rows: Vec<Vec<&(dyn ToSql + Sync)>> = vec![];
     for (vote_account, snapshot) in snapshot.validators.iter() {
         if !validators_with_extended_status.contains(vote_account) {
             if snapshot.delinquent {
-                let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+                rows.push(vec![
                     vote_account,
                     &DOWN,
                     &snapshot_epoch,
                     &snapshot_created_at,
                     &default_status_end_at,
-                ];
-                query.add(&mut params);
+                    &snapshot_id,
+                ]);
                 warn!("Validator {vote_account} is now DOWN");
             } else {
-                let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+                rows.push(vec![
                     vote_account,
                     &UP,
                     &snapshot_epoch,
                     &snapshot_created_at,
                     &default_status_end_at,
-                ];
-                query.add(&mut params);
+                    &snapshot_id,
+                ]);
                 info!("Validator {vote_account} is now UP");
             }
         }
     }
-    let insertions = query.execute(psql_client).await?;
-    info!("Stored {} changed uptimes", insertions.unwrap_or(0));
+    let insertions = copy_in(
+        transaction,
+        "uptimes",
+        &[
+            BulkColumn::new("vote_account", Type::TEXT),
+            BulkColumn::new("status", Type::TEXT),
+            BulkColumn::new("epoch", Type::NUMERIC),
+            BulkColumn::new("start_at", Type::TIMESTAMPTZ),
+            BulkColumn::new("end_at", Type::TIMESTAMPTZ),
+            BulkColumn::new("snapshot_id", Type::INT8),
+        ],
+        rows,
+    )
+    .await?;
+    report.table("uptimes").inserted += insertions;
+    info!("Stored {insertions} changed uptimes");
 
-    Ok(())
+    Ok(report)
 }
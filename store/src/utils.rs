@@ -1,10 +1,14 @@
+use crate::concentration::concentration_metrics;
 use crate::dto::{
-    BlockProductionStats, ClusterStats, CommissionRecord, DCConcentrationStats, IncidentRecord,
-    RugInfo, RuggerRecord, ScoringRunRecord, UptimeRecord, ValidatorAggregatedFlat,
+    BlockProductionStats, ClientDiversityStats, ClusterStats, CommissionRecord,
+    DCConcentrationStats, DecentralizationStats, IncidentRecord, OperatorLinkRecord,
+    OperatorRecord, RugInfo, RuggerRecord, ScoringRunRecord, UptimeRecord, ValidatorAggregatedFlat,
     ValidatorEpochStats, ValidatorRecord, ValidatorScoreRecord, ValidatorScoreV2Record,
     ValidatorScoringCsvRow, ValidatorWarning, ValidatorsAggregated, VersionRecord,
 };
+use crate::notifications::notify;
 use chrono::{DateTime, Utc};
+use collect::validators_performance::NodeClient;
 use google_cloud_bigquery::client::{Client as BqClient, ClientConfig as BqClientConfig};
 use google_cloud_bigquery::http::job::query::QueryRequest;
 use google_cloud_bigquery::query::row::Row;
@@ -16,7 +20,7 @@ use std::{
 };
 use tokio::join;
 use tokio::sync::Semaphore;
-use tokio_postgres::{types::ToSql, Client};
+use tokio_postgres::{types::ToSql, Client, GenericClient};
 
 /// Default number of recent epochs the API loads/serves (validators, uptimes, events, ...).
 pub const DEFAULT_CACHE_EPOCHS: u64 = 80;
@@ -67,7 +71,7 @@ impl<'a> InsertQueryCombiner<'a> {
         self.insertions += 1;
     }
 
-    pub async fn execute(&self, client: &mut Client) -> anyhow::Result<Option<u64>> {
+    pub async fn execute(&self, client: &impl GenericClient) -> anyhow::Result<Option<u64>> {
         if self.insertions == 0 {
             return Ok(None);
         }
@@ -122,7 +126,7 @@ impl<'a> UpdateQueryCombiner<'a> {
         self.updates += 1;
     }
 
-    pub async fn execute(&mut self, client: &mut Client) -> anyhow::Result<Option<u64>> {
+    pub async fn execute(&mut self, client: &impl GenericClient) -> anyhow::Result<Option<u64>> {
         if self.updates == 0 {
             return Ok(None);
         }
@@ -301,18 +305,22 @@ pub async fn load_uptimes(
     Ok(records)
 }
 
+/// Versions of the last epochs, only those with ids after `after_id` up to `last_id` when given.
 pub async fn load_versions(
     psql_client: &Client,
     epochs: u64,
+    after_id: Option<i64>,
+    last_id: Option<i64>,
 ) -> anyhow::Result<HashMap<String, Vec<VersionRecord>>> {
     let rows = psql_client
         .query(
             "
             WITH cluster AS (SELECT MAX(epoch) AS last_epoch FROM cluster_info)
             SELECT
-                vote_account, version, epoch, created_at
-            FROM versions, cluster WHERE epoch > cluster.last_epoch - $1::NUMERIC",
-            &[&Decimal::from(epochs)],
+                vote_account, version, client, epoch, created_at
+            FROM versions, cluster WHERE epoch > cluster.last_epoch - $1::NUMERIC
+                AND ($2::BIGINT IS NULL OR id > $2) AND ($3::BIGINT IS NULL OR id <= $3)",
+            &[&Decimal::from(epochs), &after_id, &last_id],
         )
         .await?;
 
@@ -325,6 +333,7 @@ pub async fn load_versions(
         versions.push(VersionRecord {
             epoch: row.get::<_, Decimal>("epoch").try_into()?,
             version: row.get("version"),
+            client: row.get("client"),
             created_at: row.get("created_at"),
         })
     }
@@ -420,9 +429,13 @@ pub async fn load_ruggers(psql_client: &Client) -> anyhow::Result<HashMap<String
     Ok(records)
 }
 
+/// Commissions of the last epochs, only the changes with ids after `after_id` up to `last_id` when given.
+/// The effective commissions of the validator records are loaded only with all changes.
 pub async fn load_commissions(
     psql_client: &Client,
     epochs: u64,
+    after_id: Option<i64>,
+    last_id: Option<i64>,
 ) -> anyhow::Result<HashMap<String, Vec<CommissionRecord>>> {
     let rows = psql_client
         .query(
@@ -436,6 +449,7 @@ pub async fn load_commissions(
             LEFT JOIN epochs ON commissions.epoch = epochs.epoch
             CROSS JOIN cluster
             WHERE commissions.epoch > cluster.last_epoch - $1::NUMERIC
+                AND ($2::BIGINT IS NULL OR commissions.id > $2) AND ($3::BIGINT IS NULL OR commissions.id <= $3)
             UNION
             SELECT
                 vote_account, commission_effective, validators.epoch, epochs.start_at AS epoch_start,
@@ -444,8 +458,9 @@ pub async fn load_commissions(
             LEFT JOIN epochs ON validators.epoch = epochs.epoch
             CROSS JOIN cluster
             WHERE validators.epoch > cluster.last_epoch - $1::NUMERIC AND commission_effective IS NOT NULL
+                AND $2::BIGINT IS NULL
             ",
-            &[&Decimal::from(epochs)],
+            &[&Decimal::from(epochs), &after_id, &last_id],
         )
         .await?;
 
@@ -813,6 +828,7 @@ pub async fn load_validators(
                 commission_advertised,
                 commission_effective,
                 version,
+                client,
                 mev.mev_commission AS mev_commission_bps,
                 jpf.validator_commission AS priority_commission_bps,
                 activated_stake,
@@ -936,6 +952,7 @@ pub async fn load_validators(
                     commission_effective: row.get::<_, Option<i32>>("commission_effective"),
                     commission_aggregated: None,
                     version: row.get("version"),
+                    client: row.get("client"),
                     activated_stake: row.get::<_, Decimal>("activated_stake"),
                     marinade_stake: row.get::<_, Decimal>("marinade_stake"),
                     foundation_stake: row.get::<_, Decimal>("foundation_stake"),
@@ -1001,6 +1018,7 @@ pub async fn load_validators(
                     .get::<_, Option<i32>>("commission_effective")
                     .map(|n| n.try_into().unwrap()),
                 version: row.get("version"),
+                client: row.get("client"),
                 mev_commission_bps: row.get::<_, Option<i32>>("mev_commission_bps"),
                 priority_commission_bps: row.get::<_, Option<i32>>("priority_commission_bps"),
                 dc_asn: row.get::<_, Option<i32>>("dc_asn"),
@@ -1434,10 +1452,164 @@ pub async fn load_block_production_stats(
     Ok(stats)
 }
 
+pub async fn load_client_diversity_stats(
+    psql_client: &Client,
+    epochs: u64,
+) -> anyhow::Result<Vec<ClientDiversityStats>> {
+    let last_epoch = match get_last_epoch(psql_client).await? {
+        Some(last_epoch) => last_epoch,
+        _ => return Ok(Default::default()),
+    };
+    let first_epoch = last_epoch - epochs.min(last_epoch) + 1;
+
+    let rows = psql_client
+        .query(
+            "SELECT
+                epoch,
+                COALESCE(client, 'unknown') client,
+                COALESCE(SUM(activated_stake), 0) activated_stake,
+                COUNT(*) validators
+            FROM validators
+            WHERE epoch >= $1
+            GROUP BY epoch, COALESCE(client, 'unknown')",
+            &[&Decimal::from(first_epoch)],
+        )
+        .await?;
+
+    let mut stats_by_epoch: HashMap<u64, ClientDiversityStats> = Default::default();
+    for row in rows {
+        let epoch: u64 = row.get::<_, Decimal>("epoch").try_into()?;
+        let client: String = row.get("client");
+        let activated_stake: u64 = row.get::<_, Decimal>("activated_stake").try_into()?;
+        let validators: i64 = row.get("validators");
+
+        let stats = stats_by_epoch
+            .entry(epoch)
+            .or_insert_with(|| ClientDiversityStats {
+                epoch,
+                total_activated_stake: 0,
+                concentration_by_client: Default::default(),
+                stake_by_client: Default::default(),
+                validators_by_client: Default::default(),
+            });
+        stats.total_activated_stake += activated_stake;
+        stats
+            .stake_by_client
+            .insert(client.clone(), activated_stake);
+        stats.validators_by_client.insert(client, validators as u64);
+    }
+
+    let mut stats: Vec<_> = stats_by_epoch.into_values().collect();
+    for epoch_stats in stats.iter_mut() {
+        let total_activated_stake = epoch_stats.total_activated_stake;
+        epoch_stats.concentration_by_client = epoch_stats
+            .stake_by_client
+            .iter()
+            .map(|(client, stake)| {
+                (
+                    client.clone(),
+                    *stake as f64 / total_activated_stake.max(1) as f64,
+                )
+            })
+            .collect();
+    }
+    stats.sort_by(|a, b| b.epoch.cmp(&a.epoch));
+
+    Ok(stats)
+}
+
+/// Vote accounts not clustered into operators yet count as operators of their own.
+pub async fn load_operator_stake(
+    psql_client: &Client,
+    epochs: u64,
+) -> anyhow::Result<HashMap<u64, HashMap<String, u64>>> {
+    let last_epoch = match get_last_epoch(psql_client).await? {
+        Some(last_epoch) => last_epoch,
+        _ => return Ok(Default::default()),
+    };
+    let first_epoch = last_epoch - epochs.min(last_epoch) + 1;
+
+    let rows = psql_client
+        .query(
+            "SELECT
+                validators.epoch,
+                COALESCE(validator_operators.operator_id, validators.vote_account) operator,
+                COALESCE(SUM(activated_stake), 0) activated_stake
+            FROM validators
+                LEFT JOIN validator_operators ON validator_operators.epoch = validators.epoch
+                    AND validator_operators.vote_account = validators.vote_account
+            WHERE validators.epoch >= $1
+            GROUP BY validators.epoch, operator",
+            &[&Decimal::from(first_epoch)],
+        )
+        .await?;
+
+    let mut stake_by_epoch: HashMap<u64, HashMap<String, u64>> = Default::default();
+    for row in rows {
+        stake_by_epoch
+            .entry(row.get::<_, Decimal>("epoch").try_into()?)
+            .or_default()
+            .insert(
+                row.get("operator"),
+                row.get::<_, Decimal>("activated_stake").try_into()?,
+            );
+    }
+
+    Ok(stake_by_epoch)
+}
+
+/// The `other` client stands for several unrelated clients, like the unknown one it is left out of the client metrics.
+fn told_client_stake(client_stats: &ClientDiversityStats) -> HashMap<String, u64> {
+    let other = NodeClient::Other.to_string();
+    client_stats
+        .stake_by_client
+        .iter()
+        .filter(|(client, _)| **client != other)
+        .map(|(client, stake)| (client.clone(), *stake))
+        .collect()
+}
+
+pub fn decentralization_stats(
+    dc_concentration_stats: &[DCConcentrationStats],
+    client_diversity_stats: &[ClientDiversityStats],
+    operator_stake: &HashMap<u64, HashMap<String, u64>>,
+) -> Vec<DecentralizationStats> {
+    let no_stake = HashMap::new();
+    dc_concentration_stats
+        .iter()
+        .map(|dc_stats| DecentralizationStats {
+            epoch: dc_stats.epoch,
+            dc_aso: concentration_metrics(&dc_stats.dc_stake_by_aso),
+            dc_asn: concentration_metrics(&dc_stats.dc_stake_by_asn),
+            dc_city: concentration_metrics(&dc_stats.dc_stake_by_city),
+            dc_country: concentration_metrics(&dc_stats.dc_stake_by_country),
+            client: concentration_metrics(
+                &client_diversity_stats
+                    .iter()
+                    .find(|client_stats| client_stats.epoch == dc_stats.epoch)
+                    .map_or_else(HashMap::new, told_client_stake),
+            ),
+            operator: concentration_metrics(
+                operator_stake.get(&dc_stats.epoch).unwrap_or(&no_stake),
+            ),
+        })
+        .collect()
+}
+
 pub async fn load_cluster_stats(psql_client: &Client, epochs: u64) -> anyhow::Result<ClusterStats> {
+    let dc_concentration_stats = load_dc_concentration_stats(psql_client, epochs).await?;
+    let client_diversity_stats = load_client_diversity_stats(psql_client, epochs).await?;
+    let operator_stake = load_operator_stake(psql_client, epochs).await?;
+
     Ok(ClusterStats {
         block_production_stats: load_block_production_stats(psql_client, epochs).await?,
-        dc_concentration_stats: load_dc_concentration_stats(psql_client, epochs).await?,
+        decentralization_stats: decentralization_stats(
+            &dc_concentration_stats,
+            &client_diversity_stats,
+            &operator_stake,
+        ),
+        dc_concentration_stats,
+        client_diversity_stats,
     })
 }
 
@@ -1483,6 +1655,111 @@ pub fn aggregate_validators(
     agg
 }
 
+/// Operators of the epoch, the last clustered one by default, optionally only the given one.
+pub async fn load_operators(
+    psql_client: &Client,
+    epoch: Option<u64>,
+    operator_id: Option<&str>,
+) -> anyhow::Result<Vec<OperatorRecord>> {
+    let epoch: Decimal = match epoch {
+        Some(epoch) => epoch.into(),
+        None => match psql_client
+            .query_one(
+                "SELECT MAX(epoch) AS last_epoch FROM validator_operators",
+                &[],
+            )
+            .await?
+            .get::<_, Option<Decimal>>("last_epoch")
+        {
+            Some(last_epoch) => last_epoch,
+            None => return Ok(Default::default()),
+        },
+    };
+
+    let rows = psql_client
+        .query(
+            "SELECT
+                validator_operators.operator_id,
+                validator_operators.vote_account,
+                COALESCE(validators.activated_stake, 0) activated_stake,
+                COALESCE(validators.marinade_stake, 0) marinade_stake
+            FROM validator_operators
+                LEFT JOIN validators ON validators.epoch = validator_operators.epoch
+                    AND validators.vote_account = validator_operators.vote_account
+            WHERE validator_operators.epoch = $1 AND ($2::TEXT IS NULL OR validator_operators.operator_id = $2)
+            ORDER BY validator_operators.vote_account",
+            &[&epoch, &operator_id],
+        )
+        .await?;
+
+    let last_epoch: u64 = epoch.try_into()?;
+    let mut operators: HashMap<String, OperatorRecord> = Default::default();
+    for row in rows {
+        let operator_id: String = row.get("operator_id");
+        let operator = operators
+            .entry(operator_id.clone())
+            .or_insert_with(|| OperatorRecord {
+                operator_id,
+                epoch: last_epoch,
+                vote_accounts: Default::default(),
+                activated_stake: Decimal::ZERO,
+                marinade_stake: Decimal::ZERO,
+                links: Default::default(),
+            });
+        operator.vote_accounts.push(row.get("vote_account"));
+        operator.activated_stake += row.get::<_, Decimal>("activated_stake");
+        operator.marinade_stake += row.get::<_, Decimal>("marinade_stake");
+    }
+
+    let links = psql_client
+        .query(
+            "SELECT operator_id, evidence, value, vote_accounts
+            FROM operator_links
+            WHERE epoch = $1 AND ($2::TEXT IS NULL OR operator_id = $2)
+            ORDER BY evidence, value",
+            &[&epoch, &operator_id],
+        )
+        .await?;
+    for row in links {
+        if let Some(operator) = operators.get_mut(&row.get::<_, String>("operator_id")) {
+            operator.links.push(OperatorLinkRecord {
+                evidence: row.get("evidence"),
+                value: row.get("value"),
+                vote_accounts: row.get("vote_accounts"),
+            });
+        }
+    }
+
+    let mut operators: Vec<_> = operators.into_values().collect();
+    operators.sort_by(|a, b| b.activated_stake.cmp(&a.activated_stake));
+
+    Ok(operators)
+}
+
+/// The operator the vote account belongs to in the last clustered epoch.
+pub async fn load_validator_operator(
+    psql_client: &Client,
+    vote_account: &str,
+) -> anyhow::Result<Option<OperatorRecord>> {
+    let row = psql_client
+        .query_opt(
+            "SELECT epoch, operator_id FROM validator_operators
+            WHERE vote_account = $1 ORDER BY epoch DESC LIMIT 1",
+            &[&vote_account],
+        )
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let epoch: u64 = row.get::<_, Decimal>("epoch").try_into()?;
+    let operator_id: String = row.get("operator_id");
+
+    Ok(load_operators(psql_client, Some(epoch), Some(&operator_id))
+        .await?
+        .into_iter()
+        .next())
+}
+
 pub async fn load_validators_aggregated_flat(
     psql_client: &Client,
     last_epoch: u64,
@@ -1494,7 +1771,8 @@ pub async fn load_validators_aggregated_flat(
                 cluster_stake AS (select epoch, sum(activated_stake) as stake from validators group by epoch),
                 cluster_skip_rate AS (select epoch, sum(skip_rate * activated_stake) / sum(activated_stake) stake_weighted_skip_rate from validators group by epoch),
                 dc AS (select validators.epoch, sum(activated_stake) / cluster_stake.stake as dc_concentration, dc_aso from validators LEFT JOIN cluster_stake ON validators.epoch = cluster_stake.epoch group by validators.epoch, dc_aso, cluster_stake.stake),
-                agg_versions AS (select vote_account, (array_agg(version order by created_at desc))[1] as last_version from versions where version is not null group by vote_account)
+                agg_versions AS (select vote_account, (array_agg(version order by created_at desc))[1] as last_version from versions where version is not null group by vote_account),
+                operators AS (select vote_account, operator_id from validator_operators where epoch = $2)
                 select
                     validators.vote_account,
                     min(activated_stake / 1e9)::double precision AS minimum_stake,
@@ -1506,12 +1784,14 @@ pub async fn load_validators_aggregated_flat(
                     (coalesce(avg(credits * greatest(0, 100 - coalesce(commission_effective, commission_advertised, 100))), 0) / 100)::double precision AS avg_adjusted_credits,
                     coalesce((array_agg(validators.dc_aso ORDER BY validators.epoch DESC))[1], 'Unknown') dc_aso,
                     coalesce((array_agg((marinade_stake / 1e9)::double precision ORDER BY validators.epoch DESC))[1], 0) AS marinade_stake,
-                    coalesce((array_agg(agg_versions.last_version))[1], '0.0.0') AS last_version
+                    coalesce((array_agg(agg_versions.last_version))[1], '0.0.0') AS last_version,
+                    coalesce((array_agg(operators.operator_id))[1], validators.vote_account) AS operator_id
                 FROM
                     validators
                     LEFT JOIN dc ON dc.dc_aso = validators.dc_aso AND dc.epoch = validators.epoch
                     LEFT JOIN cluster_skip_rate ON cluster_skip_rate.epoch = validators.epoch
                     LEFT JOIN agg_versions ON validators.vote_account = agg_versions.vote_account
+                    LEFT JOIN operators ON validators.vote_account = operators.vote_account
                 WHERE
                 validators.epoch BETWEEN $1 AND $2
                 GROUP BY validators.vote_account
@@ -1536,6 +1816,7 @@ pub async fn load_validators_aggregated_flat(
             dc_aso: row.get("dc_aso"),
             marinade_stake: row.get("marinade_stake"),
             version: row.get("last_version"),
+            operator_id: row.get("operator_id"),
         });
     }
 
@@ -1557,6 +1838,7 @@ fn map_to_ordered_component_values(
         .collect()
 }
 
+/// Stores the scoring run with its scores in one transaction and announces it on the store channel.
 pub async fn store_scoring(
     psql_client: &mut Client,
     epoch: i32,
@@ -1565,7 +1847,8 @@ pub async fn store_scoring(
     component_weights: Vec<f64>,
     scores: Vec<ValidatorScoringCsvRow>,
 ) -> anyhow::Result<()> {
-    let scoring_run_result = psql_client
+    let transaction = psql_client.transaction().await?;
+    let scoring_run_result = transaction
         .query_one(
             "INSERT INTO scoring_runs (created_at, epoch, components, component_weights, ui_id)
             VALUES (now(), $1, $2, $3, $4) RETURNING scoring_run_id;",
@@ -1661,8 +1944,57 @@ pub async fn store_scoring(
             ];
             query.add(&mut params);
         }
-        query.execute(psql_client).await?;
+        query.execute(&transaction).await?;
     }
 
+    notify(&transaction, "scoring_runs", epoch.try_into().ok()).await?;
+    transaction.commit().await?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decentralization_stats;
+    use crate::dto::{ClientDiversityStats, DCConcentrationStats};
+    use std::collections::HashMap;
+
+    #[test]
+    fn client_metrics_leave_out_untold_clients() {
+        let dc_stats = DCConcentrationStats {
+            epoch: 800,
+            total_activated_stake: 0,
+            dc_concentration_by_aso: Default::default(),
+            dc_stake_by_aso: Default::default(),
+            dc_concentration_by_asn: Default::default(),
+            dc_stake_by_asn: Default::default(),
+            dc_concentration_by_city: Default::default(),
+            dc_stake_by_city: Default::default(),
+            dc_concentration_by_country: Default::default(),
+            dc_stake_by_country: Default::default(),
+        };
+        let client_stats = ClientDiversityStats {
+            epoch: 800,
+            total_activated_stake: 1300,
+            concentration_by_client: Default::default(),
+            stake_by_client: [
+                ("agave", 100),
+                ("jito-solana", 100),
+                ("firedancer", 100),
+                ("other", 500),
+                ("unknown", 500),
+            ]
+            .into_iter()
+            .map(|(client, stake)| (client.to_string(), stake))
+            .collect(),
+            validators_by_client: Default::default(),
+        };
+
+        let stats = decentralization_stats(&[dc_stats], &[client_stats], &HashMap::new());
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].client.entities, 3);
+        assert_eq!(stats[0].client.nakamoto_coefficient_33, 2);
+        assert!(stats[0].client.gini.abs() < 1e-9);
+        assert_eq!(stats[0].operator.entities, 0);
+    }
+}
@@ -1,31 +1,95 @@
+use crate::bulk::{copy_into_temp_table, merge_from_temp_table, BulkColumn};
 use crate::dto::Validator;
-use crate::utils::{InsertQueryCombiner, UpdateQueryCombiner};
+use crate::operators::update_operators;
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Utc};
-use collect::validators::Snapshot;
+use collect::common::CommonParams as CollectCommonParams;
+use collect::snapshot::write_snapshot;
+use collect::validators::{collect_validators_snapshot, Snapshot, ValidatorsParams};
 use log::info;
-use rust_decimal::prelude::*;
-use serde_yaml;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use structopt::clap::Arg;
 use structopt::StructOpt;
-use tokio_postgres::types::ToSql;
-use tokio_postgres::Client;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Transaction};
 
+// Without `--snapshot-file` the snapshot is collected from the RPC, `--from-rpc` only asks for it explicitly.
 #[derive(Debug, StructOpt)]
+#[structopt(arg = Arg::with_name("from-rpc")
+    .long("from-rpc")
+    .help("Collect the snapshot from the RPC instead of reading it from a file.")
+    .conflicts_with("snapshot-path"))]
 pub struct StoreValidatorsParams {
-    #[structopt(long = "snapshot-file")]
-    snapshot_path: String,
-}
+    #[structopt(long = "snapshot-file", required_unless = "from-rpc")]
+    snapshot_path: Option<String>,
+
+    #[structopt(
+        long = "archive-snapshot",
+        help = "File to also write the snapshot collected from the RPC to (in --format).",
+        requires = "from-rpc"
+    )]
+    archive_snapshot: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    collect_common: CollectCommonParams,
 
-const DEFAULT_CHUNK_SIZE: usize = 500;
+    #[structopt(flatten)]
+    collect_validators: ValidatorsParams,
+}
 
 pub async fn store_validators(
     params: StoreValidatorsParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let (snapshot, raw): (Snapshot, _) = match params.snapshot_path {
+        Some(snapshot_path) => read_snapshot_file(snapshot_path)?,
+        None => {
+            let StoreValidatorsParams {
+                collect_common,
+                collect_validators,
+                archive_snapshot,
+                ..
+            } = params;
+            // collecting uses blocking RPC and HTTP clients
+            let snapshot = tokio::task::spawn_blocking(move || -> anyhow::Result<Snapshot> {
+                let snapshot = collect_validators_snapshot(&collect_common, collect_validators)?;
+                if let Some(archive_snapshot) = archive_snapshot {
+                    info!("Archiving the snapshot to {}", archive_snapshot.display());
+                    write_snapshot(
+                        File::create(archive_snapshot)?,
+                        collect_common.format,
+                        &snapshot,
+                    )?;
+                }
+                Ok(snapshot)
+            })
+            .await??;
+            let raw = RawSnapshot::from_snapshot(&snapshot)?;
+            (snapshot, raw)
+        }
+    };
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_validators_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+/// Records of the snapshot epoch are updated unless they were already stored from the same snapshot,
+/// then the operators of the epoch are clustered again.
+pub async fn store_validators_snapshot(
+    snapshot: Snapshot,
+    raw: &RawSnapshot,
+    transaction: &Transaction<'_>,
+) -> anyhow::Result<StoreReport> {
     info!("Storing validators snapshot...");
 
-    let snapshot_file = std::fs::File::open(params.snapshot_path)?;
-    let snapshot: Snapshot = serde_yaml::from_reader(snapshot_file)?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(transaction, &snapshot, raw, &mut report).await?;
+
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse().unwrap();
 
     let validators: HashMap<_, _> = snapshot
@@ -38,265 +102,188 @@ pub async fn store_validators(
             )
         })
         .collect();
-    let snapshot_epoch: Decimal = snapshot.epoch.into();
-    let mut updated_vote_accounts: HashSet<_> = Default::default();
 
     info!("Loaded the snapshot");
 
-    for chunk in psql_client
-        .query(
-            "
-        SELECT vote_account
-        FROM validators
-        WHERE epoch = $1
-    ",
-            &[&snapshot_epoch],
-        )
-        .await?
-        .chunks(DEFAULT_CHUNK_SIZE)
-    {
-        let mut query = UpdateQueryCombiner::new(
-            "validators".to_string(),
-            "
-            identity = u.identity,
-            vote_account = u.vote_account,
-            epoch = u.epoch,
-            info_name = u.info_name,
-            info_url = u.info_url,
-            info_keybase = u.info_keybase,
-            node_ip = u.node_ip,
-            dc_coordinates_lat = u.dc_coordinates_lat,
-            dc_coordinates_lon = u.dc_coordinates_lon,
-            dc_continent = u.dc_continent,
-            dc_country_iso = u.dc_country_iso,
-            dc_country = u.dc_country,
-            dc_city = u.dc_city,
-            dc_asn = u.dc_asn,
-            dc_aso = u.dc_aso,
-            commission_advertised = u.commission_advertised,
-            version = COALESCE(u.version, validators.version),
-            activated_stake = u.activated_stake,
-            marinade_stake = u.marinade_stake,
-            foundation_stake = u.foundation_stake,
-            marinade_native_stake = u.marinade_native_stake,
-            institutional_stake = u.institutional_stake,
-            self_stake = u.self_stake,
-            superminority = u.superminority,
-            stake_to_become_superminority = u.stake_to_become_superminority,
-            credits = u.credits,
-            leader_slots = u.leader_slots,
-            blocks_produced = u.blocks_produced,
-            skip_rate = u.skip_rate,
-            updated_at = u.updated_at,
-            info_icon_url = u.info_icon_url
-            "
-            .to_string(),
-            "u(
-                identity,
-                vote_account,
-                epoch,
-                info_name,
-                info_url,
-                info_keybase,
-                node_ip,
-                dc_coordinates_lat,
-                dc_coordinates_lon,
-                dc_continent,
-                dc_country_iso,
-                dc_country,
-                dc_city,
-                dc_asn,
-                dc_aso,
-                commission_advertised,
-                version,
-                activated_stake,
-                marinade_stake,
-                foundation_stake,
-                marinade_native_stake,
-                institutional_stake,
-                self_stake,
-                superminority,
-                stake_to_become_superminority,
-                credits,
-                leader_slots,
-                blocks_produced,
-                skip_rate,
-                updated_at,
-                info_icon_url
-            )"
-            .to_string(),
-            "validators.vote_account = u.vote_account AND validators.epoch = u.epoch".to_string(),
-        );
-        for row in chunk {
-            let vote_account: &str = row.get("vote_account");
+    let columns = [
+        BulkColumn::new("identity", Type::TEXT),
+        BulkColumn::new("vote_account", Type::TEXT),
+        BulkColumn::new("epoch", Type::NUMERIC),
+        BulkColumn::new("info_name", Type::TEXT),
+        BulkColumn::new("info_url", Type::TEXT),
+        BulkColumn::new("info_keybase", Type::TEXT),
+        BulkColumn::new("node_ip", Type::TEXT),
+        BulkColumn::new("dc_coordinates_lat", Type::FLOAT8),
+        BulkColumn::new("dc_coordinates_lon", Type::FLOAT8),
+        BulkColumn::new("dc_continent", Type::TEXT),
+        BulkColumn::new("dc_country_iso", Type::TEXT),
+        BulkColumn::new("dc_country", Type::TEXT),
+        BulkColumn::new("dc_city", Type::TEXT),
+        BulkColumn::new("dc_asn", Type::INT4),
+        BulkColumn::new("dc_aso", Type::TEXT),
+        BulkColumn::new("commission_max_observed", Type::INT4),
+        BulkColumn::new("commission_min_observed", Type::INT4),
+        BulkColumn::new("commission_advertised", Type::INT4),
+        BulkColumn::new("commission_effective", Type::INT4),
+        BulkColumn::new("version", Type::TEXT),
+        BulkColumn::new("client", Type::TEXT),
+        BulkColumn::new("activated_stake", Type::NUMERIC),
+        BulkColumn::new("marinade_stake", Type::NUMERIC),
+        BulkColumn::new("foundation_stake", Type::NUMERIC),
+        BulkColumn::new("marinade_native_stake", Type::NUMERIC),
+        BulkColumn::new("institutional_stake", Type::NUMERIC),
+        BulkColumn::new("self_stake", Type::NUMERIC),
+        BulkColumn::new("superminority", Type::BOOL),
+        BulkColumn::new("stake_to_become_superminority", Type::NUMERIC),
+        BulkColumn::new("credits", Type::NUMERIC),
+        BulkColumn::new("leader_slots", Type::NUMERIC),
+        BulkColumn::new("blocks_produced", Type::NUMERIC),
+        BulkColumn::new("skip_rate", Type::FLOAT8),
+        BulkColumn::new("uptime_pct", Type::FLOAT8),
+        BulkColumn::new("uptime", Type::NUMERIC),
+        BulkColumn::new("downtime", Type::NUMERIC),
+        BulkColumn::new("updated_at", Type::TIMESTAMPTZ),
+        BulkColumn::new("info_icon_url", Type::TEXT),
+        BulkColumn::new("withdraw_authority", Type::TEXT),
+        BulkColumn::new("snapshot_id", Type::INT8),
+    ];
+    let rows = validators.values().map(|v| {
+        let row: Vec<&(dyn ToSql + Sync)> = vec![
+            &v.identity,
+            &v.vote_account,
+            &v.epoch,
+            &v.info_name,
+            &v.info_url,
+            &v.info_keybase,
+            &v.node_ip,
+            // stored swapped to stay consistent with the existing records
+            &v.dc_coordinates_lon,
+            &v.dc_coordinates_lat,
+            &v.dc_continent,
+            &v.dc_country_iso,
+            &v.dc_country,
+            &v.dc_city,
+            &v.dc_asn,
+            &v.dc_aso,
+            &v.commission_max_observed,
+            &v.commission_min_observed,
+            &v.commission_advertised,
+            &v.commission_effective,
+            &v.version,
+            &v.client,
+            &v.activated_stake,
+            &v.marinade_stake,
+            &v.foundation_stake,
+            &v.marinade_native_stake,
+            &v.institutional_stake,
+            &v.self_stake,
+            &v.superminority,
+            &v.stake_to_become_superminority,
+            &v.credits,
+            &v.leader_slots,
+            &v.blocks_produced,
+            &v.skip_rate,
+            &v.uptime_pct,
+            &v.uptime,
+            &v.downtime,
+            &snapshot_created_at,
+            &v.info_icon_url,
+            &v.withdraw_authority,
+            &snapshot_id,
+        ];
+        row
+    });
+    copy_into_temp_table(transaction, "validators_snapshot", &columns, rows).await?;
 
-            if let Some(v) = validators.get(vote_account) {
-                let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-                    &v.identity,
-                    &v.vote_account,
-                    &v.epoch,
-                    &v.info_name,
-                    &v.info_url,
-                    &v.info_keybase,
-                    &v.node_ip,
-                    &v.dc_coordinates_lon,
-                    &v.dc_coordinates_lat,
-                    &v.dc_continent,
-                    &v.dc_country_iso,
-                    &v.dc_country,
-                    &v.dc_city,
-                    &v.dc_asn,
-                    &v.dc_aso,
-                    &v.commission_advertised,
-                    &v.version,
-                    &v.activated_stake,
-                    &v.marinade_stake,
-                    &v.foundation_stake,
-                    &v.marinade_native_stake,
-                    &v.institutional_stake,
-                    &v.self_stake,
-                    &v.superminority,
-                    &v.stake_to_become_superminority,
-                    &v.credits,
-                    &v.leader_slots,
-                    &v.blocks_produced,
-                    &v.skip_rate,
-                    &snapshot_created_at,
-                    &v.info_icon_url,
-                ];
-                query.add(
-                    &mut params,
-                    HashMap::from_iter([
-                        (2, "NUMERIC".into()),                   // epoch
-                        (7, "DOUBLE PRECISION".into()),          // dc_coordinates_lat
-                        (8, "DOUBLE PRECISION".into()),          // dc_coordinates_lon
-                        (13, "INTEGER".into()),                  // dc_asn
-                        (15, "INTEGER".into()),                  // commission_advertised
-                        (17, "NUMERIC".into()),                  // activated_stake
-                        (18, "NUMERIC".into()),                  // marinade_stake
-                        (19, "NUMERIC".into()),                  // foundation_stake
-                        (20, "NUMERIC".into()),                  // marinade_native_stake
-                        (21, "NUMERIC".into()),                  // institutional_stake
-                        (22, "NUMERIC".into()),                  // selft_stake
-                        (23, "BOOL".into()),                     // superminority
-                        (24, "NUMERIC".into()),                  // stake_to_become_superminority
-                        (25, "NUMERIC".into()),                  // credits
-                        (26, "NUMERIC".into()),                  // leader_slots
-                        (27, "NUMERIC".into()),                  // blocks_produced
-                        (28, "DOUBLE PRECISION".into()),         // skip_rate
-                        (29, "TIMESTAMP WITH TIME ZONE".into()), // updated_at
-                        (30, "TEXT".into()),                     // icon_url
-                    ]),
-                );
-                updated_vote_accounts.insert(vote_account.to_string());
-            }
-        }
-        query.execute(psql_client).await?;
-        info!(
-            "Updated previously existing validator records: {}",
-            updated_vote_accounts.len()
-        );
-    }
+    let insert_columns: Vec<_> = columns.iter().map(|column| column.name.as_str()).collect();
+    let stats = merge_from_temp_table(
+        transaction,
+        "validators_snapshot",
+        "validators",
+        &["vote_account", "epoch"],
+        &insert_columns,
+        &[
+            ("identity", "s.identity"),
+            ("info_name", "s.info_name"),
+            ("info_url", "s.info_url"),
+            ("info_keybase", "s.info_keybase"),
+            ("node_ip", "s.node_ip"),
+            ("dc_coordinates_lat", "s.dc_coordinates_lat"),
+            ("dc_coordinates_lon", "s.dc_coordinates_lon"),
+            ("dc_continent", "s.dc_continent"),
+            ("dc_country_iso", "s.dc_country_iso"),
+            ("dc_country", "s.dc_country"),
+            ("dc_city", "s.dc_city"),
+            ("dc_asn", "s.dc_asn"),
+            ("dc_aso", "s.dc_aso"),
+            ("commission_advertised", "s.commission_advertised"),
+            ("version", "COALESCE(s.version, validators.version)"),
+            ("client", "COALESCE(s.client, validators.client)"),
+            ("activated_stake", "s.activated_stake"),
+            ("marinade_stake", "s.marinade_stake"),
+            ("foundation_stake", "s.foundation_stake"),
+            ("marinade_native_stake", "s.marinade_native_stake"),
+            ("institutional_stake", "s.institutional_stake"),
+            ("self_stake", "s.self_stake"),
+            ("superminority", "s.superminority"),
+            (
+                "stake_to_become_superminority",
+                "s.stake_to_become_superminority",
+            ),
+            ("credits", "s.credits"),
+            ("leader_slots", "s.leader_slots"),
+            ("blocks_produced", "s.blocks_produced"),
+            ("skip_rate", "s.skip_rate"),
+            ("updated_at", "s.updated_at"),
+            ("info_icon_url", "s.info_icon_url"),
+            (
+                "withdraw_authority",
+                "COALESCE(s.withdraw_authority, validators.withdraw_authority)",
+            ),
+            ("snapshot_id", "s.snapshot_id"),
+        ],
+        "validators.updated_at IS DISTINCT FROM s.updated_at",
+    )
+    .await?;
+    info!(
+        "Stored validators: {} inserted, {} updated, {} unchanged",
+        stats.inserted, stats.updated, stats.skipped
+    );
 
-    let validators: Vec<_> = validators
-        .into_iter()
-        .filter(|(vote_account, _validator)| !updated_vote_accounts.contains(vote_account))
-        .collect();
-    let mut insertions = 0;
+    *report.table("validators") = stats;
+    update_operators(transaction, snapshot.epoch, &mut report).await?;
 
-    for chunk in validators.chunks(DEFAULT_CHUNK_SIZE) {
-        let mut query = InsertQueryCombiner::new(
-            "validators".to_string(),
-            "
-        identity,
-        vote_account,
-        epoch,
-        info_name,
-        info_url,
-        info_keybase,
-        node_ip,
-        dc_coordinates_lat,
-        dc_coordinates_lon,
-        dc_continent,
-        dc_country_iso,
-        dc_country,
-        dc_city,
-        dc_asn,
-        dc_aso,
-        commission_max_observed,
-        commission_min_observed,
-        commission_advertised,
-        commission_effective,
-        version,
-        activated_stake,
-        marinade_stake,
-        foundation_stake,
-        marinade_native_stake,
-        institutional_stake,
-        self_stake,
-        superminority,
-        stake_to_become_superminority,
-        credits,
-        leader_slots,
-        blocks_produced,
-        skip_rate,
-        uptime_pct,
-        uptime,
-        downtime,
-        updated_at,
-        info_icon_url
-        "
-            .to_string(),
-        );
+    Ok(report)
+}
 
-        for (vote_account, v) in chunk {
-            if updated_vote_accounts.contains(vote_account) {
-                continue;
-            }
-            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-                &v.identity,
-                &v.vote_account,
-                &v.epoch,
-                &v.info_name,
-                &v.info_url,
-                &v.info_keybase,
-                &v.node_ip,
-                &v.dc_coordinates_lon,
-                &v.dc_coordinates_lat,
-                &v.dc_continent,
-                &v.dc_country_iso,
-                &v.dc_country,
-                &v.dc_city,
-                &v.dc_asn,
-                &v.dc_aso,
-                &v.commission_max_observed,
-                &v.commission_min_observed,
-                &v.commission_advertised,
-                &v.commission_effective,
-                &v.version,
-                &v.activated_stake,
-                &v.marinade_stake,
-                &v.foundation_stake,
-                &v.marinade_native_stake,
-                &v.institutional_stake,
-                &v.self_stake,
-                &v.superminority,
-                &v.stake_to_become_superminority,
-                &v.credits,
-                &v.leader_slots,
-                &v.blocks_produced,
-                &v.skip_rate,
-                &v.uptime_pct,
-                &v.uptime,
-                &v.downtime,
-                &snapshot_created_at,
-                &v.info_icon_url,
-            ];
-            query.add(&mut params);
-        }
-        insertions += query.execute(psql_client).await?.unwrap_or(0);
-        info!("Stored {insertions} new validator records");
-    }
+#[cfg(test)]
+mod tests {
+    use super::StoreValidatorsParams;
+    use structopt::StructOpt;
 
-    Ok(())
+    #[test]
+    fn snapshot_from_file_or_rpc() {
+        let parse = |args: &[&str]| {
+            StoreValidatorsParams::from_iter_safe(
+                std::iter::once("validators").chain(args.iter().copied()),
+            )
+        };
+        assert_eq!(
+            parse(&["--snapshot-file", "snapshot.yaml"])
+                .unwrap()
+                .snapshot_path
+                .as_deref(),
+            Some("snapshot.yaml")
+        );
+        assert_eq!(parse(&["--from-rpc"]).unwrap().snapshot_path, None);
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--from-rpc", "--snapshot-file", "snapshot.yaml"]).is_err());
+        assert!(parse(&[
+            "--snapshot-file",
+            "snapshot.yaml",
+            "--archive-snapshot",
+            "out.yaml"
+        ])
+        .is_err());
+    }
 }
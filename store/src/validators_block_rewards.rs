@@ -1,12 +1,13 @@
 use crate::dto::{ValidatorBlockReward, ValidatorBlockRewardsRecord};
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Utc};
 use collect::validators_block_rewards::ValidatorsBlockRewardsSnapshot;
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
 use std::collections::HashMap;
 use structopt::StructOpt;
-use tokio_postgres::Client;
+use tokio_postgres::{Client, GenericClient};
 
 pub const VALIDATORS_BLOCK_REWARDS_TABLE: &str = "validators_block_rewards";
 
@@ -21,16 +22,26 @@ const DEFAULT_CHUNK_SIZE: usize = 500;
 pub async fn store_block_rewards(
     params: StoreBlockRewardsParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let path = params.snapshot_path;
+    let (snapshot, raw): (ValidatorsBlockRewardsSnapshot, _) = read_snapshot_file(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read snapshot block rewards file '{path}': {e}"))?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_block_rewards_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+pub async fn store_block_rewards_snapshot(
+    snapshot: ValidatorsBlockRewardsSnapshot,
+    raw: &RawSnapshot,
+    psql_client: &impl GenericClient,
+) -> anyhow::Result<StoreReport> {
     info!("Storing block rewards snapshot...");
 
-    let path = params.snapshot_path;
-    let snapshot_file = std::fs::File::open(&path)
-        .map_err(|e| anyhow::anyhow!("Failed to open snapshot block rewards file '{path}': {e}"))?;
-    let snapshot: ValidatorsBlockRewardsSnapshot =
-        serde_yaml::from_reader(snapshot_file).map_err(|e| {
-            anyhow::anyhow!("Failed to parse snapshot block rewards file '{path}': {e}")
-        })?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(psql_client, &snapshot, raw, &mut report).await?;
 
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse()?;
     let snapshot_epoch = Decimal::from(snapshot.epoch);
@@ -60,8 +71,6 @@ pub async fn store_block_rewards(
 
     let records: Vec<_> = block_rewards.values().collect();
 
-    let mut total_upserted = 0;
-
     for chunk in records.chunks(DEFAULT_CHUNK_SIZE) {
         // Build arrays for each column
         let identity_accounts: Vec<&str> =
@@ -82,9 +91,10 @@ pub async fn store_block_rewards(
             amount,
             epoch,
             created_at,
-            updated_at
+            updated_at,
+            snapshot_id
         )
-        SELECT * FROM UNNEST(
+        SELECT *, $8::BIGINT FROM UNNEST(
             $1::TEXT[],
             $2::TEXT[],
             $3::TEXT[],
@@ -97,11 +107,15 @@ pub async fn store_block_rewards(
         DO UPDATE SET
             authorized_voter = EXCLUDED.authorized_voter,
             amount = EXCLUDED.amount,
-            updated_at = EXCLUDED.updated_at"
+            updated_at = EXCLUDED.updated_at,
+            snapshot_id = EXCLUDED.snapshot_id
+        WHERE ({VALIDATORS_BLOCK_REWARDS_TABLE}.authorized_voter, {VALIDATORS_BLOCK_REWARDS_TABLE}.amount)
+            IS DISTINCT FROM (EXCLUDED.authorized_voter, EXCLUDED.amount)
+        RETURNING (xmax = 0) AS inserted"
         );
 
-        let rows_affected = psql_client
-            .execute(
+        let upserted_rows = psql_client
+            .query(
                 &query,
                 &[
                     &identity_accounts,
@@ -111,18 +125,29 @@ pub async fn store_block_rewards(
                     &epochs,
                     &created_ats,
                     &updated_ats,
+                    &snapshot_id,
                 ],
             )
             .await?;
 
-        total_upserted += rows_affected;
-
-        info!("Upserted {rows_affected} block rewards records in this chunk",);
+        let insertions = upserted_rows
+            .iter()
+            .filter(|row| row.get::<_, bool>("inserted"))
+            .count() as u64;
+        let stats = report.table(VALIDATORS_BLOCK_REWARDS_TABLE);
+        stats.inserted += insertions;
+        stats.updated += upserted_rows.len() as u64 - insertions;
+        stats.skipped += (chunk.len() - upserted_rows.len()) as u64;
+
+        info!(
+            "Upserted {} block rewards records in this chunk",
+            upserted_rows.len()
+        );
     }
 
-    info!("Stored block rewards snapshot: {total_upserted} total records upserted",);
+    info!("Stored block rewards snapshot");
 
-    Ok(())
+    Ok(report)
 }
 
 pub async fn get_last_block_rewards(
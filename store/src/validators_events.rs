@@ -1,12 +1,13 @@
 use crate::dto::{EventEpochRecord, PerformanceRecord, SettlementRecord};
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use crate::utils::DEFAULT_CACHE_EPOCHS;
 use chrono::{DateTime, Utc};
 use collect::validators_events::ValidatorsEventsSnapshot;
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
 use std::collections::HashMap;
-use tokio_postgres::Client;
+use tokio_postgres::{Client, GenericClient};
 
 pub const VALIDATORS_EVENTS_TABLE: &str = "validators_events";
 
@@ -21,14 +22,26 @@ const DEFAULT_CHUNK_SIZE: usize = 500;
 pub async fn store_events(
     params: StoreEventsParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let path = params.snapshot_path;
+    let (snapshot, raw): (ValidatorsEventsSnapshot, _) = read_snapshot_file(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read snapshot events file '{path}': {e}"))?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_events_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+pub async fn store_events_snapshot(
+    snapshot: ValidatorsEventsSnapshot,
+    raw: &RawSnapshot,
+    psql_client: &impl GenericClient,
+) -> anyhow::Result<StoreReport> {
     info!("Storing events (PSR settlements) snapshot...");
 
-    let path = params.snapshot_path;
-    let snapshot_file = std::fs::File::open(&path)
-        .map_err(|e| anyhow::anyhow!("Failed to open snapshot events file '{path}': {e}"))?;
-    let snapshot: ValidatorsEventsSnapshot = serde_yaml::from_reader(snapshot_file)
-        .map_err(|e| anyhow::anyhow!("Failed to parse snapshot events file '{path}': {e}"))?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(psql_client, &snapshot, raw, &mut report).await?;
 
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse()?;
 
@@ -41,8 +54,6 @@ pub async fn store_events(
         snapshot.events.len()
     );
 
-    let mut total_upserted = 0;
-
     for chunk in snapshot.events.chunks(DEFAULT_CHUNK_SIZE) {
         let epochs: Vec<Decimal> = chunk.iter().map(|r| Decimal::from(r.epoch)).collect();
         let vote_accounts: Vec<&str> = chunk.iter().map(|r| r.vote_account.as_str()).collect();
@@ -60,9 +71,10 @@ pub async fn store_events(
             meta,
             amount,
             created_at,
-            updated_at
+            updated_at,
+            snapshot_id
         )
-        SELECT * FROM UNNEST(
+        SELECT *, $8::BIGINT FROM UNNEST(
             $1::NUMERIC[],
             $2::TEXT[],
             $3::TEXT[],
@@ -74,11 +86,14 @@ pub async fn store_events(
         ON CONFLICT (epoch, vote_account, reason, meta)
         DO UPDATE SET
             amount = EXCLUDED.amount,
-            updated_at = EXCLUDED.updated_at"
+            updated_at = EXCLUDED.updated_at,
+            snapshot_id = EXCLUDED.snapshot_id
+        WHERE {VALIDATORS_EVENTS_TABLE}.amount IS DISTINCT FROM EXCLUDED.amount
+        RETURNING (xmax = 0) AS inserted"
         );
 
-        let rows_affected = psql_client
-            .execute(
+        let upserted_rows = psql_client
+            .query(
                 &query,
                 &[
                     &epochs,
@@ -88,18 +103,29 @@ pub async fn store_events(
                     &amounts,
                     &created_ats,
                     &updated_ats,
+                    &snapshot_id,
                 ],
             )
             .await?;
 
-        total_upserted += rows_affected;
-
-        info!("Upserted {rows_affected} events records in this chunk");
+        let insertions = upserted_rows
+            .iter()
+            .filter(|row| row.get::<_, bool>("inserted"))
+            .count() as u64;
+        let stats = report.table(VALIDATORS_EVENTS_TABLE);
+        stats.inserted += insertions;
+        stats.updated += upserted_rows.len() as u64 - insertions;
+        stats.skipped += (chunk.len() - upserted_rows.len()) as u64;
+
+        info!(
+            "Upserted {} events records in this chunk",
+            upserted_rows.len()
+        );
     }
 
-    info!("Stored events snapshot: {total_upserted} total records upserted");
+    info!("Stored events snapshot");
 
-    Ok(())
+    Ok(report)
 }
 
 /// `from = true` -> smallest epoch ending on/after `date`; else largest ending on/before.
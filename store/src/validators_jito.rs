@@ -1,17 +1,18 @@
+use crate::bulk::{copy_into_temp_table, merge_from_temp_table, BulkColumn};
 use crate::dto::{
     JitoMevRecord, JitoPriorityFeeRecord, JitoRecord, ValidatorJitoMEVInfo,
     ValidatorJitoPriorityFeeInfo,
 };
-use crate::utils::*;
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Utc};
 use collect::validators_jito::{JitoAccountType, JitoSnapshot};
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
 use std::collections::{HashMap, HashSet};
 use structopt::StructOpt;
-use tokio_postgres::types::ToSql;
-use tokio_postgres::Client;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Transaction};
 
 #[derive(Debug, StructOpt)]
 pub struct StoreJitoParams {
@@ -19,20 +20,32 @@ pub struct StoreJitoParams {
     snapshot_path: String,
 }
 
-const DEFAULT_CHUNK_SIZE: usize = 500;
-
 pub async fn store_jito(
     params: StoreJitoParams,
     psql_client: &mut Client,
     account_type: JitoAccountType,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    let path = params.snapshot_path;
+    let (snapshot, raw): (JitoSnapshot, _) = read_snapshot_file(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read snapshot file '{path}': {e}",))?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_jito_snapshot(snapshot, &raw, &transaction, account_type).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+pub async fn store_jito_snapshot(
+    snapshot: JitoSnapshot,
+    raw: &RawSnapshot,
+    transaction: &Transaction<'_>,
+    account_type: JitoAccountType,
+) -> anyhow::Result<StoreReport> {
     info!("Storing JITO account {account_type} snapshot...");
 
-    let path = params.snapshot_path;
-    let snapshot_file = std::fs::File::open(&path)
-        .map_err(|e| anyhow::anyhow!("Failed to open snapshot file '{path}': {e}"))?;
-    let snapshot: JitoSnapshot = serde_yaml::from_reader(snapshot_file)
-        .map_err(|e| anyhow::anyhow!("Failed to parse snapshot file '{path}': {e}",))?;
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(transaction, &snapshot, raw, &mut report).await?;
+
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse()?;
     let snapshot_loaded_at_slot_index = Decimal::from(snapshot.loaded_at_slot_index);
     let snapshot_epoch = Decimal::from(snapshot.epoch);
@@ -45,7 +58,7 @@ pub async fn store_jito(
         snapshot_loaded_at_slot_index
     );
 
-    match account_type {
+    let stored = match account_type {
         JitoAccountType::MevTipDistribution => {
             let validators_jito_mev: HashMap<_, _> = snapshot
                 .get_mev_validators()
@@ -53,14 +66,15 @@ pub async fn store_jito(
                 .map(|v| (v.0.clone(), ValidatorJitoMEVInfo::from_snapshot(v.1)))
                 .collect();
             store_mev(
-                psql_client,
+                transaction,
                 snapshot_epoch,
                 snapshot_created_at,
                 snapshot_loaded_at_slot_index,
+                snapshot_id,
                 account_type.db_table_name(),
                 validators_jito_mev,
             )
-            .await
+            .await?
         }
         JitoAccountType::PriorityFeeDistribution => {
             let validators_jito_priority_fee: HashMap<_, _> = snapshot
@@ -74,302 +88,205 @@ pub async fn store_jito(
                 })
                 .collect();
             store_priority_fee(
-                psql_client,
+                transaction,
                 snapshot_epoch,
                 snapshot_created_at,
                 snapshot_loaded_at_slot_index,
+                snapshot_id,
                 account_type.db_table_name(),
                 validators_jito_priority_fee,
             )
-            .await
+            .await?
         }
-    }
-}
+    };
+    report.merge(stored);
 
-async fn get_existing_vote_accounts(
-    psql_client: &Client,
-    db_table: &str,
-    snapshot_epoch: Decimal,
-) -> anyhow::Result<Vec<tokio_postgres::Row>> {
-    let select_query = format!("SELECT vote_account FROM {db_table} WHERE epoch = $1");
-    psql_client
-        .query(&select_query, &[&snapshot_epoch])
-        .await
-        .map_err(|e| {
-            anyhow::anyhow!(
-                "Failed to get existing vote accounts from DB table {db_table} for epoch {snapshot_epoch}: {e} [{e:?}]"
-            )
-        })
+    Ok(report)
 }
 
 async fn store_mev(
-    psql_client: &mut Client,
+    transaction: &Transaction<'_>,
     snapshot_epoch: Decimal,
     snapshot_created_at: DateTime<Utc>,
     snapshot_loaded_at_slot_index: Decimal,
+    snapshot_id: i64,
     db_table: &str,
     validators_mev: HashMap<String, ValidatorJitoMEVInfo>,
-) -> anyhow::Result<()> {
-    let mut updated_identities: HashSet<_> = Default::default();
+) -> anyhow::Result<StoreReport> {
     info!(
         "Processing snapshot loaded MEV records {}",
         validators_mev.keys().len()
     );
-    let existing_vote_accounts =
-        get_existing_vote_accounts(psql_client, db_table, snapshot_epoch).await?;
-    let mut updates: u64 = 0;
-
-    for chunk in existing_vote_accounts.chunks(DEFAULT_CHUNK_SIZE) {
-        let mut query = UpdateQueryCombiner::new(
-            db_table.to_string(),
-            "
-            vote_account = u.vote_account,
-            mev_commission = u.mev_commission,
-            total_epoch_rewards = u.total_epoch_rewards,
-            claimed_epoch_rewards = u.claimed_epoch_rewards,
-            total_epoch_claimants = u.total_epoch_claimants,
-            epoch_active_claimants = u.epoch_active_claimants,
-            epoch_slot = u.epoch_slot,
-            epoch = u.epoch
-            "
-            .to_string(),
-            "u(
-                vote_account,
-                mev_commission,
-                total_epoch_rewards,
-                claimed_epoch_rewards,
-                total_epoch_claimants,
-                epoch_active_claimants,
-                epoch_slot,
-                epoch
+
+    let columns = [
+        BulkColumn::new("vote_account", Type::TEXT),
+        BulkColumn::new("mev_commission", Type::INT4),
+        BulkColumn::new("total_epoch_rewards", Type::NUMERIC),
+        BulkColumn::new("claimed_epoch_rewards", Type::NUMERIC),
+        BulkColumn::new("total_epoch_claimants", Type::INT4),
+        BulkColumn::new("epoch_active_claimants", Type::INT4),
+        BulkColumn::new("epoch_slot", Type::NUMERIC),
+        BulkColumn::new("epoch", Type::NUMERIC),
+        BulkColumn::new("created_at", Type::TIMESTAMPTZ),
+        BulkColumn::new("snapshot_id", Type::INT8),
+    ];
+    let rows = validators_mev.values().map(|v| {
+        let row: Vec<&(dyn ToSql + Sync)> = vec![
+            &v.vote_account,
+            &v.mev_commission,
+            &v.total_epoch_rewards,
+            &v.claimed_epoch_rewards,
+            &v.total_epoch_claimants,
+            &v.epoch_active_claimants,
+            &snapshot_loaded_at_slot_index,
+            &snapshot_epoch,
+            &snapshot_created_at,
+            &snapshot_id,
+        ];
+        row
+    });
+    let temp_table = format!("{db_table}_snapshot");
+    copy_into_temp_table(transaction, &temp_table, &columns, rows).await?;
+
+    let insert_columns: Vec<_> = columns.iter().map(|column| column.name.as_str()).collect();
+    let stats = merge_from_temp_table(
+        transaction,
+        &temp_table,
+        db_table,
+        &["vote_account", "epoch"],
+        &insert_columns,
+        &[
+            ("mev_commission", "s.mev_commission"),
+            ("total_epoch_rewards", "s.total_epoch_rewards"),
+            ("claimed_epoch_rewards", "s.claimed_epoch_rewards"),
+            ("total_epoch_claimants", "s.total_epoch_claimants"),
+            ("epoch_active_claimants", "s.epoch_active_claimants"),
+            ("epoch_slot", "s.epoch_slot"),
+            ("snapshot_id", "s.snapshot_id"),
+        ],
+        &format!(
+            "(
+                {db_table}.mev_commission,
+                {db_table}.total_epoch_rewards,
+                {db_table}.claimed_epoch_rewards,
+                {db_table}.total_epoch_claimants,
+                {db_table}.epoch_active_claimants,
+                {db_table}.epoch_slot
+            ) IS DISTINCT FROM (
+                s.mev_commission,
+                s.total_epoch_rewards,
+                s.claimed_epoch_rewards,
+                s.total_epoch_claimants,
+                s.epoch_active_claimants,
+                s.epoch_slot
             )"
-            .to_string(),
-            format!("{db_table}.vote_account = u.vote_account AND {db_table}.epoch = u.epoch"),
-        );
-        for row in chunk {
-            let vote_account: &str = row.get("vote_account");
-
-            if let Some(v) = validators_mev.get(vote_account) {
-                let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-                    &v.vote_account,
-                    &v.mev_commission,
-                    &v.total_epoch_rewards,
-                    &v.claimed_epoch_rewards,
-                    &v.total_epoch_claimants,
-                    &v.epoch_active_claimants,
-                    &snapshot_loaded_at_slot_index,
-                    &v.epoch,
-                    &snapshot_created_at,
-                ];
-                query.add(
-                    &mut params,
-                    HashMap::from_iter([
-                        (0, "TEXT".into()),                     // vote_account
-                        (1, "INTEGER".into()),                  // mev_commission
-                        (2, "NUMERIC".into()),                  // total_epoch_rewards
-                        (3, "NUMERIC".into()),                  // claimed_epoch_rewards
-                        (4, "INTEGER".into()),                  // total_epoch_claimants
-                        (5, "INTEGER".into()),                  // epoch_active_claimants
-                        (6, "NUMERIC".into()),                  // snapshot_loaded_at_slot_index
-                        (7, "DECIMAL".into()),                  // epoch
-                        (8, "TIMESTAMP WITH TIME ZONE".into()), // snapshot_created_at
-                    ]),
-                );
-                updated_identities.insert(vote_account.to_string());
-            }
-        }
-        updates += query.execute(psql_client).await?.unwrap_or(0);
-        info!(
-            "Trying to update {} previously existing MEV records. SQL updated records: {}",
-            updated_identities.len(),
-            updates
-        );
-    }
-    let validators_mev_executions: Vec<_> = validators_mev
-        .into_iter()
-        .filter(|(vote_account, _)| !updated_identities.contains(vote_account))
-        .collect();
-    let mut insertions = 0;
-
-    for chunk in validators_mev_executions.chunks(DEFAULT_CHUNK_SIZE) {
-        let mut query = InsertQueryCombiner::new(
-            db_table.to_string(),
-            "
-        vote_account,
-        mev_commission,
-        total_epoch_rewards,
-        claimed_epoch_rewards,
-        total_epoch_claimants,
-        epoch_active_claimants,
-        epoch_slot,
-        epoch,
-        created_at
-        "
-            .to_string(),
-        );
-
-        for (vote_account, v) in chunk {
-            if updated_identities.contains(vote_account) {
-                continue;
-            }
-            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-                &v.vote_account,
-                &v.mev_commission,
-                &v.total_epoch_rewards,
-                &v.claimed_epoch_rewards,
-                &v.total_epoch_claimants,
-                &v.epoch_active_claimants,
-                &snapshot_loaded_at_slot_index,
-                &snapshot_epoch,
-                &snapshot_created_at,
-            ];
-            query.add(&mut params);
-        }
-        insertions += query.execute(psql_client).await?.unwrap_or(0);
-        info!("Inserted new new MEV records {insertions}");
-    }
+        ),
+    )
+    .await?;
 
-    info!("Stored MEV snapshot: {updates} updated, {insertions} inserted");
+    info!(
+        "Stored MEV snapshot: {} updated, {} inserted, {} unchanged",
+        stats.updated, stats.inserted, stats.skipped
+    );
 
-    Ok(())
+    let mut report = StoreReport::default();
+    *report.table(db_table) = stats;
+    Ok(report)
 }
 
 async fn store_priority_fee(
-    psql_client: &mut Client,
+    transaction: &Transaction<'_>,
     snapshot_epoch: Decimal,
     snapshot_created_at: DateTime<Utc>,
     snapshot_loaded_at_slot_index: Decimal,
+    snapshot_id: i64,
     db_table: &str,
     validators_priority_fee: HashMap<String, ValidatorJitoPriorityFeeInfo>,
-) -> anyhow::Result<()> {
-    let mut updated_identities: HashSet<_> = Default::default();
+) -> anyhow::Result<StoreReport> {
     info!(
         "Processing snapshot loaded priority fee records {}",
         validators_priority_fee.keys().len()
     );
-    let existing_vote_accounts =
-        get_existing_vote_accounts(psql_client, db_table, snapshot_epoch).await?;
-    let mut updates: u64 = 0;
-
-    for chunk in existing_vote_accounts.chunks(DEFAULT_CHUNK_SIZE) {
-        let mut query = UpdateQueryCombiner::new(
-            db_table.to_string(),
-            "
-            vote_account = u.vote_account,
-            validator_commission = u.validator_commission,
-            total_lamports_transferred = u.total_lamports_transferred,
-            total_epoch_rewards = u.total_epoch_rewards,
-            claimed_epoch_rewards = u.claimed_epoch_rewards,
-            total_epoch_claimants = u.total_epoch_claimants,
-            epoch_active_claimants = u.epoch_active_claimants,
-            epoch_slot = u.epoch_slot,
-            epoch = u.epoch
-            "
-            .to_string(),
-            "u(
-                vote_account,
-                validator_commission,
-                total_lamports_transferred,
-                total_epoch_rewards,
-                claimed_epoch_rewards,
-                total_epoch_claimants,
-                epoch_active_claimants,
-                epoch_slot,
-                epoch
+
+    let columns = [
+        BulkColumn::new("vote_account", Type::TEXT),
+        BulkColumn::new("validator_commission", Type::INT4),
+        BulkColumn::new("total_lamports_transferred", Type::NUMERIC),
+        BulkColumn::new("total_epoch_rewards", Type::NUMERIC),
+        BulkColumn::new("claimed_epoch_rewards", Type::NUMERIC),
+        BulkColumn::new("total_epoch_claimants", Type::INT4),
+        BulkColumn::new("epoch_active_claimants", Type::INT4),
+        BulkColumn::new("epoch_slot", Type::NUMERIC),
+        BulkColumn::new("epoch", Type::NUMERIC),
+        BulkColumn::new("created_at", Type::TIMESTAMPTZ),
+        BulkColumn::new("snapshot_id", Type::INT8),
+    ];
+    let rows = validators_priority_fee.values().map(|v| {
+        let row: Vec<&(dyn ToSql + Sync)> = vec![
+            &v.vote_account,
+            &v.priority_commission,
+            &v.total_lamports_transferred,
+            &v.total_epoch_rewards,
+            &v.claimed_epoch_rewards,
+            &v.total_epoch_claimants,
+            &v.epoch_active_claimants,
+            &snapshot_loaded_at_slot_index,
+            &snapshot_epoch,
+            &snapshot_created_at,
+            &snapshot_id,
+        ];
+        row
+    });
+    let temp_table = format!("{db_table}_snapshot");
+    copy_into_temp_table(transaction, &temp_table, &columns, rows).await?;
+
+    let insert_columns: Vec<_> = columns.iter().map(|column| column.name.as_str()).collect();
+    let stats = merge_from_temp_table(
+        transaction,
+        &temp_table,
+        db_table,
+        &["vote_account", "epoch"],
+        &insert_columns,
+        &[
+            ("validator_commission", "s.validator_commission"),
+            ("total_lamports_transferred", "s.total_lamports_transferred"),
+            ("total_epoch_rewards", "s.total_epoch_rewards"),
+            ("claimed_epoch_rewards", "s.claimed_epoch_rewards"),
+            ("total_epoch_claimants", "s.total_epoch_claimants"),
+            ("epoch_active_claimants", "s.epoch_active_claimants"),
+            ("epoch_slot", "s.epoch_slot"),
+            ("snapshot_id", "s.snapshot_id"),
+        ],
+        &format!(
+            "(
+                {db_table}.validator_commission,
+                {db_table}.total_lamports_transferred,
+                {db_table}.total_epoch_rewards,
+                {db_table}.claimed_epoch_rewards,
+                {db_table}.total_epoch_claimants,
+                {db_table}.epoch_active_claimants,
+                {db_table}.epoch_slot
+            ) IS DISTINCT FROM (
+                s.validator_commission,
+                s.total_lamports_transferred,
+                s.total_epoch_rewards,
+                s.claimed_epoch_rewards,
+                s.total_epoch_claimants,
+                s.epoch_active_claimants,
+                s.epoch_slot
             )"
-            .to_string(),
-            format!("{db_table}.vote_account = u.vote_account AND {db_table}.epoch = u.epoch"),
-        );
-        for row in chunk {
-            let vote_account: &str = row.get("vote_account");
-
-            if let Some(v) = validators_priority_fee.get(vote_account) {
-                let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-                    &v.vote_account,
-                    &v.priority_commission,
-                    &v.total_lamports_transferred,
-                    &v.total_epoch_rewards,
-                    &v.claimed_epoch_rewards,
-                    &v.total_epoch_claimants,
-                    &v.epoch_active_claimants,
-                    &snapshot_loaded_at_slot_index,
-                    &v.epoch,
-                    &snapshot_created_at,
-                ];
-                query.add(
-                    &mut params,
-                    HashMap::from_iter([
-                        (0, "TEXT".into()),                     // vote_account
-                        (1, "INTEGER".into()),                  // validator_commission
-                        (2, "NUMERIC".into()),                  // total_lamports_transferred
-                        (3, "NUMERIC".into()),                  // total_epoch_rewards
-                        (4, "NUMERIC".into()),                  // claimed_epoch_rewards
-                        (5, "INTEGER".into()),                  // total_epoch_claimants
-                        (6, "INTEGER".into()),                  // epoch_active_claimants
-                        (7, "NUMERIC".into()),                  // snapshot_loaded_at_slot_index
-                        (8, "NUMERIC".into()),                  // epoch
-                        (9, "TIMESTAMP WITH TIME ZONE".into()), // snapshot_created_at
-                    ]),
-                );
-                updated_identities.insert(vote_account.to_string());
-            }
-        }
-        updates += query.execute(psql_client).await?.unwrap_or(0);
-        info!(
-            "Trying to update {} previously existing priority fee records. SQL updated records: {}",
-            updated_identities.len(),
-            updates
-        );
-    }
-    let validators_priority_fee_executions: Vec<_> = validators_priority_fee
-        .into_iter()
-        .filter(|(vote_account, _)| !updated_identities.contains(vote_account))
-        .collect();
-    let mut insertions = 0;
-
-    for chunk in validators_priority_fee_executions.chunks(DEFAULT_CHUNK_SIZE) {
-        let mut query = InsertQueryCombiner::new(
-            db_table.to_string(),
-            "
-        vote_account,
-        validator_commission,
-        total_lamports_transferred,
-        total_epoch_rewards,
-        claimed_epoch_rewards,
-        total_epoch_claimants,
-        epoch_active_claimants,
-        epoch_slot,
-        epoch,
-        created_at
-        "
-            .to_string(),
-        );
-
-        for (vote_account, v) in chunk {
-            if updated_identities.contains(vote_account) {
-                continue;
-            }
-            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-                &v.vote_account,
-                &v.priority_commission,
-                &v.total_lamports_transferred,
-                &v.total_epoch_rewards,
-                &v.claimed_epoch_rewards,
-                &v.total_epoch_claimants,
-                &v.epoch_active_claimants,
-                &snapshot_loaded_at_slot_index,
-                &snapshot_epoch,
-                &snapshot_created_at,
-            ];
-            query.add(&mut params);
-        }
-        insertions += query.execute(psql_client).await?.unwrap_or(0);
-        info!("Inserted new new priority fee records {insertions}");
-    }
+        ),
+    )
+    .await?;
 
-    info!("Stored priority fee snapshot: {updates} updated, {insertions} inserted");
+    info!(
+        "Stored priority fee snapshot: {} updated, {} inserted, {} unchanged",
+        stats.updated, stats.inserted, stats.skipped
+    );
 
-    Ok(())
+    let mut report = StoreReport::default();
+    *report.table(db_table) = stats;
+    Ok(report)
 }
 
 async fn get_last_validator_info<T, F>(
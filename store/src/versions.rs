@@ -1,12 +1,13 @@
-use crate::utils::*;
+use crate::bulk::{copy_into_temp_table, BulkColumn};
+use crate::report::{finish_transaction, StoreReport};
+use crate::snapshots::{archive_snapshot, read_snapshot_file, RawSnapshot};
 use chrono::{DateTime, Utc};
 use collect::validators_performance::ValidatorsPerformanceSnapshot;
 use log::info;
 use rust_decimal::prelude::*;
-use serde_yaml;
-use std::collections::HashSet;
 use structopt::StructOpt;
-use tokio_postgres::{types::ToSql, Client};
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, Transaction};
 
 #[derive(Debug, StructOpt)]
 pub struct StoreVersionsParams {
@@ -17,64 +18,105 @@ pub struct StoreVersionsParams {
 pub async fn store_versions(
     params: StoreVersionsParams,
     psql_client: &mut Client,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
-    info!("Storing versions...");
+    let (snapshot, raw): (ValidatorsPerformanceSnapshot, _) =
+        read_snapshot_file(params.snapshot_path)?;
+
+    let transaction = psql_client.transaction().await?;
+    let report = store_versions_snapshot(snapshot, &raw, &transaction).await?;
+    finish_transaction(transaction, &report, dry_run).await
+}
+
+/// A record is stored only when the version or client differs from the latest stored one or the epoch changed.
+pub async fn store_versions_snapshot(
+    snapshot: ValidatorsPerformanceSnapshot,
+    raw: &RawSnapshot,
+    transaction: &Transaction<'_>,
+) -> anyhow::Result<StoreReport> {
+    info!("Storing version...");
+
+    let mut report = StoreReport::default();
+    let snapshot_id = archive_snapshot(transaction, &snapshot, raw, &mut report).await?;
 
-    let snapshot_file = std::fs::File::open(params.snapshot_path)?;
-    let snapshot: ValidatorsPerformanceSnapshot = serde_yaml::from_reader(snapshot_file)?;
     let snapshot_epoch_slot: Decimal = snapshot.epoch_slot.into();
     let snapshot_epoch: Decimal = snapshot.epoch.into();
     let snapshot_created_at: DateTime<Utc> = snapshot.created_at.parse().unwrap();
 
     info!("Loaded the snapshot");
 
-    let mut skipped_vote_accounts: HashSet<String> = Default::default();
-
-    for row in psql_client
-        .query(
-            "
-        SELECT DISTINCT ON (vote_account)
-            vote_account,
-            version,
-            epoch
-        FROM versions
-        ORDER BY vote_account, created_at DESC
-    ",
-            &[],
-        )
-        .await?
-    {
-        let vote_account: &str = row.get("vote_account");
-        let version: Option<String> = row.get("version");
-        let epoch: Decimal = row.get("epoch");
-
-        if let Some(validator_snapshot) = snapshot.validators.get(vote_account) {
-            if epoch == snapshot_epoch && version == validator_snapshot.version {
-                skipped_vote_accounts.insert(vote_account.to_string());
-            }
-        }
-    }
-
-    let mut query = InsertQueryCombiner::new(
-        "versions".to_string(),
-        "vote_account, version, epoch_slot, epoch, created_at".to_string(),
-    );
-
-    for (vote_account, v) in snapshot.validators.iter() {
-        if !skipped_vote_accounts.contains(vote_account) {
-            let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+    let clients: Vec<_> = snapshot
+        .validators
+        .iter()
+        .map(|(vote_account, v)| {
+            (
                 vote_account,
                 &v.version,
+                v.node_client().map(|client| client.to_string()),
+                v.feature_set.map(i64::from),
+            )
+        })
+        .collect();
+    let rows = clients
+        .iter()
+        .map(|(vote_account, version, client, feature_set)| {
+            let row: Vec<&(dyn ToSql + Sync)> = vec![
+                vote_account,
+                version,
+                client,
+                feature_set,
                 &snapshot_epoch_slot,
                 &snapshot_epoch,
                 &snapshot_created_at,
+                &snapshot_id,
             ];
-            query.add(&mut params);
-        }
-    }
-    let insertions = query.execute(psql_client).await?;
+            row
+        });
+    let loaded = copy_into_temp_table(
+        transaction,
+        "versions_snapshot",
+        &[
+            BulkColumn::new("vote_account", Type::TEXT),
+            BulkColumn::new("version", Type::TEXT),
+            BulkColumn::new("client", Type::TEXT),
+            BulkColumn::new("feature_set", Type::INT8),
+            BulkColumn::new("epoch_slot", Type::NUMERIC),
+            BulkColumn::new("epoch", Type::NUMERIC),
+            BulkColumn::new("created_at", Type::TIMESTAMPTZ),
+            BulkColumn::new("snapshot_id", Type::INT8),
+        ],
+        rows,
+    )
+    .await?;
+
+    let insertions = transaction
+        .execute(
+            "
+        WITH latest AS (
+            SELECT DISTINCT ON (vote_account)
+                vote_account,
+                version,
+                client,
+                epoch
+            FROM versions
+            WHERE vote_account IN (SELECT vote_account FROM versions_snapshot)
+            ORDER BY vote_account, created_at DESC
+        )
+        INSERT INTO versions (vote_account, version, client, feature_set, epoch_slot, epoch, created_at, snapshot_id)
+        SELECT s.vote_account, s.version, s.client, s.feature_set, s.epoch_slot, s.epoch, s.created_at, s.snapshot_id
+        FROM versions_snapshot s
+        LEFT JOIN latest ON latest.vote_account = s.vote_account
+        WHERE latest.vote_account IS NULL OR latest.epoch <> s.epoch OR latest.version IS DISTINCT FROM s.version OR latest.client IS DISTINCT FROM s.client
+    ",
+            &[],
+        )
+        .await?;
+
+    let stats = report.table("versions");
+    stats.inserted = insertions;
+    stats.skipped = loaded - insertions;
 
-    info!("Stored {} version changes", insertions.unwrap_or(0));
+    info!("Stored {insertions} version changes");
 
-    Ok(())
+    Ok(report)
 }
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::*;
+use tokio_postgres::Client;
+
+/// The last changes of the tables the API caches, cheap to query through indexes.
+/// The cached data is outdated when the watermarks of its tables moved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Watermarks {
+    /// Last epoch of `cluster_info`, the cached data covers the epochs before it
+    pub last_epoch: Option<u64>,
+    /// Last closed epoch in `epochs`
+    pub last_closed_epoch: Option<u64>,
+    pub validators_updated_at: Option<DateTime<Utc>>,
+    pub operators_updated_at: Option<DateTime<Utc>>,
+    pub last_commission_id: Option<i64>,
+    pub last_version_id: Option<i64>,
+    pub last_uptime_id: Option<i64>,
+    pub uptimes_end_at: Option<DateTime<Utc>>,
+    pub last_scoring_run_id: Option<i64>,
+    pub scoring_runs: i64,
+    pub last_mev_id: Option<i64>,
+    pub last_jito_priority_fee_id: Option<i64>,
+}
+
+pub async fn load_watermarks(psql_client: &Client) -> anyhow::Result<Watermarks> {
+    let row = psql_client
+        .query_one(
+            "SELECT
+                (SELECT MAX(epoch) FROM cluster_info) last_epoch,
+                (SELECT MAX(epoch) FROM epochs) last_closed_epoch,
+                (SELECT MAX(updated_at) FROM validators) validators_updated_at,
+                (SELECT MAX(updated_at) FROM validator_operators) operators_updated_at,
+                (SELECT MAX(id) FROM commissions) last_commission_id,
+                (SELECT MAX(id) FROM versions) last_version_id,
+                (SELECT MAX(id) FROM uptimes) last_uptime_id,
+                (SELECT MAX(end_at) FROM uptimes) uptimes_end_at,
+                (SELECT MAX(scoring_run_id) FROM scoring_runs) last_scoring_run_id,
+                (SELECT COUNT(*) FROM scoring_runs) scoring_runs,
+                (SELECT MAX(id) FROM mev) last_mev_id,
+                (SELECT MAX(id) FROM jito_priority_fee) last_jito_priority_fee_id",
+            &[],
+        )
+        .await?;
+
+    Ok(Watermarks {
+        last_epoch: row
+            .get::<_, Option<Decimal>>("last_epoch")
+            .map(u64::try_from)
+            .transpose()?,
+        last_closed_epoch: row
+            .get::<_, Option<Decimal>>("last_closed_epoch")
+            .map(u64::try_from)
+            .transpose()?,
+        validators_updated_at: row.get("validators_updated_at"),
+        operators_updated_at: row.get("operators_updated_at"),
+        last_commission_id: row.get("last_commission_id"),
+        last_version_id: row.get("last_version_id"),
+        last_uptime_id: row.get("last_uptime_id"),
+        uptimes_end_at: row.get("uptimes_end_at"),
+        last_scoring_run_id: row.get("last_scoring_run_id"),
+        scoring_runs: row.get("scoring_runs"),
+        last_mev_id: row.get("last_mev_id"),
+        last_jito_priority_fee_id: row.get("last_jito_priority_fee_id"),
+    })
+}